@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+
+use crate::{move_input::MoveInput, pieces::*};
+
+/// Height of the arc pieces travel in when hopping
+const HOP_HEIGHT: f32 = 1.;
+/// Shortest time any animation takes, so short moves don't look like a jump
+const MIN_DURATION: f32 = 0.15;
+/// Slowest and fastest speeds selectable with the - and + keys, in squares per second
+const MIN_SPEED: f32 = 1.;
+const MAX_SPEED: f32 = 24.;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the share of time passed to the share of the way travelled
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            Easing::Linear => Easing::EaseIn,
+            Easing::EaseIn => Easing::EaseOut,
+            Easing::EaseOut => Easing::EaseInOut,
+            Easing::EaseInOut => Easing::Linear,
+        }
+    }
+}
+
+pub struct AnimationSettings {
+    // Squares travelled per second
+    pub speed: f32,
+    pub easing: Easing,
+    // Whether every piece hops over the board, knights always do
+    pub hop_all_pieces: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            speed: 6.,
+            easing: Easing::EaseInOut,
+            hop_all_pieces: false,
+        }
+    }
+}
+
+/// Whether any piece is still on its way, moves aren't accepted until they're done
+#[derive(Default)]
+pub struct Animating(pub bool);
+
+/// Moves an entity from one transform to another over time
+pub struct Animation {
+    from: Transform,
+    to: Transform,
+    elapsed: f32,
+    duration: f32,
+    hop_height: f32,
+}
+
+impl Animation {
+    pub fn new(from: Transform, to: Transform, settings: &AnimationSettings, hop: bool) -> Self {
+        let distance = from.translation.distance(to.translation);
+        Self {
+            from,
+            to,
+            elapsed: 0.,
+            duration: (distance / settings.speed).max(MIN_DURATION),
+            hop_height: if hop { HOP_HEIGHT } else { 0. },
+        }
+    }
+}
+
+pub struct AnimationPlugin;
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AnimationSettings>()
+            .init_resource::<Animating>()
+            .add_system(animation_hotkeys.system())
+            .add_system(
+                start_piece_animations
+                    .system()
+                    .label("start_piece_animations"),
+            )
+            .add_system(
+                animate
+                    .system()
+                    .label("animate")
+                    .after("start_piece_animations"),
+            )
+            .add_system(update_animating.system().after("animate"));
+    }
+}
+
+/// + and - change the speed, E cycles the easing curves and H makes every piece hop
+fn animation_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut settings: ResMut<AnimationSettings>,
+) {
+    // The keys are part of the move being typed
+    if move_input.active {
+        return;
+    }
+
+    for key in keyboard_input.get_just_pressed() {
+        match key {
+            KeyCode::Equals | KeyCode::NumpadAdd => {
+                settings.speed = (settings.speed * 2.).min(MAX_SPEED)
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                settings.speed = (settings.speed / 2.).max(MIN_SPEED)
+            }
+            KeyCode::E => settings.easing = settings.easing.next(),
+            KeyCode::H => settings.hop_all_pieces = !settings.hop_all_pieces,
+            _ => (),
+        }
+    }
+}
+
+/// Send pieces whose square changed on their way there
+fn start_piece_animations(
+    mut commands: Commands,
+    settings: Res<AnimationSettings>,
+    query: Query<(Entity, &Transform, &Piece, Option<&Animation>), Without<Dragged>>,
+) {
+    for (entity, transform, piece, animation) in query.iter() {
+        let target = Vec3::new(piece.x as f32, 0., piece.y as f32);
+        let is_heading_there = match animation {
+            Some(animation) => animation.to.translation == target,
+            None => transform.translation == target,
+        };
+        if is_heading_there {
+            continue;
+        }
+
+        let hop = settings.hop_all_pieces || piece.piece_type == PieceType::Knight;
+        commands.entity(entity).insert(Animation::new(
+            *transform,
+            Transform {
+                translation: target,
+                ..*transform
+            },
+            &settings,
+            hop,
+        ));
+    }
+}
+
+fn animate(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut query: Query<(Entity, &mut Transform, &mut Animation), Without<Dragged>>,
+) {
+    for (entity, mut transform, mut animation) in query.iter_mut() {
+        animation.elapsed += time.delta_seconds();
+        let t = (animation.elapsed / animation.duration).min(1.);
+        let progress = settings.easing.apply(t);
+
+        // The hop peaks halfway through the time
+        let hop = animation.hop_height * 4. * t * (1. - t);
+        transform.translation = animation
+            .from
+            .translation
+            .lerp(animation.to.translation, progress)
+            + Vec3::Y * hop;
+        transform.scale = animation.from.scale.lerp(animation.to.scale, progress);
+
+        if t >= 1. {
+            *transform = animation.to;
+            commands.entity(entity).remove::<Animation>();
+        }
+    }
+}
+
+fn update_animating(mut animating: ResMut<Animating>, query: Query<(), With<Animation>>) {
+    let is_animating = query.iter().next().is_some();
+    if animating.0 != is_animating {
+        animating.0 = is_animating;
+    }
+}
@@ -0,0 +1,61 @@
+use std::{env, process};
+
+use rust_chess::{network::DEFAULT_PORT, server::Server};
+
+const USAGE: &str = "\
+Hosts games between players connecting with --server, pairing those who want
+the same time control, and streams them to anyone connecting with --watch.
+Bots and web pages can play over WebSocket on the same port, as described in
+docs/protocol.md.
+
+Usage: server [options]
+
+Options:
+  --port <n>    Port to listen on [default: 7878]";
+
+fn main() {
+    let port = match parse_args(env::args().skip(1)) {
+        Ok(Some(port)) => port,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match Server::start(port) {
+        Ok(server) => {
+            println!("Listening on port {}", server.local_address.port());
+            server.wait();
+        }
+        Err(error) => {
+            eprintln!("Can't listen on port {}: {}", port, error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Returns None when help was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<u16>, String> {
+    let mut port = DEFAULT_PORT;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--port" => {
+                port = value
+                    .parse()
+                    .map_err(|_| format!("{} must be a port number", arg))?
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(Some(port))
+}
@@ -0,0 +1,218 @@
+use std::{
+    convert::TryFrom,
+    env,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    process,
+};
+
+use rust_chess::{database::today, openings::Openings, profiles::ProfileBook, tournament::*};
+
+const USAGE: &str = "\
+Plays games between two engines and reports how the first one scored
+
+Usage: tournament --engine <spec> --engine <spec> [options]
+
+Engines:
+  builtin[,depth=<plies>][,quiescence=on|off][,name=<name>]
+  uci=<command>[,option.<name>=<value>...][,name=<name>]
+
+Options:
+  --games <n>           Games to play [default: 100]
+  --concurrency <n>     Games played at the same time [default: 1]
+  --tc <base>[+<inc>]   Seconds per player and per move [default: 10+0.1]
+  --openings <file>     Start positions from an .epd or .pgn file, each
+                        played twice with colors reversed
+  --max-moves <n>       Adjudicate a draw after this many moves
+  --draw <rule>         Adjudicate a draw when both engines' scores stay
+                        close to 0, e.g. movenumber=40,score=10,count=8
+  --resign <rule>       Adjudicate a loss when an engine's score stays
+                        this low, e.g. score=600,count=4
+  --sprt <test>         Stop once the test is decided, e.g.
+                        elo0=0,elo1=5,alpha=0.05,beta=0.05
+  --pgn <file>          Write the games to a PGN file
+  --profiles <file>     Rate the engines' profiles in this profile file,
+                        such as the game's profiles/profiles.ron";
+
+fn main() {
+    let config = match parse_args(env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(&config) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+struct Options {
+    tournament: TournamentConfig,
+    pgn: Option<String>,
+    profiles: Option<String>,
+}
+
+/// Returns None when help was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut engines = Vec::new();
+    let mut games = 100;
+    let mut concurrency = 1;
+    let mut time_control: TimeControl = "10+0.1".parse()?;
+    let mut openings = Vec::new();
+    let mut adjudication = Adjudication::default();
+    let mut sprt = None;
+    let mut pgn = None;
+    let mut profiles = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{} must be a number", arg))
+        };
+        match arg.as_str() {
+            "--engine" => engines.push(value.parse::<EngineSpec>()?),
+            "--games" => games = number()?,
+            "--concurrency" => concurrency = number()?,
+            "--tc" => time_control = value.parse()?,
+            "--openings" => openings = load_openings(Path::new(&value))?,
+            "--max-moves" => adjudication.max_moves = Some(number()? as u32),
+            "--draw" => adjudication.draw = Some(value.parse()?),
+            "--resign" => adjudication.resign = Some(value.parse()?),
+            "--sprt" => sprt = Some(value.parse()?),
+            "--pgn" => pgn = Some(value),
+            "--profiles" => profiles = Some(value),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    let engines = match <[EngineSpec; 2]>::try_from(engines) {
+        Ok(engines) => engines,
+        Err(_) => return Err("Exactly two engines are needed".to_string()),
+    };
+    Ok(Some(Options {
+        tournament: TournamentConfig {
+            engines,
+            games,
+            concurrency,
+            openings,
+            time_control,
+            adjudication,
+            sprt,
+        },
+        pgn,
+        profiles,
+    }))
+}
+
+fn run(options: &Options) -> io::Result<()> {
+    let config = &options.tournament;
+    let mut pgn = match &options.pgn {
+        Some(path) => Some(File::create(path)?),
+        None => None,
+    };
+    let (first, second) = (&config.engines[0].name, &config.engines[1].name);
+    let event = format!("{} vs {}", first, second);
+    let mut pgn_error = None;
+    let mut profiles = match &options.profiles {
+        Some(path) => Some(
+            ProfileBook::open(Path::new(path))
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+        ),
+        None => None,
+    };
+    let mut profile_error = None;
+    // Names the opening of each game in its PGN
+    let eco = Openings::default();
+
+    let standings = run_tournament(config, |game, standings| {
+        let record = &game.record;
+        println!(
+            "Game {} ({} vs {}): {} {{{}}}",
+            game.index + 1,
+            record.white,
+            record.black,
+            record.result.pgn(),
+            record.reason
+        );
+        println!("Score of {}: {}", event, standings.score);
+
+        if let Some(file) = pgn.as_mut() {
+            if let Err(error) =
+                file.write_all(record.to_pgn(&event, game.index + 1, &eco).as_bytes())
+            {
+                pgn_error.get_or_insert(error);
+            }
+        }
+        if let Some(book) = profiles.as_mut() {
+            let rated = book
+                .record_game((&record.white, &record.black), record.result, &today())
+                .and_then(|_| book.write());
+            if let Err(error) = rated {
+                profile_error.get_or_insert(error);
+            }
+        }
+    })?;
+
+    let score = &standings.score;
+    println!();
+    println!("Score of {}: {}", event, score);
+    println!(
+        "Elo difference: {:.1} +/- {:.1}, LOS: {:.1}%",
+        score.elo(),
+        score.elo_error(),
+        score.los() * 100.
+    );
+    if let (Some(sprt), Some(status)) = (config.sprt, standings.sprt) {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match status {
+            SprtStatus::Continue => "no conclusion yet",
+            SprtStatus::AcceptH0 => "H0 accepted",
+            SprtStatus::AcceptH1 => "H1 accepted",
+        };
+        println!(
+            "SPRT: llr {:.2} ({:.2}, {:.2}) [{}, {}], {}",
+            sprt.llr(score),
+            lower,
+            upper,
+            sprt.elo0,
+            sprt.elo1,
+            verdict
+        );
+    }
+
+    if let Some(book) = &profiles {
+        for name in [first, second].iter() {
+            if let Some(profile) = book.get(name) {
+                println!(
+                    "Rating of {}: {:.0} +/- {:.0}",
+                    name,
+                    profile.rating.rating,
+                    profile.rating.deviation * 2.
+                );
+            }
+        }
+    }
+    if let Some(error) = profile_error {
+        eprintln!("Couldn't rate the games: {}", error);
+    }
+
+    match pgn_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -1,10 +1,15 @@
-use bevy::{
-    app::{AppExit, Events},
-    prelude::*,
-};
+use bevy::prelude::*;
 use bevy_mod_picking::*;
 
-use crate::pieces::*;
+use crate::{
+    animation::Animating,
+    check::Check,
+    game::{DrawOffer, GameOver, PlayerKind, Players},
+    headless::is_headless,
+    history::{HistoryView, MoveHistory, MoveRecord},
+    pieces::*,
+    theme::{self, color, Themes},
+};
 
 pub struct PlayerTurn(pub PieceColor);
 
@@ -24,12 +29,14 @@ impl Default for PlayerTurn {
 }
 
 #[derive(Default)]
-struct SelectedSquare {
-    entity: Option<Entity>,
+pub struct SelectedSquare {
+    pub entity: Option<Entity>,
 }
 #[derive(Default)]
-struct SelectedPiece {
-    entity: Option<Entity>,
+pub struct SelectedPiece {
+    pub entity: Option<Entity>,
+    // Legal moves of the selected piece
+    pub moves: Vec<ChessMove>,
 }
 
 pub struct BoardPlugin;
@@ -38,25 +45,9 @@ impl Plugin for BoardPlugin {
         app.init_resource::<SelectedSquare>()
             .init_resource::<SelectedPiece>()
             .init_resource::<PlayerTurn>()
+            .init_resource::<BoardState>()
             .add_event::<ResetSelectedEvent>()
             .add_event::<MoveEvent>()
-            .add_startup_system(create_board.system())
-            .add_system(color_squares.system())
-            // .add_system_to_stage(CoreStage::PostUpdate, print_events.system())
-            .add_system_to_stage(
-                CoreStage::PostUpdate,
-                select_square
-                    .system()
-                    .label("select_square")
-                    .after(PickingSystem::Selection),
-            )
-            .add_system_to_stage(
-                CoreStage::PostUpdate,
-                validate_move
-                    .system()
-                    .label("validate_move")
-                    .after("select_square"),
-            )
             .add_system_to_stage(
                 CoreStage::PostUpdate,
                 move_piece
@@ -73,9 +64,9 @@ impl Plugin for BoardPlugin {
             )
             .add_system_to_stage(
                 CoreStage::PostUpdate,
-                despawn_taken_pieces
+                capture_taken_pieces
                     .system()
-                    .label("despawn_taken_pieces")
+                    .label("capture_taken_pieces")
                     .after("move_piece"),
             )
             .add_system_to_stage(
@@ -83,8 +74,36 @@ impl Plugin for BoardPlugin {
                 reset_selected
                     .system()
                     .label("reset_selected")
-                    .after("despawn_taken_pieces"),
+                    .after("capture_taken_pieces"),
             );
+
+        // Without a window there are no clicks, so nothing selects squares
+        // before the move is validated
+        if is_headless(app) {
+            app.add_startup_system(create_squares.system())
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    validate_move.system().label("validate_move"),
+                );
+        } else {
+            app.add_startup_system(create_board.system())
+                .add_system(color_squares.system())
+                // .add_system_to_stage(CoreStage::PostUpdate, print_events.system())
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    select_square
+                        .system()
+                        .label("select_square")
+                        .after(PickingSystem::Selection),
+                )
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    validate_move
+                        .system()
+                        .label("validate_move")
+                        .after("select_square"),
+                );
+        }
     }
 }
 #[derive(PartialEq)]
@@ -98,53 +117,94 @@ pub struct Square {
     pub color: SquareColor,
 }
 
+impl Square {
+    pub fn new(x: u8, y: u8) -> Self {
+        let color = if (x + y) % 2 == 1 {
+            SquareColor::Light
+        } else {
+            SquareColor::Dark
+        };
+        Self { x, y, color }
+    }
+}
+
 fn create_board(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    themes: Res<Themes>,
 ) {
     // Add meshes and materials
     let mesh = meshes.add(Mesh::from(shape::Plane { size: 1. }));
-    // let light_material = materials.add(Color::rgb(1., 0.9, 0.9).into());
-    // let dark_material = materials.add(Color::rgb(0., 0.1, 0.1).into());
+    let theme = themes.current();
 
     // Spawn 64 squares
     for x in 0..8 {
         for y in 0..8 {
-            let color = if (x + y + 1) % 2 == 0 {
-                SquareColor::Light
-            } else {
-                SquareColor::Dark
-            };
+            let square = Square::new(x, y);
 
             commands
                 .spawn_bundle(PbrBundle {
                     mesh: mesh.clone(),
                     // Change material according to position for alternating pattern
-                    material: match color {
-                        SquareColor::Dark => materials.add(Color::rgb(0., 0.1, 0.1).into()),
-                        SquareColor::Light => materials.add(Color::rgb(1., 0.9, 0.9).into()),
+                    material: match square.color {
+                        SquareColor::Dark => materials.add(theme::color(theme.dark_square).into()),
+                        SquareColor::Light => {
+                            materials.add(theme::color(theme.light_square).into())
+                        }
                     },
                     transform: Transform::from_translation(Vec3::new(x as f32, 0., y as f32)),
                     ..Default::default()
                 })
                 .insert_bundle(PickableBundle::default())
-                .insert(Square { x, y, color });
+                .insert(square);
         }
     }
 }
 
+/// Spawn the squares without meshes, for running without a renderer
+fn create_squares(mut commands: Commands) {
+    for x in 0..8 {
+        for y in 0..8 {
+            commands.spawn().insert(Square::new(x, y));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn select_square(
     mut selected_square: ResMut<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
+    game_over: Res<GameOver>,
+    history_view: Res<HistoryView>,
+    animating: Res<Animating>,
     mouse_button_inputs: Res<Input<MouseButton>>,
     squares_query: Query<(Entity, &Selection, &Square)>,
+    ui_query: Query<&Interaction, With<Node>>,
 ) {
     // Only run if the left button is pressed
     if !mouse_button_inputs.just_pressed(MouseButton::Left) {
         return;
     }
 
+    // No more moves once the game is over, and past positions are read-only
+    if game_over.0.is_some() || history_view.ply.is_some() {
+        return;
+    }
+
+    // Wait for the last move to finish
+    if animating.0 {
+        return;
+    }
+
+    // The click was meant for the UI, not the board
+    if ui_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
     // Populate selected_square resource with the newly selected square
     if let Some((square_entity, _, _)) = squares_query
         .iter()
@@ -155,13 +215,16 @@ fn select_square(
         // Player clicked outside the board, deselect everything
         selected_square.entity = None;
         selected_piece.entity = None;
+        selected_piece.moves.clear();
     }
 }
 
 fn select_piece(
     selected_square: Res<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
-    turn: ResMut<PlayerTurn>,
+    turn: Res<PlayerTurn>,
+    players: Res<Players>,
+    board_state: Res<BoardState>,
     squares_query: Query<(Entity, &Square)>,
     pieces_query: Query<(Entity, &Piece)>,
 ) {
@@ -173,39 +236,90 @@ fn select_piece(
         return;
     }
 
-    match selected_square.entity {
+    // Only the player sitting at this screen moves their own pieces
+    if players.get(turn.0) != PlayerKind::Human {
+        return;
+    }
+
+    let square = match selected_square
+        .entity
+        .and_then(|entity| squares_query.get(entity).ok())
+    {
+        Some((_square_entity, square)) => square,
         None => return,
-        Some(selected_square_entity) => {
-            if let Ok((_square_entity, square)) = squares_query.get(selected_square_entity) {
-                // Select the piece in the currently selected square
-                if let Some((piece_entity, _piece)) =
-                    pieces_query.iter().find(|(_piece_entity, piece)| {
-                        piece.x == square.x && piece.y == square.y && piece.color == turn.0
-                    })
-                {
-                    // piece_entity is now the entity in the same square
-                    selected_piece.entity = Some(piece_entity);
-                }
-            }
-        }
+    };
+
+    // Select the piece in the currently selected square
+    if let Some((piece_entity, piece)) = pieces_query.iter().find(|(_piece_entity, piece)| {
+        piece.x == square.x && piece.y == square.y && piece.color == turn.0
+    }) {
+        let pieces: Vec<Piece> = pieces_query.iter().map(|(_, piece)| *piece).collect();
+
+        // piece_entity is now the entity in the same square
+        selected_piece.entity = Some(piece_entity);
+        selected_piece.moves = legal_moves(piece, &pieces, &board_state);
     }
 }
 
-struct MoveEvent {
-    piece: Entity,
-    end_position: (u8, u8),
+pub struct MoveEvent {
+    pub piece: Entity,
+    pub chess_move: ChessMove,
 }
 
-fn move_piece(mut event_reader: EventReader<MoveEvent>, mut pieces: Query<&mut Piece>) {
+/// Apply a move to the pieces, record it and pass the turn to the other player
+fn move_piece(
+    mut commands: Commands,
+    mut event_reader: EventReader<MoveEvent>,
+    mut history: ResMut<MoveHistory>,
+    mut board_state: ResMut<BoardState>,
+    mut turn: ResMut<PlayerTurn>,
+    draw_offer: Res<DrawOffer>,
+    mut pieces: Query<(Entity, &mut Piece)>,
+) {
     for event in event_reader.iter() {
-        if let Ok(mut piece) = pieces.get_mut(event.piece) {
-            piece.x = event.end_position.0;
-            piece.y = event.end_position.1;
+        let chess_move = event.chess_move;
+        let piece = if let Ok((_entity, piece)) = pieces.get_mut(event.piece) {
+            *piece
+        } else {
+            continue;
+        };
+
+        let pieces_before: Vec<Piece> = pieces.iter_mut().map(|(_, piece)| *piece).collect();
+        history.moves.push(MoveRecord {
+            color: piece.color,
+            from: chess_move.from,
+            to: chess_move.to,
+            san: san(&chess_move, &pieces_before, &board_state),
+            draw_offered: draw_offer.offered_by == Some(piece.color),
+            position: apply_move(&pieces_before, &chess_move),
+        });
+        board_state.update(&chess_move, &piece);
+
+        for (entity, mut other) in pieces.iter_mut() {
+            if entity == event.piece {
+                other.x = chess_move.to.0;
+                other.y = chess_move.to.1;
+                if let Some(promotion) = chess_move.promotion {
+                    other.piece_type = promotion;
+                    commands.entity(entity).insert(Promoted);
+                }
+            } else if Some((other.x, other.y)) == chess_move.capture {
+                // Mark the piece as taken
+                commands.entity(entity).insert(Taken);
+            } else if let Some((rook_from, rook_to)) = chess_move.castle {
+                if (other.x, other.y) == rook_from {
+                    other.x = rook_to.0;
+                    other.y = rook_to.1;
+                }
+            }
         }
+
+        // Change turn
+        turn.toggle();
     }
 }
 
-struct ResetSelectedEvent;
+pub struct ResetSelectedEvent;
 
 fn reset_selected(
     mut event_reader: EventReader<ResetSelectedEvent>,
@@ -215,120 +329,160 @@ fn reset_selected(
     for _event in event_reader.iter() {
         selected_square.entity = None;
         selected_piece.entity = None;
+        selected_piece.moves.clear();
     }
 }
 
 fn validate_move(
-    mut commands: Commands,
     selected_square: Res<SelectedSquare>,
-    selected_piece: Res<SelectedPiece>,
-    mut turn: ResMut<PlayerTurn>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    turn: Res<PlayerTurn>,
     squares_query: Query<&Square>,
     pieces_query: Query<&Piece>,
-    pieces_entity_query: Query<(Entity, &Piece)>,
-    mut reset_selected_event: ResMut<Events<ResetSelectedEvent>>,
-    mut move_event: ResMut<Events<MoveEvent>>,
+    mut reset_selected_event: EventWriter<ResetSelectedEvent>,
+    mut move_event: EventWriter<MoveEvent>,
 ) {
     if !selected_square.is_changed() {
         return;
     }
 
-    let square_entity = if let Some(entity) = selected_square.entity {
-        entity
-    } else {
-        return;
+    let square = match selected_square
+        .entity
+        .and_then(|entity| squares_query.get(entity).ok())
+    {
+        Some(square) => square,
+        None => return,
     };
 
-    let square = if let Ok(square) = squares_query.get(square_entity) {
-        square
+    let selected_piece_entity = if let Some(entity) = selected_piece.entity {
+        entity
     } else {
         return;
     };
 
-    if let Some(selected_piece_entity) = selected_piece.entity {
-        // find the selected piece
-        let piece = if let Ok(piece) = pieces_query.get(selected_piece_entity) {
-            piece
-        } else {
-            return;
-        };
-
-        if piece.can_reach_position((square.x, square.y), &pieces_query) {
-            // Check if a piece of the opposite color exists in this square and despawn it
-            for (other_entity, other_piece) in pieces_entity_query.iter() {
-                if other_piece.x == square.x
-                    && other_piece.y == square.y
-                    && other_piece.color != piece.color
-                {
-                    // Mark the piece as taken
-                    commands.entity(other_entity).insert(Taken);
-                }
-            }
-
-            // Move piece
-            move_event.send(MoveEvent {
-                piece: selected_piece_entity,
-                end_position: (square.x, square.y),
-            });
-
-            // piece.x = square.x;
-            // piece.y = square.y;
-
-            // Change turn
-            turn.toggle();
-        }
+    // Pawns reaching the last rank become queens when moved by clicking
+    if let Some(chess_move) = selected_piece.moves.iter().find(|chess_move| {
+        chess_move.to == (square.x, square.y)
+            && matches!(chess_move.promotion, None | Some(PieceType::Queen))
+    }) {
+        move_event.send(MoveEvent {
+            piece: selected_piece_entity,
+            chess_move: *chess_move,
+        });
+        reset_selected_event.send(ResetSelectedEvent);
+        return;
+    }
 
+    // Not a legal destination: select another of the player's pieces or deselect
+    selected_piece.entity = None;
+    selected_piece.moves.clear();
+    if !pieces_query
+        .iter()
+        .any(|piece| piece.x == square.x && piece.y == square.y && piece.color == turn.0)
+    {
         reset_selected_event.send(ResetSelectedEvent);
     }
 }
 
-fn despawn_taken_pieces(
-    mut commands: Commands,
-    mut app_exit_events: ResMut<Events<AppExit>>,
-    query: Query<(Entity, &Piece, &Taken)>,
-) {
+fn capture_taken_pieces(mut commands: Commands, query: Query<(Entity, &Piece, &Taken)>) {
     for (entity, piece, _taken) in query.iter() {
-        // If the king is taken, we should exit
-        if piece.piece_type == PieceType::King {
-            println!(
-                "{} won! Thanks for playing!",
-                match piece.color {
-                    PieceColor::White => "Black",
-                    PieceColor::Black => "White",
-                }
-            );
-            app_exit_events.send(AppExit);
-        }
-
-        // Despawn piece and children
-        commands.entity(entity).despawn_recursive();
+        // Keep the model around to show it beside the board
+        commands
+            .entity(entity)
+            .remove::<Piece>()
+            .remove::<Taken>()
+            .insert(Captured {
+                color: piece.color,
+                piece_type: piece.piece_type,
+            });
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn color_squares(
+    time: Res<Time>,
     selected_square: Res<SelectedSquare>,
+    selected_piece: Res<SelectedPiece>,
+    history: Res<MoveHistory>,
+    history_view: Res<HistoryView>,
+    check: Res<Check>,
+    turn: Res<PlayerTurn>,
+    themes: Res<Themes>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    squares_query: Query<(
-        Entity,
-        &Square,
-        &Handle<StandardMaterial>,
-        &Selection,
-        &Hover,
-    )>,
+    squares_query: Query<(Entity, &Square, &Handle<StandardMaterial>, &Hover)>,
+    pieces_query: Query<&Piece>,
 ) {
-    for (entity, square, material_handle, selection, hover) in squares_query.iter() {
+    // When looking at a past position, tint the move that led to it instead
+    let shown_move = match history_view.ply {
+        Some(ply) => history.moves.get(ply),
+        None => history.moves.last(),
+    };
+    let last_move: Vec<(u8, u8)> =
+        shown_move.map_or_else(Vec::new, |record| vec![record.from, record.to]);
+
+    // The king of the player to move is in check
+    let checked_king = if check.is_check && history_view.ply.is_none() {
+        pieces_query
+            .iter()
+            .find(|piece| piece.color == turn.0 && piece.piece_type == PieceType::King)
+            .map(|king| (king.x, king.y))
+    } else {
+        None
+    };
+    let glow = 0.3 + 0.2 * (time.seconds_since_startup() as f32 * 4.).sin();
+    let theme = themes.current();
+
+    for (entity, square, material_handle, hover) in squares_query.iter() {
         // Get the actual material
         let material = materials.get_mut(material_handle).unwrap();
+        let position = (square.x, square.y);
+
+        // Legal moves of the selected piece ending in this square
+        let mut moves = selected_piece
+            .moves
+            .iter()
+            .filter(|chess_move| chess_move.to == position)
+            .peekable();
+        let is_destination = moves.peek().is_some();
+        let (mut is_capture, mut is_castle, mut is_promotion) = (false, false, false);
+        for chess_move in moves {
+            is_capture |= chess_move.capture.is_some();
+            is_castle |= chess_move.castle.is_some();
+            is_promotion |= chess_move.promotion.is_some();
+        }
+        let is_hovered_or_selected = hover.hovered() || Some(entity) == selected_square.entity;
+        let is_checked_king = checked_king == Some(position);
+
+        material.emissive = if is_checked_king && !is_hovered_or_selected {
+            Color::rgb(glow, 0., 0.)
+        } else {
+            Color::BLACK
+        };
 
         // Change the material color
-        material.base_color = if hover.hovered() {
-            Color::rgb(0.8, 0.3, 0.3)
+        material.base_color = color(if hover.hovered() {
+            theme.hover
         } else if Some(entity) == selected_square.entity {
-            Color::rgb(0.9, 0.1, 0.1)
+            theme.selected
+        } else if is_promotion {
+            theme.promotion
+        } else if is_capture {
+            theme.capture
+        } else if is_castle {
+            theme.castle
+        } else if is_destination {
+            theme.destination
+        } else if is_checked_king {
+            theme.check
+        } else if last_move.contains(&position) {
+            match square.color {
+                SquareColor::Light => theme.last_move_light,
+                SquareColor::Dark => theme.last_move_dark,
+            }
         } else if square.color == SquareColor::Light {
-            Color::rgb(1., 0.9, 0.9)
+            theme.light_square
         } else {
-            Color::rgb(0., 0.1, 0.1)
-        };
+            theme.dark_square
+        });
     }
 }
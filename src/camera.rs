@@ -0,0 +1,322 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    render::camera::{Camera, CameraProjection, OrthographicProjection},
+};
+use bevy_mod_picking::PickingCameraBundle;
+
+use crate::{
+    board::PlayerTurn,
+    game::{PlayerKind, Players},
+    move_input::MoveInput,
+    pieces::PieceColor,
+    render_mode::RenderMode,
+};
+
+/// How fast the camera catches up with the chosen view, higher is faster
+const CAMERA_SPEED: f32 = 4.;
+
+/// Radians the camera turns per pixel the mouse moves while orbiting
+const ORBIT_SENSITIVITY: f32 = 0.005;
+/// Board units the camera moves per pixel at distance 1 while panning
+const PAN_SENSITIVITY: f32 = 0.0015;
+/// Share of the distance zoomed per scroll wheel line
+const ZOOM_STEP: f32 = 0.1;
+const MIN_DISTANCE: f32 = 4.;
+const MAX_DISTANCE: f32 = 30.;
+/// Lowest and highest angles the camera can orbit to above the board
+const MIN_PITCH: f32 = 0.1;
+const MAX_PITCH: f32 = FRAC_PI_2;
+/// Half the board units seen from top to bottom in 2D per unit of distance,
+/// so zooming works the same as in 3D
+const ORTHOGRAPHIC_SCALE: f32 = 0.4;
+
+/// Where the camera is looking from, as an orbit around a point on the board
+#[derive(Clone, Copy)]
+pub struct CameraOrbit {
+    pub focus: Vec3,
+    // Angle around the board, 0 looks from the h-file towards the a-file
+    pub yaw: f32,
+    // Angle above the board, a right angle looks straight down
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl CameraOrbit {
+    pub fn transform(&self) -> Transform {
+        let rotation = Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(-self.pitch);
+        Transform {
+            translation: self.focus + rotation * Vec3::Z * self.distance,
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    /// Move a fraction of the way towards `target`, turning the short way around
+    fn approach(&mut self, target: &CameraOrbit, amount: f32) {
+        let mut yaw_difference = (target.yaw - self.yaw) % (2. * PI);
+        if yaw_difference > PI {
+            yaw_difference -= 2. * PI;
+        } else if yaw_difference < -PI {
+            yaw_difference += 2. * PI;
+        }
+
+        self.focus = self.focus.lerp(target.focus, amount);
+        self.yaw += yaw_difference * amount;
+        self.pitch += (target.pitch - self.pitch) * amount;
+        self.distance += (target.distance - self.distance) * amount;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CameraPreset {
+    // Looking over the board from behind the player's pieces
+    Player,
+    TopDown,
+    Side,
+}
+
+/// The view the camera moves to
+pub struct CameraView {
+    pub preset: CameraPreset,
+    // The side shown at the bottom of the screen
+    pub orientation: PieceColor,
+    // Turn the board towards whoever is to move, until flipped by hand
+    pub auto_flip: bool,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            preset: CameraPreset::Player,
+            orientation: PieceColor::White,
+            auto_flip: true,
+        }
+    }
+}
+
+impl CameraView {
+    /// The 2D mode always looks straight down
+    pub fn orbit(&self, render_mode: RenderMode) -> CameraOrbit {
+        // Looking from the lower ranks for White and the higher ones for Black
+        let yaw = match self.orientation {
+            PieceColor::White => -FRAC_PI_2,
+            PieceColor::Black => FRAC_PI_2,
+        };
+        let (yaw, pitch, distance) = match self.preset {
+            CameraPreset::Player => (yaw, 1., 14.),
+            CameraPreset::TopDown => (yaw, FRAC_PI_2, 14.),
+            CameraPreset::Side => (yaw + FRAC_PI_2, 0.6, 14.),
+        };
+        let pitch = match render_mode {
+            RenderMode::ThreeD => pitch,
+            RenderMode::TwoD => FRAC_PI_2,
+        };
+
+        CameraOrbit {
+            focus: Vec3::new(3.5, 0., 3.5),
+            yaw,
+            pitch,
+            distance,
+        }
+    }
+}
+
+/// The orbit the camera is at, on its way to the one of the `CameraView`
+pub struct MainCamera {
+    pub orbit: CameraOrbit,
+    pub target: CameraOrbit,
+}
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CameraView>()
+            .add_system(spawn_camera.system().label("spawn_camera"))
+            .add_system(camera_hotkeys.system().label("camera_hotkeys"))
+            .add_system(auto_flip.system().label("auto_flip"))
+            .add_system(
+                move_camera
+                    .system()
+                    .label("move_camera")
+                    .after("spawn_camera")
+                    .after("camera_hotkeys")
+                    .after("auto_flip"),
+            )
+            .add_system(camera_controls.system().after("move_camera"));
+    }
+}
+
+/// Spawn a perspective camera for 3D or an orthographic one for 2D, replacing
+/// the camera of the other mode
+fn spawn_camera(
+    mut commands: Commands,
+    render_mode: Res<RenderMode>,
+    view: Res<CameraView>,
+    query: Query<(Entity, &MainCamera)>,
+) {
+    if !render_mode.is_changed() {
+        return;
+    }
+
+    let target = view.orbit(*render_mode);
+    // Carry on from where the old camera was
+    let mut orbit = target;
+    for (entity, camera) in query.iter() {
+        orbit = camera.orbit;
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mut entity_commands = match *render_mode {
+        RenderMode::ThreeD => commands.spawn_bundle(PerspectiveCameraBundle {
+            transform: orbit.transform(),
+            ..Default::default()
+        }),
+        RenderMode::TwoD => {
+            let mut bundle = OrthographicCameraBundle::new_3d();
+            bundle.orthographic_projection.scale = orbit.distance * ORTHOGRAPHIC_SCALE;
+            bundle.transform = orbit.transform();
+            commands.spawn_bundle(bundle)
+        }
+    };
+    entity_commands
+        .insert_bundle(PickingCameraBundle::default())
+        .insert(MainCamera { orbit, target });
+}
+
+/// F flips the board, 1 to 4 pick the White, Black, top-down and side views
+/// and R goes back to the current one after orbiting, panning or zooming
+fn camera_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Res<MoveInput>,
+    render_mode: Res<RenderMode>,
+    mut view: ResMut<CameraView>,
+    mut query: Query<&mut MainCamera>,
+) {
+    // The keys are part of the move being typed
+    if move_input.active {
+        return;
+    }
+
+    for key in keyboard_input.get_just_pressed() {
+        match key {
+            KeyCode::F => {
+                view.orientation = view.orientation.opponent();
+                view.auto_flip = false;
+            }
+            KeyCode::Key1 => {
+                view.preset = CameraPreset::Player;
+                view.orientation = PieceColor::White;
+                view.auto_flip = false;
+            }
+            KeyCode::Key2 => {
+                view.preset = CameraPreset::Player;
+                view.orientation = PieceColor::Black;
+                view.auto_flip = false;
+            }
+            KeyCode::Key3 => view.preset = CameraPreset::TopDown,
+            KeyCode::Key4 => view.preset = CameraPreset::Side,
+            KeyCode::R => {
+                for mut camera in query.iter_mut() {
+                    camera.target = view.orbit(*render_mode);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Face the player to move in hot-seat games, or the only human player otherwise
+fn auto_flip(turn: Res<PlayerTurn>, players: Res<Players>, mut view: ResMut<CameraView>) {
+    if !view.auto_flip || !(turn.is_changed() || players.is_changed()) {
+        return;
+    }
+
+    let orientation = match (players.white, players.black) {
+        (PlayerKind::Human, PlayerKind::Human) => turn.0,
+        (_, PlayerKind::Human) => PieceColor::Black,
+        _ => PieceColor::White,
+    };
+    if view.orientation != orientation {
+        view.orientation = orientation;
+    }
+}
+
+fn move_camera(
+    time: Res<Time>,
+    render_mode: Res<RenderMode>,
+    view: Res<CameraView>,
+    mut query: Query<(
+        &mut MainCamera,
+        &mut Transform,
+        &mut Camera,
+        Option<&mut OrthographicProjection>,
+    )>,
+) {
+    for (mut camera, mut transform, mut projection_camera, projection) in query.iter_mut() {
+        if view.is_changed() {
+            camera.target = view.orbit(*render_mode);
+        }
+
+        let target = camera.target;
+        let amount = (CAMERA_SPEED * time.delta_seconds()).min(1.);
+        camera.orbit.approach(&target, amount);
+        *transform = camera.orbit.transform();
+
+        // Moving an orthographic camera closer doesn't make the board bigger
+        if let Some(mut projection) = projection {
+            let scale = camera.orbit.distance * ORTHOGRAPHIC_SCALE;
+            if projection.scale != scale {
+                projection.scale = scale;
+                projection_camera.projection_matrix = projection.get_projection_matrix();
+            }
+        }
+    }
+}
+
+/// Right-drag orbits around the board, middle-drag pans and the scroll wheel zooms.
+/// Left clicks are left to piece selection. The 2D board can't be orbited.
+fn camera_controls(
+    render_mode: Res<RenderMode>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    mut query: Query<&mut MainCamera>,
+    ui_query: Query<&Interaction, With<Node>>,
+) {
+    let motion = motion_events
+        .iter()
+        .fold(Vec2::ZERO, |motion, event| motion + event.delta);
+    let scroll: f32 = wheel_events.iter().map(|event| event.y).sum();
+
+    // Scrolling over the UI is meant for the UI
+    let over_ui = ui_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+
+    for mut camera in query.iter_mut() {
+        let target = &mut camera.target;
+
+        if mouse_button_inputs.pressed(MouseButton::Right) && *render_mode == RenderMode::ThreeD {
+            target.yaw -= motion.x * ORBIT_SENSITIVITY;
+            target.pitch =
+                (target.pitch + motion.y * ORBIT_SENSITIVITY).clamp(MIN_PITCH, MAX_PITCH);
+        }
+
+        if mouse_button_inputs.pressed(MouseButton::Middle) {
+            // Move along the board, in the directions the screen is facing
+            let rotation = Quat::from_rotation_y(target.yaw);
+            let right = rotation * Vec3::X;
+            let up = rotation * -Vec3::Z;
+            let amount = PAN_SENSITIVITY * target.distance;
+            target.focus += (up * motion.y - right * motion.x) * amount;
+        }
+
+        if scroll != 0. && !over_ui {
+            target.distance =
+                (target.distance * (1. - scroll * ZOOM_STEP)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::{
+    animation::{Animation, AnimationSettings},
+    pieces::*,
+};
+
+/// Distance between captured pieces lined up beside the board
+const CAPTURED_SPACING: f32 = 0.5;
+/// Size of captured pieces compared to the ones on the board
+const CAPTURED_SCALE: f32 = 0.5;
+
+/// Material lead of the side that is ahead, if any
+#[derive(Default, PartialEq)]
+pub struct MaterialBalance {
+    pub leader: Option<(PieceColor, i32)>,
+}
+
+/// Shows taken pieces next to the board and keeps track of the material balance
+pub struct CapturedPlugin;
+impl Plugin for CapturedPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MaterialBalance>()
+            .add_system(line_up_captured_pieces.system())
+            .add_system(update_material_balance.system());
+    }
+}
+
+/// Line up each side's lost pieces along its own side of the board, most valuable first
+fn line_up_captured_pieces(
+    mut commands: Commands,
+    settings: Res<AnimationSettings>,
+    added_query: Query<(), Added<Captured>>,
+    captured_query: Query<(Entity, &Captured, &Transform)>,
+) {
+    if added_query.iter().next().is_none() {
+        return;
+    }
+
+    for color in [PieceColor::White, PieceColor::Black].iter() {
+        let mut captured: Vec<(Entity, i32)> = captured_query
+            .iter()
+            .filter(|(_, captured, _)| captured.color == *color)
+            .map(|(entity, captured, _)| (entity, captured.piece_type.value()))
+            .collect();
+        captured.sort_by_key(|(entity, value)| (-value, *entity));
+
+        for (index, (entity, _value)) in captured.iter().enumerate() {
+            let offset = index as f32 * CAPTURED_SPACING;
+            let translation = match color {
+                PieceColor::White => Vec3::new(offset, 0., -1.5),
+                PieceColor::Black => Vec3::new(7. - offset, 0., 8.5),
+            };
+            let to = Transform {
+                translation,
+                scale: Vec3::splat(CAPTURED_SCALE),
+                ..Default::default()
+            };
+            if let Ok((_, _, transform)) = captured_query.get(*entity) {
+                if *transform != to {
+                    // Fly over the board into the tray
+                    commands
+                        .entity(*entity)
+                        .insert(Animation::new(*transform, to, &settings, true));
+                }
+            }
+        }
+    }
+}
+
+/// Compare the material on the board, which also accounts for promotions
+fn update_material_balance(mut balance: ResMut<MaterialBalance>, pieces_query: Query<&Piece>) {
+    let score: i32 = pieces_query
+        .iter()
+        .map(|piece| match piece.color {
+            PieceColor::White => piece.piece_type.value(),
+            PieceColor::Black => -piece.piece_type.value(),
+        })
+        .sum();
+
+    let leader = match score {
+        0 => None,
+        score if score > 0 => Some((PieceColor::White, score)),
+        score => Some((PieceColor::Black, -score)),
+    };
+
+    // Only touch the resource when the balance changes, so the UI can react to it
+    if balance.leader != leader {
+        balance.leader = leader;
+    }
+}
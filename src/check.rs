@@ -2,23 +2,17 @@ use bevy::prelude::*;
 
 use crate::{
     board::PlayerTurn,
-    pieces::{can_any_piece_reach_king, Piece, PieceType},
+    game::{GameOutcome, GameOver},
+    history::MoveHistory,
+    pieces::{all_legal_moves, is_in_check, BoardState, Piece},
 };
 
-struct Check {
+#[derive(Default)]
+pub struct Check {
     pub is_check: bool,
     pub is_checkmate: bool,
 }
 
-impl Default for Check {
-    fn default() -> Self {
-        Self {
-            is_check: false,
-            is_checkmate: false,
-        }
-    }
-}
-
 pub struct CheckPlugin;
 impl Plugin for CheckPlugin {
     fn build(&self, app: &mut AppBuilder) {
@@ -27,27 +21,36 @@ impl Plugin for CheckPlugin {
     }
 }
 
-fn check_updater(mut check: ResMut<Check>, turn: Res<PlayerTurn>, pieces_query: Query<&Piece>) {
+fn check_updater(
+    mut check: ResMut<Check>,
+    mut game_over: ResMut<GameOver>,
+    turn: Res<PlayerTurn>,
+    board_state: Res<BoardState>,
+    history: Res<MoveHistory>,
+    pieces_query: Query<&Piece>,
+) {
     if !turn.is_changed() {
         return;
     }
 
-    let pieces: Vec<Piece> = pieces_query
-        .iter()
-        .collect::<Vec<&Piece>>()
-        .into_iter()
-        .cloned()
-        .collect();
+    let pieces: Vec<Piece> = pieces_query.iter().cloned().collect();
 
-    // find the current player's king
-    if let Some(king) = pieces_query
-        .iter()
-        .find(|piece| piece.color == turn.0 && piece.piece_type == PieceType::King)
-    {
-        // are any pieces attacking the king ?
-        if can_any_piece_reach_king(king, &pieces_query) {
-            println!("check!");
-            check.is_check = true;
+    // are any pieces attacking the current player's king?
+    check.is_check = is_in_check(turn.0, &pieces);
+    check.is_checkmate =
+        check.is_check && all_legal_moves(turn.0, &pieces, &board_state).is_empty();
+    if check.is_check {
+        println!("check!");
+    }
+
+    // The move may have ended the game by checkmate, stalemate or a draw rule
+    if game_over.0.is_some() {
+        return;
+    }
+    match history.repetitions() {
+        Ok((position, repetitions)) => {
+            game_over.0 = GameOutcome::by_rules(&position, repetitions);
         }
+        Err(error) => eprintln!("Can't apply the rules to the game: {}", error),
     }
 }
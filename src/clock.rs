@@ -0,0 +1,226 @@
+use std::{str::FromStr, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::PlayerTurn,
+    game::{GameOutcome, GameOver, GameResult, PlayerKind, Players, Termination},
+    headless::is_headless,
+    history::MoveHistory,
+    pieces::PieceColor,
+};
+
+/// Below this much time left the player to move is warned once
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+/// Base time per player and time added after every move
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    /// Reads seconds as "60" or "10+0.1"
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let seconds = |text: &str| {
+            text.parse::<f64>()
+                .ok()
+                .filter(|seconds| *seconds >= 0.)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| format!("Expected seconds, got {:?}", text))
+        };
+        let (base, increment) = match text.split_once('+') {
+            Some((base, increment)) => (seconds(base)?, seconds(increment)?),
+            None => (seconds(text)?, Duration::from_secs(0)),
+        };
+        Ok(TimeControl { base, increment })
+    }
+}
+
+/// Time left for both players in the game on screen, counting down for the
+/// player to move once the first move has been made
+#[derive(Default)]
+pub struct GameClock {
+    // The game is untimed without one
+    pub time_control: Option<TimeControl>,
+    pub white: Duration,
+    pub black: Duration,
+    // Moves the increment has been added for
+    moves_counted: usize,
+}
+
+impl GameClock {
+    pub fn new(time_control: Option<TimeControl>) -> Self {
+        let base = time_control.map_or(Duration::from_secs(0), |time_control| time_control.base);
+        Self {
+            time_control,
+            white: base,
+            black: base,
+            moves_counted: 0,
+        }
+    }
+
+    /// Clocks showing the times left after `moves_played` moves, without the
+    /// increment being added for them again
+    pub fn resumed(
+        time_control: Option<TimeControl>,
+        white: Duration,
+        black: Duration,
+        moves_played: usize,
+    ) -> Self {
+        Self {
+            time_control,
+            white,
+            black,
+            moves_counted: moves_played,
+        }
+    }
+
+    /// Reads "--clock 300+2" from the command line for a timed game
+    pub fn from_args() -> Self {
+        let mut args = std::env::args().skip_while(|arg| arg != "--clock").skip(1);
+        let time_control = args.next().and_then(|text| match text.parse() {
+            Ok(time_control) => Some(time_control),
+            Err(error) => {
+                eprintln!("Ignoring --clock {}: {}", text, error);
+                None
+            }
+        });
+        Self::new(time_control)
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    pub fn set(&mut self, color: PieceColor, remaining: Duration) {
+        match color {
+            PieceColor::White => self.white = remaining,
+            PieceColor::Black => self.black = remaining,
+        }
+    }
+}
+
+/// Sent when the clock of a player at this screen drops below ten seconds
+pub struct LowTime(pub PieceColor);
+
+// Component to mark the Text entity showing both clocks
+struct ClockText;
+
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameClock>()
+            .add_event::<LowTime>()
+            .add_system(run_clock.system().label("run_clock"));
+
+        if !is_headless(app) {
+            app.add_startup_system(init_clock_text.system())
+                .add_system(clock_text_update.system().after("run_clock"));
+        }
+    }
+}
+
+/// Count down for the player to move, add the increment after each move and
+/// end the game when a flag falls
+fn run_clock(
+    time: Res<Time>,
+    turn: Res<PlayerTurn>,
+    players: Res<Players>,
+    history: Res<MoveHistory>,
+    mut game_over: ResMut<GameOver>,
+    mut clock: ResMut<GameClock>,
+    mut low_time: EventWriter<LowTime>,
+) {
+    let increment = match clock.time_control {
+        Some(time_control) => time_control.increment,
+        None => return,
+    };
+
+    for record in history.moves.iter().skip(clock.moves_counted) {
+        let remaining = clock.remaining(record.color);
+        clock.set(record.color, remaining + increment);
+    }
+    if clock.moves_counted != history.moves.len() {
+        clock.moves_counted = history.moves.len();
+    }
+
+    if game_over.0.is_some() || history.moves.is_empty() {
+        return;
+    }
+
+    let before = clock.remaining(turn.0);
+    let remaining = before.saturating_sub(time.delta());
+    clock.set(turn.0, remaining);
+    if before >= LOW_TIME && remaining < LOW_TIME && players.get(turn.0) == PlayerKind::Human {
+        low_time.send(LowTime(turn.0));
+    }
+    // A remote player's flag is called by their own game, which knows when they moved
+    if remaining == Duration::from_secs(0) && players.get(turn.0) != PlayerKind::Remote {
+        game_over.0 = Some(GameOutcome {
+            result: GameResult::win_for(turn.0.opponent()),
+            termination: Termination::Timeout,
+        });
+    }
+}
+
+/// Minutes and seconds, with tenths in the last ten seconds
+fn format_clock(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    if seconds < 10 {
+        format!("0:{:04.1}", remaining.as_secs_f32())
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn init_clock_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(80.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(ClockText);
+}
+
+fn clock_text_update(clock: Res<GameClock>, mut query: Query<&mut Text, With<ClockText>>) {
+    if !clock.is_changed() {
+        return;
+    }
+
+    let value = match clock.time_control {
+        Some(_) => format!(
+            "White {}  Black {}",
+            format_clock(clock.white),
+            format_clock(clock.black)
+        ),
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
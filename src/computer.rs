@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+
+use crate::{
+    animation::Animating,
+    board::{MoveEvent, PlayerTurn},
+    engine::search,
+    game::{DrawOffer, GameAction, GameOver, Players},
+    history::MoveHistory,
+    pieces::Piece,
+};
+
+/// The engine accepts a draw when its search scores the position at or below this
+const DRAW_ACCEPT_SCORE: i32 = 0;
+
+/// Lets the built-in engine play the sides `Players` gives it
+pub struct ComputerPlugin;
+impl Plugin for ComputerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(play_computer_moves.system())
+            .add_system(answer_draw_offers.system().before("handle_game_actions"));
+    }
+}
+
+/// Search for the engine's move when it's its turn, once the last move is done
+fn play_computer_moves(
+    (players, turn): (Res<Players>, Res<PlayerTurn>),
+    game_over: Res<GameOver>,
+    animating: Res<Animating>,
+    history: Res<MoveHistory>,
+    pieces_query: Query<(Entity, &Piece)>,
+    mut move_event: EventWriter<MoveEvent>,
+    // Length of the history when the last move was sent, so it's sent once
+    mut moved_at: Local<Option<usize>>,
+) {
+    if game_over.0.is_some() || animating.0 || *moved_at == Some(history.moves.len()) {
+        return;
+    }
+    let limits = match players.engine(turn.0) {
+        Some(limits) => limits,
+        None => return,
+    };
+
+    let position = match history.position(history.moves.len()) {
+        Ok(position) => position,
+        Err(error) => {
+            eprintln!("The computer can't play from this position: {}", error);
+            return;
+        }
+    };
+    let chess_move = match search(&position, &limits).best_move {
+        Some(chess_move) => chess_move,
+        None => return,
+    };
+    if let Some((piece, _)) = pieces_query
+        .iter()
+        .find(|(_, piece)| (piece.x, piece.y) == chess_move.from)
+    {
+        move_event.send(MoveEvent { piece, chess_move });
+        *moved_at = Some(history.moves.len());
+    }
+}
+
+/// Answer draw offers made to the engine from its own search of the position
+fn answer_draw_offers(
+    draw_offer: Res<DrawOffer>,
+    players: Res<Players>,
+    history: Res<MoveHistory>,
+    mut actions: EventWriter<GameAction>,
+) {
+    if !draw_offer.is_changed() {
+        return;
+    }
+
+    let (computer, limits) = match draw_offer.offered_by {
+        Some(color) => match players.engine(color.opponent()) {
+            Some(limits) => (color.opponent(), limits),
+            None => return,
+        },
+        None => return,
+    };
+
+    let position = match history.position(history.moves.len()) {
+        Ok(position) => position,
+        Err(error) => {
+            eprintln!("The computer can't judge the draw offer: {}", error);
+            return;
+        }
+    };
+    // The search scores the position for the side to move
+    let score = search(&position, &limits).score;
+    let score = if position.turn == computer {
+        score
+    } else {
+        -score
+    };
+
+    actions.send(if score <= DRAW_ACCEPT_SCORE {
+        GameAction::AcceptDraw(computer)
+    } else {
+        GameAction::DeclineDraw(computer)
+    });
+}
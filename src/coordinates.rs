@@ -0,0 +1,181 @@
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_mod_picking::Hover;
+
+use crate::{
+    board::Square,
+    camera::{CameraView, MainCamera},
+    move_input::MoveInput,
+    pieces::{square_name, PieceColor},
+};
+
+/// Distance between the edge squares' centers and the labels beside them
+const LABEL_OFFSET: f32 = 0.8;
+const LABEL_FONT_SIZE: f32 = 20.0;
+
+/// Which square names are drawn, toggled with C and N
+pub struct CoordinateSettings {
+    // a-h and 1-8 along the edges of the board
+    pub show_labels: bool,
+    // The name of the hovered square next to the cursor
+    pub show_hovered_square: bool,
+}
+
+impl Default for CoordinateSettings {
+    fn default() -> Self {
+        Self {
+            show_labels: true,
+            show_hovered_square: false,
+        }
+    }
+}
+
+// Component to mark the Text entity naming a file or rank
+enum CoordinateLabel {
+    File(u8),
+    Rank(u8),
+}
+
+// Component to mark the Text entity following the cursor
+struct HoveredSquareText;
+
+pub struct CoordinatesPlugin;
+impl Plugin for CoordinatesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CoordinateSettings>()
+            .add_startup_system(init_coordinate_labels.system())
+            .add_system(coordinate_hotkeys.system())
+            .add_system(place_coordinate_labels.system().after("move_camera"))
+            .add_system(hovered_square_text_update.system());
+    }
+}
+
+fn init_coordinate_labels(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: LABEL_FONT_SIZE,
+        color: Color::WHITE,
+    };
+
+    let labels = (0..8)
+        .map(CoordinateLabel::File)
+        .chain((0..8).map(CoordinateLabel::Rank));
+    for label in labels {
+        let value = match label {
+            CoordinateLabel::File(file) => ((b'a' + file) as char).to_string(),
+            CoordinateLabel::Rank(rank) => ((b'1' + rank) as char).to_string(),
+        };
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text: Text::with_section(value, style.clone(), Default::default()),
+                ..Default::default()
+            })
+            .insert(label);
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            text: Text::with_section("", style, Default::default()),
+            ..Default::default()
+        })
+        .insert(HoveredSquareText);
+}
+
+fn coordinate_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut settings: ResMut<CoordinateSettings>,
+) {
+    // The keys are part of the move being typed
+    if move_input.active {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::C) {
+        settings.show_labels = !settings.show_labels;
+    }
+    if keyboard_input.just_pressed(KeyCode::N) {
+        settings.show_hovered_square = !settings.show_hovered_square;
+    }
+}
+
+/// Keep the labels next to the board edges on the side of the player at the bottom
+fn place_coordinate_labels(
+    windows: Res<Windows>,
+    settings: Res<CoordinateSettings>,
+    view: Res<CameraView>,
+    camera_query: Query<(&Camera, &Transform), With<MainCamera>>,
+    mut label_query: Query<(&CoordinateLabel, &mut Style, &mut Visible)>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some((camera, transform)) => (camera, GlobalTransform::from(*transform)),
+        None => return,
+    };
+
+    // The edges closest to the player, and to the left of them
+    let (near_rank, left_file) = match view.orientation {
+        PieceColor::White => (-LABEL_OFFSET, -LABEL_OFFSET),
+        PieceColor::Black => (7. + LABEL_OFFSET, 7. + LABEL_OFFSET),
+    };
+
+    for (label, mut style, mut visible) in label_query.iter_mut() {
+        let world_position = match label {
+            CoordinateLabel::File(file) => Vec3::new(near_rank, 0., *file as f32),
+            CoordinateLabel::Rank(rank) => Vec3::new(*rank as f32, 0., left_file),
+        };
+        let screen_position = camera.world_to_screen(&windows, &camera_transform, world_position);
+
+        visible.is_visible = settings.show_labels && screen_position.is_some();
+        if let Some(screen_position) = screen_position {
+            // Center the label on the point
+            style.position = Rect {
+                left: Val::Px(screen_position.x - LABEL_FONT_SIZE / 4.),
+                bottom: Val::Px(screen_position.y - LABEL_FONT_SIZE / 2.),
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// Name the square under the cursor, e.g. "e4"
+fn hovered_square_text_update(
+    windows: Res<Windows>,
+    settings: Res<CoordinateSettings>,
+    squares_query: Query<(&Square, &Hover)>,
+    mut text_query: Query<(&mut Text, &mut Style), With<HoveredSquareText>>,
+) {
+    let hovered_square = squares_query
+        .iter()
+        .find(|(_, hover)| hover.hovered())
+        .map(|(square, _)| square_name((square.x, square.y)));
+    let cursor_position = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+
+    for (mut text, mut style) in text_query.iter_mut() {
+        match (&hovered_square, cursor_position) {
+            (Some(name), Some(cursor_position)) if settings.show_hovered_square => {
+                if text.sections[0].value != *name {
+                    text.sections[0].value = name.clone();
+                }
+                style.position = Rect {
+                    left: Val::Px(cursor_position.x + 16.),
+                    bottom: Val::Px(cursor_position.y + 16.),
+                    ..Default::default()
+                };
+            }
+            _ => {
+                if !text.sections[0].value.is_empty() {
+                    text.sections[0].value.clear();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    game::GameResult,
+    history::{HistoryView, MoveHistory},
+    move_input::MoveInput,
+    pieces::PieceColor,
+    save::SaveAction,
+};
+
+use super::{Exploration, GameDatabase, GameFilter, GameRecord};
+
+/// Games listed at once, the latest matching ones
+const MAX_LISTED: usize = 12;
+/// Moves listed when exploring a position or material
+const MAX_CONTINUATIONS: usize = 6;
+
+/// The screen listing the games in the database, opened from the settings menu
+#[derive(Default)]
+pub struct GameBrowser {
+    pub open: bool,
+    pub filter: GameFilter,
+    // The filter typed keys go to
+    focus: Option<FilterField>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FilterField {
+    Player,
+    Result,
+    Opening,
+    From,
+    To,
+    Position,
+    Material,
+}
+
+impl GameBrowser {
+    fn text_mut(&mut self, field: FilterField) -> Option<&mut String> {
+        match field {
+            FilterField::Player => Some(&mut self.filter.player),
+            FilterField::Opening => Some(&mut self.filter.opening),
+            FilterField::From => Some(&mut self.filter.from),
+            FilterField::To => Some(&mut self.filter.to),
+            FilterField::Material => Some(&mut self.filter.material),
+            FilterField::Result | FilterField::Position => None,
+        }
+    }
+
+    fn label(&self, field: FilterField) -> String {
+        let text = |label: &str, value: &str| {
+            let cursor = if self.focus == Some(field) { "_" } else { "" };
+            format!("{}: {}{}", label, value, cursor)
+        };
+        match field {
+            FilterField::Player => text("Player", &self.filter.player),
+            FilterField::Opening => text("Opening", &self.filter.opening),
+            FilterField::From => text("From", &self.filter.from),
+            FilterField::To => text("To", &self.filter.to),
+            FilterField::Material => text("Material", &self.filter.material),
+            FilterField::Position => text(
+                "Position",
+                self.filter
+                    .position
+                    .map_or("any", |_| "the one on the board"),
+            ),
+            FilterField::Result => text(
+                "Result",
+                self.filter.result.map_or("any", |result| result.pgn()),
+            ),
+        }
+    }
+}
+
+// Component to mark the node holding the browser
+struct BrowserPanel;
+
+// Component to mark the button editing a filter
+struct FilterButton(FilterField);
+
+// Component to mark the button replaying a game
+struct GameEntry(u64);
+
+// Component to mark the button closing the browser
+struct CloseBrowserButton;
+
+struct BrowserMaterials {
+    panel: Handle<ColorMaterial>,
+    button: Handle<ColorMaterial>,
+    focused: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+pub struct BrowserPlugin;
+impl Plugin for BrowserPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameBrowser>()
+            .add_startup_system(init_browser_materials.system())
+            // Before anything else sees the keys typed into a filter
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                type_filter.system().after(InputSystem),
+            )
+            .add_system(browser_buttons.system().label("browser_buttons"))
+            .add_system(browser_update.system().after("browser_buttons"));
+    }
+}
+
+fn init_browser_materials(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(BrowserMaterials {
+        panel: color_materials.add(Color::rgba(0.05, 0.05, 0.05, 0.95).into()),
+        button: color_materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+        focused: color_materials.add(Color::rgb(0.6, 0.5, 0.1).into()),
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+    });
+}
+
+/// Typing goes to the focused filter, and is kept from the game's hotkeys
+fn type_filter(
+    mut browser: ResMut<GameBrowser>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    let field = match browser.focus {
+        Some(field) if browser.open => field,
+        _ => return,
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Return)
+        || keyboard_input.just_pressed(KeyCode::Escape)
+        || keyboard_input.just_pressed(KeyCode::Tab)
+    {
+        browser.focus = None;
+    } else if keyboard_input.just_pressed(KeyCode::Back) {
+        if let Some(text) = browser.text_mut(field) {
+            text.pop();
+        }
+    }
+    let typed: String = characters
+        .iter()
+        .map(|character| character.char)
+        .filter(|character| !character.is_control())
+        .collect();
+    if browser.focus.is_some() && !typed.is_empty() {
+        if let Some(text) = browser.text_mut(field) {
+            text.push_str(&typed);
+        }
+    }
+
+    let pressed: Vec<KeyCode> = keyboard_input.get_just_pressed().copied().collect();
+    for key in pressed {
+        keyboard_input.reset(key);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn browser_buttons(
+    mut browser: ResMut<GameBrowser>,
+    database: Res<GameDatabase>,
+    (history, view): (Res<MoveHistory>, Res<HistoryView>),
+    move_input: Option<ResMut<MoveInput>>,
+    mut actions: EventWriter<SaveAction>,
+    filter_query: Query<(&Interaction, &FilterButton), Changed<Interaction>>,
+    game_query: Query<(&Interaction, &GameEntry), Changed<Interaction>>,
+    close_query: Query<&Interaction, (Changed<Interaction>, With<CloseBrowserButton>)>,
+) {
+    for (interaction, button) in filter_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if button.0 == FilterField::Result {
+            browser.filter.result = match browser.filter.result {
+                None => Some(GameResult::WhiteWins),
+                Some(GameResult::WhiteWins) => Some(GameResult::BlackWins),
+                Some(GameResult::BlackWins) => Some(GameResult::Draw),
+                Some(GameResult::Draw) => None,
+            };
+            browser.focus = None;
+        } else if button.0 == FilterField::Position {
+            // The position shown, which is a past one while stepping through moves
+            let plies = view.ply.map_or(history.moves.len(), |ply| ply + 1);
+            browser.filter.position = match browser.filter.position {
+                Some(_) => None,
+                None => history
+                    .position(plies)
+                    .ok()
+                    .map(|position| position.zobrist()),
+            };
+            browser.focus = None;
+        } else {
+            browser.focus = Some(button.0);
+        }
+    }
+    // Keys can only go one place
+    if let Some(mut move_input) = move_input {
+        if browser.focus.is_some() && move_input.active {
+            move_input.active = false;
+            move_input.text.clear();
+        }
+    }
+
+    for (interaction, entry) in game_query.iter() {
+        if *interaction == Interaction::Clicked {
+            if let Some(game) = database.get(entry.0) {
+                actions.send(SaveAction::Replay(game.saved_game()));
+                browser.open = false;
+                browser.focus = None;
+            }
+        }
+    }
+
+    for interaction in close_query.iter() {
+        if *interaction == Interaction::Clicked {
+            browser.open = false;
+            browser.focus = None;
+        }
+    }
+}
+
+/// Lay the browser out again whenever a filter or the games change
+fn browser_update(
+    mut commands: Commands,
+    browser: Res<GameBrowser>,
+    database: Res<GameDatabase>,
+    materials: Res<BrowserMaterials>,
+    panel_query: Query<Entity, With<BrowserPanel>>,
+) {
+    if !browser.is_changed() && !database.is_changed() {
+        return;
+    }
+
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !browser.open {
+        return;
+    }
+
+    let games = database.search(&browser.filter);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(40.),
+                    right: Val::Px(40.),
+                    top: Val::Px(40.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                padding: Rect::all(Val::Px(10.)),
+                ..Default::default()
+            },
+            material: materials.panel.clone(),
+            ..Default::default()
+        })
+        .insert(BrowserPanel)
+        .with_children(|parent| {
+            spawn_label(
+                parent,
+                &materials,
+                format!(
+                    "Game database: {} of {} games",
+                    games.len(),
+                    database.games.len()
+                ),
+            );
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        flex_wrap: FlexWrap::Wrap,
+                        ..Default::default()
+                    },
+                    material: materials.panel.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for field in [
+                        FilterField::Player,
+                        FilterField::Result,
+                        FilterField::Opening,
+                        FilterField::From,
+                        FilterField::To,
+                        FilterField::Position,
+                        FilterField::Material,
+                    ]
+                    .iter()
+                    {
+                        let focused = browser.focus == Some(*field);
+                        spawn_button(
+                            parent,
+                            &materials,
+                            browser.label(*field),
+                            focused,
+                            FilterButton(*field),
+                        );
+                    }
+                });
+
+            for line in explorer_lines(&browser.filter, &games) {
+                spawn_label(parent, &materials, line);
+            }
+
+            for game in games.iter().take(MAX_LISTED) {
+                spawn_button(
+                    parent,
+                    &materials,
+                    game_summary(game),
+                    false,
+                    GameEntry(game.id),
+                );
+            }
+            if games.len() > MAX_LISTED {
+                spawn_label(
+                    parent,
+                    &materials,
+                    format!("and {} more, narrow the filters", games.len() - MAX_LISTED),
+                );
+            }
+
+            spawn_button(
+                parent,
+                &materials,
+                "Close".to_string(),
+                false,
+                CloseBrowserButton,
+            );
+        });
+}
+
+/// How the moves played from the position or material filtered for scored
+fn explorer_lines(filter: &GameFilter, games: &[&GameRecord]) -> Vec<String> {
+    let exploration = match filter.position {
+        Some(hash) => Exploration::of_position(games, hash),
+        None if filter.material.trim().is_empty() => return Vec::new(),
+        None => match filter.material_signature() {
+            Ok(signature) => Exploration::of_material(games, &signature),
+            Err(error) => return vec![error],
+        },
+    };
+
+    exploration
+        .continuations
+        .iter()
+        .take(MAX_CONTINUATIONS)
+        .map(|continuation| {
+            format!(
+                "{}: {} games, +{} ={} -{}, White scores {:.0}%",
+                continuation.san.as_deref().unwrap_or("Game over"),
+                continuation.games,
+                continuation.white_wins,
+                continuation.draws,
+                continuation.black_wins,
+                continuation.score(PieceColor::White) * 100.
+            )
+        })
+        .collect()
+}
+
+/// One line about a game: "2024.05.17  Player - Computer  1-0  C50 Italian Game  23 moves"
+fn game_summary(game: &GameRecord) -> String {
+    let opening = match (&game.eco, &game.opening) {
+        (Some(eco), Some(name)) => format!("{} {}", eco, name),
+        _ => "Unknown opening".to_string(),
+    };
+    format!(
+        "{}  {} - {}  {}  {}  {} moves",
+        game.date,
+        game.white,
+        game.black,
+        game.result.pgn(),
+        opening,
+        game.moves.len().div_ceil(2)
+    )
+}
+
+fn spawn_label(parent: &mut ChildBuilder, materials: &BrowserMaterials, label: String) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(4.)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            label,
+            TextStyle {
+                font: materials.font.clone(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    materials: &BrowserMaterials,
+    label: String,
+    focused: bool,
+    button: impl bevy::ecs::component::Component,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(2.)),
+                padding: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: if focused {
+                materials.focused.clone()
+            } else {
+                materials.button.clone()
+            },
+            ..Default::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    label,
+                    TextStyle {
+                        font: materials.font.clone(),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
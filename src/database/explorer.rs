@@ -0,0 +1,98 @@
+use std::cmp::Reverse;
+
+use crate::{
+    game::GameResult,
+    pieces::{Material, MaterialSignature, PieceColor},
+};
+
+use super::GameRecord;
+
+/// A move played from the explored position and how the games went on
+pub struct Continuation {
+    // None for games that ended there
+    pub san: Option<String>,
+    pub games: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    pub black_wins: usize,
+}
+
+impl Continuation {
+    /// Share of the points `color` took, from 0 to 1
+    pub fn score(&self, color: PieceColor) -> f32 {
+        let wins = match color {
+            PieceColor::White => self.white_wins,
+            PieceColor::Black => self.black_wins,
+        };
+        (wins as f32 + self.draws as f32 / 2.) / self.games as f32
+    }
+}
+
+/// The games that reached a position or a material balance, and what was
+/// played from there
+#[derive(Default)]
+pub struct Exploration {
+    pub games: Vec<u64>,
+    // The most played first
+    pub continuations: Vec<Continuation>,
+}
+
+impl Exploration {
+    /// Games reaching the position with this Zobrist hash, whatever the move
+    /// order
+    pub fn of_position(games: &[&GameRecord], hash: u64) -> Self {
+        Self::gather(games, |game| {
+            game.hashes.iter().position(|reached| *reached == hash)
+        })
+    }
+
+    /// Games reaching the material, going on from the first time they did
+    pub fn of_material(games: &[&GameRecord], signature: &MaterialSignature) -> Self {
+        Self::gather(games, |game| {
+            game.material
+                .iter()
+                .position(|key| signature.matches(&Material::from_key(*key)).is_some())
+        })
+    }
+
+    fn gather(games: &[&GameRecord], first_ply: impl Fn(&GameRecord) -> Option<usize>) -> Self {
+        let mut exploration = Self::default();
+        for game in games.iter() {
+            let ply = match first_ply(game) {
+                Some(ply) => ply,
+                None => continue,
+            };
+            exploration.games.push(game.id);
+
+            let san = game.moves.get(ply).cloned();
+            let index = match exploration
+                .continuations
+                .iter()
+                .position(|continuation| continuation.san == san)
+            {
+                Some(index) => index,
+                None => {
+                    exploration.continuations.push(Continuation {
+                        san,
+                        games: 0,
+                        white_wins: 0,
+                        draws: 0,
+                        black_wins: 0,
+                    });
+                    exploration.continuations.len() - 1
+                }
+            };
+            let continuation = &mut exploration.continuations[index];
+            continuation.games += 1;
+            match game.result {
+                GameResult::WhiteWins => continuation.white_wins += 1,
+                GameResult::Draw => continuation.draws += 1,
+                GameResult::BlackWins => continuation.black_wins += 1,
+            }
+        }
+        exploration
+            .continuations
+            .sort_by_key(|continuation| Reverse(continuation.games));
+        exploration
+    }
+}
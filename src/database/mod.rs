@@ -0,0 +1,14 @@
+mod record;
+pub use record::*;
+
+mod store;
+pub use store::*;
+
+mod explorer;
+pub use explorer::*;
+
+mod recorder;
+pub use recorder::*;
+
+mod browser;
+pub use browser::*;
@@ -0,0 +1,142 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::TimeControl,
+    game::{GameOutcome, GameResult, PlayerKind, Termination},
+    history::MoveHistory,
+    openings::Openings,
+    pieces::*,
+    save::SavedGame,
+};
+
+/// A finished game as kept in the database
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub id: u64,
+    pub white: String,
+    pub black: String,
+    // Day the game ended in UTC, written as in PGN: "2024.05.17"
+    pub date: String,
+    pub result: GameResult,
+    pub termination: Termination,
+    #[serde(default)]
+    pub eco: Option<String>,
+    #[serde(default)]
+    pub opening: Option<String>,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    // FEN of the position the game started from, none for the usual one
+    #[serde(default)]
+    pub start: Option<String>,
+    // Every move in SAN
+    pub moves: Vec<String>,
+    pub final_fen: String,
+    // Zobrist hash of the position before each move and after the last
+    #[serde(default)]
+    pub hashes: Vec<u64>,
+    // Material at the same points, as packed by `Material::key`
+    #[serde(default)]
+    pub material: Vec<u64>,
+}
+
+impl GameRecord {
+    /// The game on the board, once it's over. The id is given when it's added.
+    pub fn new(
+        history: &MoveHistory,
+        (white, black): (String, String),
+        outcome: &GameOutcome,
+        time_control: Option<TimeControl>,
+        openings: &Openings,
+    ) -> Result<Self, String> {
+        let moves: Vec<String> = history
+            .moves
+            .iter()
+            .map(|record| record.san.clone())
+            .collect();
+        let mut game = Self {
+            id: 0,
+            white,
+            black,
+            date: today(),
+            result: outcome.result,
+            termination: outcome.termination,
+            eco: None,
+            opening: None,
+            time_control,
+            start: history.start.clone(),
+            moves,
+            final_fen: String::new(),
+            hashes: Vec::new(),
+            material: Vec::new(),
+        };
+        game.index_positions()?;
+        if let Some(opening) = openings.classify(&game.hashes) {
+            game.eco = Some(opening.eco.clone());
+            game.opening = Some(opening.name.clone());
+        }
+        Ok(game)
+    }
+
+    /// Play the moves through to note every position reached, and the last
+    pub fn index_positions(&mut self) -> Result<(), String> {
+        let mut position = Position::from_fen(self.start.as_deref().unwrap_or(STARTING_FEN))
+            .map_err(|error| error.to_string())?;
+        self.hashes = vec![position.zobrist()];
+        self.material = vec![Material::of(&position.pieces).key()];
+        for (ply, input) in self.moves.iter().enumerate() {
+            let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+                .map_err(|error| format!("Move {} ({}): {}", ply / 2 + 1, input, error))?;
+            position.play(&chess_move);
+            self.hashes.push(position.zobrist());
+            self.material.push(Material::of(&position.pieces).key());
+        }
+        self.final_fen = position.to_fen();
+        Ok(())
+    }
+
+    /// The game ready to be put on the board with its moves to step through
+    pub fn saved_game(&self) -> SavedGame {
+        SavedGame {
+            start: self
+                .start
+                .clone()
+                .unwrap_or_else(|| STARTING_FEN.to_string()),
+            moves: self.moves.clone(),
+            white: PlayerKind::Human,
+            black: PlayerKind::Human,
+            result: Some((self.result, self.termination)),
+            ..SavedGame::default()
+        }
+    }
+}
+
+/// Today's date in UTC, as "YYYY.MM.DD"
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86_400) as i64;
+    let (year, month, day) = civil_date(days);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// The calendar date `days` after 1970-01-01, from Howard Hinnant's
+/// `civil_from_days`
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::GameClock,
+    game::{GameOver, Players},
+    headless::is_headless,
+    history::MoveHistory,
+    openings::Openings,
+    profiles::Seats,
+    save::GameLoaded,
+};
+
+use super::{BrowserPlugin, DatabaseFile, GameDatabase, GameRecord};
+
+/// Stores every game in the database once it's over, and lists them in a
+/// browser to replay them from when there's a window. Needs the `SavePlugin`,
+/// which puts the games back on the board.
+pub struct DatabasePlugin;
+impl Plugin for DatabasePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DatabaseFile>();
+        let path = app
+            .world()
+            .get_resource::<DatabaseFile>()
+            .unwrap()
+            .path
+            .clone();
+        app.insert_resource(GameDatabase::open_or_skip(&path))
+            .init_resource::<Openings>()
+            .init_resource::<Seats>()
+            .add_system(record_finished_games.system().after("load_games"));
+
+        if !is_headless(app) {
+            app.add_plugin(BrowserPlugin);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_finished_games(
+    mut database: ResMut<GameDatabase>,
+    openings: Res<Openings>,
+    game_over: Res<GameOver>,
+    history: Res<MoveHistory>,
+    (players, seats): (Res<Players>, Res<Seats>),
+    clock: Res<GameClock>,
+    mut loaded: EventReader<GameLoaded>,
+    mut recorded: Local<bool>,
+) {
+    // A finished game that was loaded is in the database from when it was played
+    if loaded.iter().count() > 0 {
+        *recorded = game_over.0.is_some();
+        return;
+    }
+
+    let outcome = match game_over.0 {
+        Some(outcome) => outcome,
+        None => {
+            *recorded = false;
+            return;
+        }
+    };
+    if *recorded || history.moves.is_empty() {
+        return;
+    }
+    *recorded = true;
+
+    let names = seats.names(&players);
+    let added = GameRecord::new(&history, names, &outcome, clock.time_control, &openings)
+        .and_then(|game| database.add(game));
+    if let Err(error) = added {
+        eprintln!("Couldn't store the game in the database: {}", error);
+    }
+}
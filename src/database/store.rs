@@ -0,0 +1,211 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    game::GameResult,
+    pieces::{Material, MaterialSignature},
+};
+
+use super::GameRecord;
+
+/// Every finished game, kept in a file with one JSON record per line so a
+/// new game is appended without writing the others again
+pub struct GameDatabase {
+    pub path: PathBuf,
+    pub games: Vec<GameRecord>,
+    // Above every id in the file, including those of lines that were skipped
+    next_id: u64,
+    // Nothing is added to a file that couldn't be read, where new games could
+    // take ids already used
+    readable: bool,
+}
+
+/// Where finished games are kept
+pub struct DatabaseFile {
+    pub path: PathBuf,
+}
+
+impl Default for DatabaseFile {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("games/games.jsonl"),
+        }
+    }
+}
+
+impl GameDatabase {
+    /// Open the database at `path`, or if it can't be read, report why and
+    /// carry on with an empty one that nothing is written to
+    pub fn open_or_skip(path: &Path) -> Self {
+        Self::open(path).unwrap_or_else(|error| {
+            eprintln!("Skipping the game database {}: {}", path.display(), error);
+            Self {
+                path: path.to_path_buf(),
+                games: Vec::new(),
+                next_id: 1,
+                readable: false,
+            }
+        })
+    }
+
+    /// Read the games in the file, which is created with the first game added.
+    /// Lines that aren't games are reported and skipped.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut games: Vec<GameRecord> = Vec::new();
+        let mut last_id = 0;
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<GameRecord>(line) {
+                Ok(game) => {
+                    last_id = last_id.max(game.id);
+                    games.push(game);
+                }
+                Err(error) => {
+                    eprintln!(
+                        "Skipping line {} of {}: {}",
+                        index + 1,
+                        path.display(),
+                        error
+                    );
+                    // Its id stays taken, if it can still be made out
+                    let id = serde_json::from_str::<serde_json::Value>(line)
+                        .ok()
+                        .and_then(|value| value.get("id")?.as_u64());
+                    last_id = last_id.max(id.unwrap_or(0));
+                }
+            }
+        }
+        // Games stored before positions were indexed
+        for game in games.iter_mut() {
+            if game.hashes.len() != game.moves.len() + 1 {
+                if let Err(error) = game.index_positions() {
+                    eprintln!("Skipping positions of game {}: {}", game.id, error);
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            games,
+            next_id: last_id + 1,
+            readable: true,
+        })
+    }
+
+    /// Store a game under the next id, which is returned
+    pub fn add(&mut self, mut game: GameRecord) -> Result<u64, String> {
+        if !self.readable {
+            return Err(format!(
+                "{} couldn't be read, so no games are added to it",
+                self.path.display()
+            ));
+        }
+        game.id = self.next_id;
+        let line = serde_json::to_string(&game).map_err(|error| error.to_string())?;
+
+        if let Some(folder) = self.path.parent() {
+            fs::create_dir_all(folder).map_err(|error| error.to_string())?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|error| error.to_string())?;
+        writeln!(file, "{}", line).map_err(|error| error.to_string())?;
+
+        let id = game.id;
+        self.next_id = id + 1;
+        self.games.push(game);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&GameRecord> {
+        self.games.iter().find(|game| game.id == id)
+    }
+
+    /// The games matching the filter, latest first
+    pub fn search(&self, filter: &GameFilter) -> Vec<&GameRecord> {
+        self.games
+            .iter()
+            .rev()
+            .filter(|game| filter.matches(game))
+            .collect()
+    }
+}
+
+/// Which games to list, empty text matching any game
+#[derive(Clone, Default)]
+pub struct GameFilter {
+    // Part of either player's name, in any case
+    pub player: String,
+    pub result: Option<GameResult>,
+    // Part of the opening's name, or the start of its ECO code
+    pub opening: String,
+    // First and last day to include, partial dates such as "2024.05" working
+    // too
+    pub from: String,
+    pub to: String,
+    // Zobrist hash of a position the game reached
+    pub position: Option<u64>,
+    // Material the game reached, as read by `MaterialSignature::parse`
+    pub material: String,
+}
+
+impl GameFilter {
+    pub fn matches(&self, game: &GameRecord) -> bool {
+        let player = self.player.trim().to_lowercase();
+        let opening = self.opening.trim().to_lowercase();
+        let from = normalize_date(&self.from);
+        let to = normalize_date(&self.to);
+
+        let player_matches = player.is_empty()
+            || game.white.to_lowercase().contains(&player)
+            || game.black.to_lowercase().contains(&player);
+        let opening_matches = opening.is_empty()
+            || game
+                .opening
+                .as_ref()
+                .is_some_and(|name| name.to_lowercase().contains(&opening))
+            || game
+                .eco
+                .as_ref()
+                .is_some_and(|eco| eco.to_lowercase().starts_with(&opening));
+        let result_matches = self.result.is_none_or(|result| result == game.result);
+        let day = |length: usize| &game.date[..length.min(game.date.len())];
+        let from_matches = from.is_empty() || game.date.as_str() >= from.as_str();
+        let to_matches = to.is_empty() || day(to.len()) <= to.as_str();
+        let position_matches = self.position.is_none_or(|hash| game.hashes.contains(&hash));
+        let material_matches = self.material.trim().is_empty()
+            || self.material_signature().is_ok_and(|signature| {
+                game.material
+                    .iter()
+                    .any(|key| signature.matches(&Material::from_key(*key)).is_some())
+            });
+
+        player_matches
+            && opening_matches
+            && result_matches
+            && from_matches
+            && to_matches
+            && position_matches
+            && material_matches
+    }
+
+    pub fn material_signature(&self) -> Result<MaterialSignature, String> {
+        MaterialSignature::parse(&self.material)
+    }
+}
+
+/// Dates as stored, whichever separator they were typed with
+fn normalize_date(text: &str) -> String {
+    text.trim().replace(['-', '/'], ".")
+}
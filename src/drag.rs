@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy_mod_picking::*;
+
+use crate::{
+    animation::Animating,
+    board::{SelectedPiece, SelectedSquare, Square},
+    pieces::{Dragged, Piece},
+};
+
+/// Height pieces are lifted to while being dragged
+const LIFT_HEIGHT: f32 = 0.5;
+
+#[derive(Default)]
+struct DraggedPiece {
+    entity: Option<Entity>,
+}
+
+/// Lets players move pieces by dragging them, next to selecting with two clicks
+pub struct DragPlugin;
+impl Plugin for DragPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DraggedPiece>()
+            .add_system(drag_piece.system())
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                drop_piece
+                    .system()
+                    .after("select_square")
+                    .before("validate_move"),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                start_drag
+                    .system()
+                    .after("select_piece")
+                    .after("reset_selected"),
+            );
+    }
+}
+
+/// Pick up the piece that was just selected by pressing on it
+#[allow(clippy::too_many_arguments)]
+fn start_drag(
+    mut commands: Commands,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    animating: Res<Animating>,
+    selected_square: Res<SelectedSquare>,
+    selected_piece: Res<SelectedPiece>,
+    mut dragged_piece: ResMut<DraggedPiece>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<&Piece>,
+) {
+    if !mouse_button_inputs.just_pressed(MouseButton::Left) || animating.0 {
+        return;
+    }
+
+    let (piece_entity, square_entity) = match (selected_piece.entity, selected_square.entity) {
+        (Some(piece_entity), Some(square_entity)) => (piece_entity, square_entity),
+        _ => return,
+    };
+
+    // Only drag a piece that was pressed on, not one selected by an earlier click
+    if let (Ok(piece), Ok(square)) = (
+        pieces_query.get(piece_entity),
+        squares_query.get(square_entity),
+    ) {
+        if piece.x == square.x && piece.y == square.y {
+            commands.entity(piece_entity).insert(Dragged);
+            dragged_piece.entity = Some(piece_entity);
+        }
+    }
+}
+
+/// Keep the dragged piece under the cursor, above the board
+fn drag_piece(
+    camera_query: Query<&PickingCamera>,
+    mut dragged_query: Query<&mut Transform, With<Dragged>>,
+) {
+    for camera in camera_query.iter() {
+        let ray = if let Some(ray) = camera.ray() {
+            ray
+        } else {
+            continue;
+        };
+
+        // Intersect the cursor ray with a plane at the lift height
+        let (origin, direction) = (ray.origin(), ray.direction());
+        if direction.y.abs() < f32::EPSILON {
+            continue;
+        }
+        let distance = (LIFT_HEIGHT - origin.y) / direction.y;
+        if distance < 0. {
+            continue;
+        }
+
+        for mut transform in dragged_query.iter_mut() {
+            transform.translation = origin + direction * distance;
+        }
+    }
+}
+
+/// Releasing over another square tries to move there, otherwise the piece snaps back
+fn drop_piece(
+    mut commands: Commands,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    mut dragged_piece: ResMut<DraggedPiece>,
+    mut selected_square: ResMut<SelectedSquare>,
+    squares_query: Query<(Entity, &Hover), With<Square>>,
+) {
+    if !mouse_button_inputs.just_released(MouseButton::Left) {
+        return;
+    }
+
+    let piece_entity = if let Some(entity) = dragged_piece.entity.take() {
+        entity
+    } else {
+        return;
+    };
+    commands.entity(piece_entity).remove::<Dragged>();
+
+    // Dropping onto a square counts as clicking it, which validate_move picks up
+    if let Some((square_entity, _hover)) = squares_query.iter().find(|(_, hover)| hover.hovered()) {
+        if Some(square_entity) != selected_square.entity {
+            selected_square.entity = Some(square_entity);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pieces::*;
+
+/// Score of a checkmate, less the plies it takes so faster mates score higher
+pub const MATE_SCORE: i32 = 100_000;
+/// How many captures deep the search follows exchanges past its depth
+const MAX_QUIESCENCE_DEPTH: u32 = 4;
+
+/// What the built-in AI is allowed to spend on a move
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    pub depth: u32,
+    // Set move by move from the clock, so never saved
+    #[serde(skip)]
+    pub time: Option<Duration>,
+    // Follow captures past the depth so exchanges aren't cut off halfway
+    pub quiescence: bool,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            time: None,
+            quiescence: true,
+        }
+    }
+}
+
+pub struct SearchResult {
+    pub best_move: Option<ChessMove>,
+    // In centipawns for the side to move
+    pub score: i32,
+    // Last depth searched to the end
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+struct Search {
+    limits: SearchLimits,
+    deadline: Option<Instant>,
+    nodes: u64,
+    aborted: bool,
+}
+
+/// Find the best move by searching deeper and deeper until the depth or time runs out
+pub fn search(position: &Position, limits: &SearchLimits) -> SearchResult {
+    let deadline = limits.time.map(|time| Instant::now() + time);
+    // The first depth is always finished, so there's a move to play
+    let mut search = Search {
+        limits: *limits,
+        deadline: None,
+        nodes: 0,
+        aborted: false,
+    };
+    let mut result = SearchResult {
+        best_move: None,
+        score: 0,
+        depth: 0,
+        nodes: 0,
+    };
+
+    let mut moves = position.legal_moves();
+    if moves.is_empty() {
+        result.score = if position.is_check() { -MATE_SCORE } else { 0 };
+        return result;
+    }
+    order_moves(&mut moves, &position.pieces);
+
+    for depth in 1..=limits.depth.max(1) {
+        let mut best: Option<(ChessMove, i32)> = None;
+        let mut alpha = -MATE_SCORE - 1;
+        for chess_move in moves.iter() {
+            let mut next = position.clone();
+            next.play(chess_move);
+            let score = -search.negamax(&next, depth - 1, 1, -MATE_SCORE - 1, -alpha);
+            if search.aborted {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some((*chess_move, score));
+            }
+        }
+        if search.aborted {
+            break;
+        }
+        search.deadline = deadline;
+
+        if let Some((best_move, score)) = best {
+            result.best_move = Some(best_move);
+            result.score = score;
+            result.depth = depth;
+            // Search the best move first next time, it makes the cutoffs come sooner
+            if let Some(index) = moves.iter().position(|other| *other == best_move) {
+                let chess_move = moves.remove(index);
+                moves.insert(0, chess_move);
+            }
+        }
+        if score_is_mate(result.score) {
+            break;
+        }
+    }
+
+    result.nodes = search.nodes;
+    result
+}
+
+fn score_is_mate(score: i32) -> bool {
+    score.abs() > MATE_SCORE - 1000
+}
+
+impl Search {
+    fn is_out_of_time(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            if !self.aborted && Instant::now() >= deadline {
+                self.aborted = true;
+            }
+        }
+        self.aborted
+    }
+
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.is_out_of_time() {
+            return 0;
+        }
+        if position.halfmove_clock >= 100 || position.is_insufficient_material() {
+            return 0;
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.is_check() {
+                -MATE_SCORE + ply
+            } else {
+                0
+            };
+        }
+        if depth == 0 {
+            return if self.limits.quiescence {
+                self.quiescence(position, moves, 0, alpha, beta)
+            } else {
+                evaluate(position)
+            };
+        }
+
+        order_moves(&mut moves, &position.pieces);
+        for chess_move in moves.iter() {
+            let mut next = position.clone();
+            next.play(chess_move);
+            let score = -self.negamax(&next, depth - 1, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Keep playing captures until the position is quiet, either side can stop
+    /// capturing when standing pat is better
+    fn quiescence(
+        &mut self,
+        position: &Position,
+        moves: Vec<ChessMove>,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta || depth >= MAX_QUIESCENCE_DEPTH {
+            return stand_pat.min(beta);
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut captures: Vec<ChessMove> = moves
+            .into_iter()
+            .filter(|chess_move| chess_move.capture.is_some())
+            .collect();
+        order_moves(&mut captures, &position.pieces);
+        for chess_move in captures.iter() {
+            self.nodes += 1;
+            if self.is_out_of_time() {
+                return 0;
+            }
+
+            let mut next = position.clone();
+            next.play(chess_move);
+            let replies = next.legal_moves();
+            let score = if replies.is_empty() {
+                if next.is_check() {
+                    MATE_SCORE
+                } else {
+                    0
+                }
+            } else {
+                -self.quiescence(&next, replies, depth + 1, -beta, -alpha)
+            };
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+/// Captures of valuable pieces by cheap ones first, then promotions, then the rest
+fn order_moves(moves: &mut [ChessMove], pieces: &[Piece]) {
+    let value_at = |position: Option<(u8, u8)>| {
+        position
+            .and_then(|position| pieces.iter().find(|piece| (piece.x, piece.y) == position))
+            .map_or(0, |piece| piece_value(piece.piece_type))
+    };
+    moves.sort_by_key(|chess_move| {
+        let gain = match chess_move.capture {
+            Some(_) => 10 * value_at(chess_move.capture) - value_at(Some(chess_move.from)),
+            None => 0,
+        };
+        let promotion = chess_move.promotion.map_or(0, piece_value);
+        -(gain + promotion)
+    });
+}
+
+fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King => 0,
+        piece_type => piece_type.value() * 100,
+    }
+}
+
+/// Material and piece placement in centipawns for the side to move
+pub fn evaluate(position: &Position) -> i32 {
+    let has_queens = position
+        .pieces
+        .iter()
+        .any(|piece| piece.piece_type == PieceType::Queen);
+
+    let score: i32 = position
+        .pieces
+        .iter()
+        .map(|piece| {
+            // Ranks counted from the piece's own side
+            let rank = match piece.color {
+                PieceColor::White => piece.x as i32,
+                PieceColor::Black => 7 - piece.x as i32,
+            };
+            // 0 on the edge up to 6 in the middle four squares
+            let centrality =
+                7 - ((2 * piece.x as i32 - 7).abs() + (2 * piece.y as i32 - 7).abs()) / 2;
+
+            let placement = match piece.piece_type {
+                PieceType::Pawn => {
+                    let central_file = if piece.y == 3 || piece.y == 4 { 10 } else { 0 };
+                    rank * 8 + central_file
+                }
+                PieceType::Knight => centrality * 6,
+                PieceType::Bishop => centrality * 3,
+                PieceType::Queen => centrality,
+                PieceType::Rook => 0,
+                // Stay home while the queens are on, help out in the endgame
+                PieceType::King if has_queens => -rank * 10,
+                PieceType::King => centrality * 4,
+            };
+
+            let value = piece_value(piece.piece_type) + placement;
+            if piece.color == position.turn {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum();
+
+    score
+}
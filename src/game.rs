@@ -0,0 +1,354 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::SearchLimits,
+    history::MoveHistory,
+    openings::Openings,
+    pieces::{PieceColor, Position},
+    profiles::Seats,
+};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    pub fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => GameResult::WhiteWins,
+            PieceColor::Black => GameResult::BlackWins,
+        }
+    }
+
+    /// The color that won, none for a draw
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            GameResult::WhiteWins => Some(PieceColor::White),
+            GameResult::BlackWins => Some(PieceColor::Black),
+            GameResult::Draw => None,
+        }
+    }
+
+    /// Result as written in the PGN Result tag and at the end of the movetext
+    pub fn pgn(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// How the game ended
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Termination {
+    Resignation,
+    DrawAgreement,
+    Timeout,
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+impl Termination {
+    /// Value of the PGN Termination tag
+    pub fn pgn(&self) -> &'static str {
+        match self {
+            Termination::Timeout => "time forfeit",
+            _ => "normal",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl GameOutcome {
+    /// Human readable reason for the result, e.g. "White resigns"
+    pub fn description(&self) -> String {
+        let winner = match self.result {
+            GameResult::WhiteWins => PieceColor::White,
+            GameResult::BlackWins => PieceColor::Black,
+            GameResult::Draw => {
+                return match self.termination {
+                    Termination::Stalemate => "Draw by stalemate",
+                    Termination::Repetition => "Draw by threefold repetition",
+                    Termination::FiftyMoves => "Draw by the fifty-move rule",
+                    Termination::InsufficientMaterial => "Draw by insufficient material",
+                    _ => "Draw by agreement",
+                }
+                .to_string()
+            }
+        };
+
+        match self.termination {
+            Termination::Resignation => format!("{} resigns", winner.opponent().name()),
+            Termination::Timeout => format!("{} ran out of time", winner.opponent().name()),
+            _ => format!("{} checkmates", winner.name()),
+        }
+    }
+
+    /// How the rules end the game in a position reached `repetitions` times:
+    /// checkmate, stalemate or one of the automatic draws
+    pub fn by_rules(position: &Position, repetitions: u32) -> Option<Self> {
+        let draw = |termination| GameOutcome {
+            result: GameResult::Draw,
+            termination,
+        };
+        let outcome = if position.legal_moves().is_empty() {
+            if position.is_check() {
+                GameOutcome {
+                    result: GameResult::win_for(position.turn.opponent()),
+                    termination: Termination::Checkmate,
+                }
+            } else {
+                draw(Termination::Stalemate)
+            }
+        } else if position.is_insufficient_material() {
+            draw(Termination::InsufficientMaterial)
+        } else if position.halfmove_clock >= 100 {
+            draw(Termination::FiftyMoves)
+        } else if repetitions >= 3 {
+            draw(Termination::Repetition)
+        } else {
+            return None;
+        };
+        Some(outcome)
+    }
+}
+
+/// Holds the outcome once the game has finished
+#[derive(Default)]
+pub struct GameOver(pub Option<GameOutcome>);
+
+/// The draw offer waiting for an answer, if any
+#[derive(Default)]
+pub struct DrawOffer {
+    pub offered_by: Option<PieceColor>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PlayerKind {
+    Human,
+    Computer,
+    // Playing from another instance of the game over the network
+    Remote,
+}
+
+pub struct Players {
+    pub white: PlayerKind,
+    pub black: PlayerKind,
+    // What the built-in engine may spend on each side's moves, when it plays them
+    pub white_engine: SearchLimits,
+    pub black_engine: SearchLimits,
+}
+
+impl Players {
+    pub fn get(&self, color: PieceColor) -> PlayerKind {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    /// The limits the engine plays `color` with, none when it doesn't play that side
+    pub fn engine(&self, color: PieceColor) -> Option<SearchLimits> {
+        if self.get(color) != PlayerKind::Computer {
+            return None;
+        }
+        Some(match color {
+            PieceColor::White => self.white_engine,
+            PieceColor::Black => self.black_engine,
+        })
+    }
+
+    /// Reads "--computer white", "--computer black" or "--computer both" for the
+    /// sides the built-in engine plays, and "--depth 4" for how deep it searches
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let value_after = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .filter(|value| !value.starts_with("--"))
+        };
+
+        let mut players = Players::default();
+        if args.iter().any(|arg| arg == "--computer") {
+            match value_after("--computer").map(String::as_str) {
+                Some("white") => players.white = PlayerKind::Computer,
+                Some("both") => {
+                    players.white = PlayerKind::Computer;
+                    players.black = PlayerKind::Computer;
+                }
+                _ => players.black = PlayerKind::Computer,
+            }
+        }
+        if let Some(depth) = value_after("--depth") {
+            match depth.parse() {
+                Ok(depth) => {
+                    players.white_engine.depth = depth;
+                    players.black_engine.depth = depth;
+                }
+                Err(error) => eprintln!("Ignoring --depth {}: {}", depth, error),
+            }
+        }
+        players
+    }
+
+    /// The color buttons and typed commands act for: the player to move if
+    /// they sit at this screen, otherwise the human on the other side
+    pub fn acting_color(&self, turn: PieceColor) -> Option<PieceColor> {
+        if self.get(turn) == PlayerKind::Human {
+            Some(turn)
+        } else if self.get(turn.opponent()) == PlayerKind::Human {
+            Some(turn.opponent())
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Self {
+            white: PlayerKind::Human,
+            black: PlayerKind::Human,
+            white_engine: SearchLimits::default(),
+            black_engine: SearchLimits::default(),
+        }
+    }
+}
+
+/// Something a player does outside of moving a piece
+#[derive(Clone, Copy)]
+pub enum GameAction {
+    Resign(PieceColor),
+    OfferDraw(PieceColor),
+    AcceptDraw(PieceColor),
+    DeclineDraw(PieceColor),
+}
+
+/// A line of chat from one of the players
+#[derive(Clone)]
+pub struct ChatMessage {
+    pub color: PieceColor,
+    pub text: String,
+}
+
+pub struct GamePlugin;
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameOver>()
+            .init_resource::<DrawOffer>()
+            .init_resource::<Players>()
+            .add_event::<GameAction>()
+            .add_event::<ChatMessage>()
+            .add_system(handle_game_actions.system().label("handle_game_actions"))
+            .add_system(expire_draw_offer.system())
+            .add_system(report_game_over.system());
+    }
+}
+
+fn handle_game_actions(
+    mut events: EventReader<GameAction>,
+    mut game_over: ResMut<GameOver>,
+    mut draw_offer: ResMut<DrawOffer>,
+    mut history: ResMut<MoveHistory>,
+) {
+    for action in events.iter() {
+        if game_over.0.is_some() {
+            continue;
+        }
+
+        match *action {
+            GameAction::Resign(color) => {
+                draw_offer.offered_by = None;
+                game_over.0 = Some(GameOutcome {
+                    result: GameResult::win_for(color.opponent()),
+                    termination: Termination::Resignation,
+                });
+            }
+            GameAction::OfferDraw(color) => {
+                if draw_offer.offered_by.is_some() {
+                    continue;
+                }
+                draw_offer.offered_by = Some(color);
+                // An offer made after moving belongs to that move, otherwise
+                // it gets attached to the offering player's next move
+                if history.last_mover() == Some(color) {
+                    if let Some(record) = history.moves.last_mut() {
+                        record.draw_offered = true;
+                    }
+                }
+            }
+            GameAction::AcceptDraw(color) => {
+                if draw_offer.offered_by == Some(color.opponent()) {
+                    draw_offer.offered_by = None;
+                    game_over.0 = Some(GameOutcome {
+                        result: GameResult::Draw,
+                        termination: Termination::DrawAgreement,
+                    });
+                }
+            }
+            GameAction::DeclineDraw(color) => {
+                if draw_offer.offered_by == Some(color.opponent()) {
+                    draw_offer.offered_by = None;
+                }
+            }
+        }
+    }
+}
+
+/// Making a move instead of answering declines a pending draw offer
+fn expire_draw_offer(history: Res<MoveHistory>, mut draw_offer: ResMut<DrawOffer>) {
+    if !history.is_changed() {
+        return;
+    }
+
+    if let (Some(offered_by), Some(last_mover)) = (draw_offer.offered_by, history.last_mover()) {
+        if last_mover != offered_by {
+            draw_offer.offered_by = None;
+        }
+    }
+}
+
+fn report_game_over(
+    game_over: Res<GameOver>,
+    history: Res<MoveHistory>,
+    (players, seats): (Res<Players>, Option<Res<Seats>>),
+    openings: Option<Res<Openings>>,
+) {
+    if !game_over.is_changed() {
+        return;
+    }
+
+    if let Some(outcome) = &game_over.0 {
+        // Worked out here rather than read from CurrentOpening, which may not
+        // have seen the last move yet
+        let opening = openings.as_ref().and_then(|openings| {
+            history
+                .hashes()
+                .ok()
+                .and_then(|hashes| openings.classify(&hashes).cloned())
+        });
+        println!("{}. Thanks for playing!", outcome.description());
+        let (white, black) = match seats {
+            Some(seats) => seats.names(&players),
+            None => Seats::default().names(&players),
+        };
+        println!(
+            "{}",
+            history.to_pgn((&white, &black), Some(outcome), opening.as_ref())
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::{app::Events, prelude::*};
+
+use crate::{
+    animation::Animating,
+    board::{BoardPlugin, MoveEvent, PlayerTurn},
+    check::CheckPlugin,
+    clock::ClockPlugin,
+    computer::ComputerPlugin,
+    game::GamePlugin,
+    history::HistoryPlugin,
+    openings::OpeningsPlugin,
+    pieces::*,
+};
+
+/// Frames a move takes to be applied and its captures and check to be seen
+const MOVE_FRAMES: usize = 2;
+
+/// Present when the app runs without a window, renderer or picking. Plugins
+/// leave out their meshes, materials and input systems when they find it.
+pub struct Headless;
+
+pub fn is_headless(app: &mut AppBuilder) -> bool {
+    app.world().contains_resource::<Headless>()
+}
+
+/// The game logic on `MinimalPlugins`, for playing games in tests and scripts
+pub fn headless_app() -> AppBuilder {
+    let mut app = App::build();
+    app.insert_resource(Headless)
+        .add_plugins(MinimalPlugins)
+        // Nothing is animated without a renderer
+        .init_resource::<Animating>()
+        .add_plugin(BoardPlugin)
+        .add_plugin(PiecesPlugin)
+        .add_plugin(CheckPlugin)
+        .add_plugin(GamePlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(ComputerPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(OpeningsPlugin);
+    app
+}
+
+/// Play a move in SAN or UCI for the player to move and run the frames it takes
+pub fn play_move(app: &mut App, input: &str) -> Result<(), MoveParseError> {
+    let world = &mut app.world;
+    let pieces: Vec<(Entity, Piece)> = world
+        .query::<(Entity, &Piece)>()
+        .iter(world)
+        .map(|(entity, piece)| (entity, *piece))
+        .collect();
+    let turn = world.get_resource::<PlayerTurn>().unwrap().0;
+    let board_state = world.get_resource::<BoardState>().unwrap();
+
+    let board: Vec<Piece> = pieces.iter().map(|(_, piece)| *piece).collect();
+    let chess_move = parse_move(input, turn, &board, board_state)?;
+    let piece = pieces
+        .iter()
+        .find(|(_, piece)| (piece.x, piece.y) == chess_move.from)
+        .map(|(entity, _)| *entity)
+        .unwrap();
+
+    world
+        .get_resource_mut::<Events<MoveEvent>>()
+        .unwrap()
+        .send(MoveEvent { piece, chess_move });
+    for _ in 0..MOVE_FRAMES {
+        app.update();
+    }
+    Ok(())
+}
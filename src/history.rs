@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::GameOutcome,
+    headless::is_headless,
+    openings::Opening,
+    pieces::{
+        parse_move, spawn_piece, square_name, Piece, PieceColor, PieceMaterials, PieceSets,
+        Position, STARTING_FEN,
+    },
+};
+
+/// A half-move as it was played on the board
+#[derive(Clone)]
+pub struct MoveRecord {
+    pub color: PieceColor,
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    // Standard algebraic notation, worked out before the move was made
+    pub san: String,
+    // The player offered a draw along with this move
+    pub draw_offered: bool,
+    // The pieces on the board after the move
+    pub position: Vec<Piece>,
+}
+
+impl MoveRecord {
+    /// The move in UCI notation, e.g. "e7e8q"
+    pub fn uci(&self) -> String {
+        // Promotions are the only moves with an equals sign in SAN, as in "e8=Q+"
+        let promotion = self
+            .san
+            .split('=')
+            .nth(1)
+            .and_then(|rest| rest.chars().next())
+            .map_or(String::new(), |letter| {
+                letter.to_ascii_lowercase().to_string()
+            });
+        format!(
+            "{}{}{}",
+            square_name(self.from),
+            square_name(self.to),
+            promotion
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct MoveHistory {
+    // FEN of the position the game started from, none for the usual one
+    pub start: Option<String>,
+    pub moves: Vec<MoveRecord>,
+}
+
+impl MoveHistory {
+    /// Returns the color that made the last move, if any move has been played
+    pub fn last_mover(&self) -> Option<PieceColor> {
+        self.moves.last().map(|record| record.color)
+    }
+
+    /// The position after the first `plies` moves, played through from the start
+    pub fn position(&self, plies: usize) -> Result<Position, String> {
+        let mut position = Position::from_fen(self.start.as_deref().unwrap_or(STARTING_FEN))
+            .map_err(|error| error.to_string())?;
+        for record in self.moves.iter().take(plies) {
+            let chess_move = parse_move(
+                &record.uci(),
+                position.turn,
+                &position.pieces,
+                &position.state,
+            )
+            .map_err(|error| error.to_string())?;
+            position.play(&chess_move);
+        }
+        Ok(position)
+    }
+
+    /// The current position and how many times it has been reached
+    pub fn repetitions(&self) -> Result<(Position, u32), String> {
+        let mut position = self.position(0)?;
+        let mut keys = vec![position.key()];
+        for record in self.moves.iter() {
+            let chess_move = parse_move(
+                &record.uci(),
+                position.turn,
+                &position.pieces,
+                &position.state,
+            )
+            .map_err(|error| error.to_string())?;
+            position.play(&chess_move);
+            keys.push(position.key());
+        }
+        let current = position.key();
+        let repetitions = keys.iter().filter(|key| **key == current).count() as u32;
+        Ok((position, repetitions))
+    }
+
+    /// Zobrist hashes of the start position and of the position after each move
+    pub fn hashes(&self) -> Result<Vec<u64>, String> {
+        let mut position = self.position(0)?;
+        let mut hashes = vec![position.zobrist()];
+        for record in self.moves.iter() {
+            let chess_move = parse_move(
+                &record.uci(),
+                position.turn,
+                &position.pieces,
+                &position.state,
+            )
+            .map_err(|error| error.to_string())?;
+            position.play(&chess_move);
+            hashes.push(position.zobrist());
+        }
+        Ok(hashes)
+    }
+
+    /// Export the game as PGN, including the players' names, the result, how
+    /// the game ended and the opening played
+    pub fn to_pgn(
+        &self,
+        (white, black): (&str, &str),
+        outcome: Option<&GameOutcome>,
+        opening: Option<&Opening>,
+    ) -> String {
+        let result = outcome.map_or("*", |outcome| outcome.result.pgn());
+
+        let mut pgn = String::new();
+        pgn.push_str("[Event \"Casual game\"]\n");
+        pgn.push_str("[Site \"rust_chess\"]\n");
+        pgn.push_str(&format!("[White \"{}\"]\n", white));
+        pgn.push_str(&format!("[Black \"{}\"]\n", black));
+        pgn.push_str(&format!("[Result \"{}\"]\n", result));
+        if let Some(fen) = &self.start {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+        }
+        if let Some(opening) = opening {
+            pgn.push_str(&format!("[ECO \"{}\"]\n", opening.eco));
+            pgn.push_str(&format!("[Opening \"{}\"]\n", opening.name));
+        }
+        if let Some(outcome) = outcome {
+            pgn.push_str(&format!(
+                "[Termination \"{}\"]\n",
+                outcome.termination.pgn()
+            ));
+        }
+        pgn.push('\n');
+
+        let mut movetext = Vec::new();
+        for (index, record) in self.moves.iter().enumerate() {
+            if index % 2 == 0 {
+                movetext.push(format!("{}.", index / 2 + 1));
+            }
+            movetext.push(record.san.clone());
+            if record.draw_offered {
+                movetext.push("(=)".to_string());
+            }
+        }
+        if let Some(outcome) = outcome {
+            movetext.push(format!("{{{}}}", outcome.description()));
+        }
+        movetext.push(result.to_string());
+
+        pgn.push_str(&movetext.join(" "));
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+/// The move whose position is shown on the board instead of the live one
+#[derive(Default)]
+pub struct HistoryView {
+    pub ply: Option<usize>,
+}
+
+/// A read-only piece spawned to show a past position
+pub struct PastPositionPiece(pub Piece);
+
+pub struct HistoryPlugin;
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MoveHistory>()
+            .init_resource::<HistoryView>();
+        if !is_headless(app) {
+            app.add_system(show_past_position.system());
+        }
+    }
+}
+
+/// Show the position after the viewed move next to the live pieces, which are
+/// hidden meanwhile
+fn show_past_position(
+    mut commands: Commands,
+    view: Res<HistoryView>,
+    history: Res<MoveHistory>,
+    asset_server: Res<AssetServer>,
+    materials: Res<PieceMaterials>,
+    piece_sets: Res<PieceSets>,
+    past_pieces_query: Query<Entity, With<PastPositionPiece>>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    for entity in past_pieces_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if let Some(record) = view.ply.and_then(|ply| history.moves.get(ply)) {
+        for piece in record.position.iter() {
+            let entity = spawn_piece(
+                &mut commands,
+                materials.get(piece.color),
+                piece.color,
+                piece.piece_type,
+                (piece.x, piece.y),
+                piece_sets.current(),
+                &asset_server,
+            );
+            // Without a Piece component the game logic doesn't see it
+            commands
+                .entity(entity)
+                .remove::<Piece>()
+                .insert(PastPositionPiece(*piece));
+        }
+    }
+}
@@ -0,0 +1,26 @@
+pub mod animation;
+pub mod board;
+pub mod camera;
+pub mod captured;
+pub mod check;
+pub mod clock;
+pub mod computer;
+pub mod coordinates;
+pub mod database;
+pub mod drag;
+pub mod engine;
+pub mod game;
+pub mod headless;
+pub mod history;
+pub mod move_input;
+pub mod network;
+pub mod openings;
+pub mod pieces;
+pub mod profiles;
+pub mod render_mode;
+pub mod save;
+pub mod server;
+pub mod sound;
+pub mod theme;
+pub mod tournament;
+pub mod ui;
@@ -1,18 +1,31 @@
 use bevy::prelude::*;
-mod board;
-use board::BoardPlugin;
-
-mod pieces;
-use pieces::PiecesPlugin;
-
-mod ui;
-use ui::UIPlugin;
-
-mod check;
-use check::CheckPlugin;
-
 use bevy_mod_picking::*;
 
+use rust_chess::{
+    animation::AnimationPlugin,
+    board::BoardPlugin,
+    camera::CameraPlugin,
+    captured::CapturedPlugin,
+    check::CheckPlugin,
+    clock::{ClockPlugin, GameClock},
+    computer::ComputerPlugin,
+    coordinates::CoordinatesPlugin,
+    database::DatabasePlugin,
+    drag::DragPlugin,
+    game::{GamePlugin, Players},
+    history::HistoryPlugin,
+    move_input::MoveInputPlugin,
+    network::{NetworkPlugin, NetworkRole},
+    openings::OpeningsPlugin,
+    pieces::PiecesPlugin,
+    profiles::ProfilesPlugin,
+    render_mode::{RenderMode, RenderModePlugin},
+    save::SavePlugin,
+    sound::SoundPlugin,
+    theme::ThemePlugin,
+    ui::UIPlugin,
+};
+
 fn main() {
     App::build()
         .insert_resource(Msaa { samples: 4 })
@@ -22,6 +35,10 @@ fn main() {
             height: 700.,
             ..Default::default()
         })
+        .insert_resource(RenderMode::from_args())
+        .insert_resource(GameClock::from_args())
+        .insert_resource(NetworkRole::from_args())
+        .insert_resource(Players::from_args())
         .add_plugins(DefaultPlugins)
         .add_plugin(PickingPlugin)
         .add_plugin(DebugCursorPickingPlugin)
@@ -32,21 +49,29 @@ fn main() {
         .add_plugin(PiecesPlugin)
         .add_plugin(UIPlugin)
         .add_plugin(CheckPlugin)
+        .add_plugin(GamePlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(ComputerPlugin)
+        .add_plugin(NetworkPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(OpeningsPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(DatabasePlugin)
+        .add_plugin(ProfilesPlugin)
+        .add_plugin(CapturedPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(CoordinatesPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(ThemePlugin)
+        .add_plugin(RenderModePlugin)
+        .add_plugin(DragPlugin)
+        .add_plugin(MoveInputPlugin)
         .add_startup_system(setup.system())
         .run();
 }
 
 fn setup(mut commands: Commands) {
-    // Camera
-    commands
-        .spawn_bundle(PerspectiveCameraBundle {
-            transform: Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(),
-                Vec3::new(-7.0, 20.0, 4.0),
-            )),
-            ..Default::default()
-        })
-        .insert_bundle(PickingCameraBundle::default());
     // Light
     commands.spawn_bundle(LightBundle {
         transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
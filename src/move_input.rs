@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+
+use crate::{
+    animation::Animating,
+    board::{MoveEvent, PlayerTurn, ResetSelectedEvent},
+    game::{ChatMessage, GameOver, PlayerKind, Players},
+    history::HistoryView,
+    pieces::*,
+};
+
+/// Number of legal moves listed under the input
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Moves typed in algebraic notation, opened and submitted with Enter
+#[derive(Default)]
+pub struct MoveInput {
+    pub active: bool,
+    pub text: String,
+    // Legal moves in SAN starting with the typed text
+    pub suggestions: Vec<String>,
+    // Why the last submitted move was rejected
+    pub error: Option<String>,
+    submitted: bool,
+}
+
+// Component to mark the Text entity showing the move input
+struct MoveInputText;
+
+pub struct MoveInputPlugin;
+impl Plugin for MoveInputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MoveInput>()
+            .add_startup_system(init_move_input_text.system())
+            .add_system(type_move_input.system().label("type_move_input"))
+            .add_system(
+                update_suggestions
+                    .system()
+                    .label("update_suggestions")
+                    .after("type_move_input"),
+            )
+            .add_system(
+                submit_move_input
+                    .system()
+                    .label("submit_move_input")
+                    .after("update_suggestions"),
+            )
+            .add_system(move_input_text_update.system().after("submit_move_input"));
+    }
+}
+
+fn init_move_input_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(90.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: String::new(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 18.0,
+                            color: Color::GOLD,
+                        },
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(MoveInputText);
+}
+
+fn type_move_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut input: ResMut<MoveInput>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        if input.active {
+            input.submitted = true;
+        } else {
+            input.active = true;
+            input.text.clear();
+            input.error = None;
+        }
+        // Don't pick up the newline as a character
+        characters.iter().for_each(drop);
+        return;
+    }
+
+    if !input.active {
+        characters.iter().for_each(drop);
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        input.active = false;
+        input.text.clear();
+        input.error = None;
+    } else if keyboard_input.just_pressed(KeyCode::Back) {
+        input.text.pop();
+    } else if keyboard_input.just_pressed(KeyCode::Tab) {
+        // Complete as far as all suggestions agree
+        if let Some(first) = input.suggestions.first() {
+            let mut completion = first.clone();
+            for suggestion in input.suggestions.iter().skip(1) {
+                while !suggestion.starts_with(&completion) {
+                    completion.pop();
+                }
+            }
+            if completion.len() > input.text.len() {
+                input.text = completion;
+            }
+        }
+    }
+
+    for character in characters.iter() {
+        if character.char.is_ascii_graphic() {
+            input.text.push(character.char);
+            input.error = None;
+        }
+    }
+}
+
+fn update_suggestions(
+    mut input: ResMut<MoveInput>,
+    turn: Res<PlayerTurn>,
+    board_state: Res<BoardState>,
+    pieces_query: Query<&Piece>,
+) {
+    if !input.active || !(input.is_changed() || turn.is_changed()) {
+        return;
+    }
+
+    let pieces: Vec<Piece> = pieces_query.iter().cloned().collect();
+    let suggestions: Vec<String> = all_legal_moves(turn.0, &pieces, &board_state)
+        .iter()
+        .map(|chess_move| san(chess_move, &pieces, &board_state))
+        .filter(|san| san.starts_with(input.text.as_str()))
+        .collect();
+
+    // Only touch the resource when something changed, it's what triggers this system
+    if input.suggestions != suggestions {
+        input.suggestions = suggestions;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn submit_move_input(
+    mut input: ResMut<MoveInput>,
+    turn: Res<PlayerTurn>,
+    players: Res<Players>,
+    game_over: Res<GameOver>,
+    history_view: Res<HistoryView>,
+    animating: Res<Animating>,
+    board_state: Res<BoardState>,
+    pieces_query: Query<(Entity, &Piece)>,
+    mut move_event: EventWriter<MoveEvent>,
+    mut reset_selected_event: EventWriter<ResetSelectedEvent>,
+    mut chat: EventWriter<ChatMessage>,
+) {
+    if !input.submitted {
+        return;
+    }
+    input.submitted = false;
+
+    // Text after a slash is said to the other player instead of played
+    if let Some(text) = input.text.strip_prefix('/') {
+        let has_remote = [PieceColor::White, PieceColor::Black]
+            .iter()
+            .any(|color| players.get(*color) == PlayerKind::Remote);
+        match players.acting_color(turn.0) {
+            Some(color) if has_remote => {
+                chat.send(ChatMessage {
+                    color,
+                    text: text.trim().to_string(),
+                });
+                input.active = false;
+                input.text.clear();
+                input.error = None;
+            }
+            _ => input.error = Some("There's nobody to chat with".to_string()),
+        }
+        return;
+    }
+
+    if game_over.0.is_some() {
+        input.error = Some("The game is over".to_string());
+        return;
+    }
+    if history_view.ply.is_some() {
+        input.error = Some("Go back to the live position to move".to_string());
+        return;
+    }
+    if animating.0 {
+        input.error = Some("Wait for the last move to finish".to_string());
+        return;
+    }
+    if players.get(turn.0) != PlayerKind::Human {
+        input.error = Some("It's not your turn".to_string());
+        return;
+    }
+
+    let pieces: Vec<Piece> = pieces_query.iter().map(|(_, piece)| *piece).collect();
+    match parse_move(&input.text, turn.0, &pieces, &board_state) {
+        Ok(chess_move) => {
+            if let Some((piece_entity, _piece)) = pieces_query
+                .iter()
+                .find(|(_, piece)| (piece.x, piece.y) == chess_move.from)
+            {
+                move_event.send(MoveEvent {
+                    piece: piece_entity,
+                    chess_move,
+                });
+                reset_selected_event.send(ResetSelectedEvent);
+            }
+            input.active = false;
+            input.text.clear();
+            input.error = None;
+        }
+        Err(error) => input.error = Some(error.to_string()),
+    }
+}
+
+fn move_input_text_update(input: Res<MoveInput>, mut query: Query<&mut Text, With<MoveInputText>>) {
+    if !input.is_changed() {
+        return;
+    }
+
+    let (line, details) = if input.active {
+        let details = match &input.error {
+            Some(error) => error.clone(),
+            None => {
+                let mut details = input
+                    .suggestions
+                    .iter()
+                    .take(MAX_SUGGESTIONS)
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                if input.suggestions.len() > MAX_SUGGESTIONS {
+                    details.push_str(" ...");
+                }
+                details
+            }
+        };
+        (format!("Move: {}_\n", input.text), details)
+    } else {
+        (String::new(), "Press Enter to type a move".to_string())
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = line.clone();
+        text.sections[1].value = details.clone();
+    }
+}
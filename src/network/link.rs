@@ -0,0 +1,344 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TryIter};
+
+use super::Message;
+
+/// How often the link looks for new connections and dead ones
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Quiet time after which a ping is sent
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+/// Quiet time after which the other side is given up on and reconnected to
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Wait between attempts to reach the host
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest hello read from a connection wanting to take over, enough for
+/// every move of a long game
+const MAX_HELLO_BYTES: u64 = 64 * 1024;
+
+pub enum LinkEvent {
+    Connected(SocketAddr),
+    Disconnected,
+    Message(Message),
+}
+
+enum LinkCommand {
+    Send(Message),
+    // Only let a new connection take over when it's back for this game
+    Expect(u64),
+    // Hang up the current connection, after which the link reconnects
+    Drop,
+    Stop,
+}
+
+/// Where connections come from
+enum Endpoint {
+    Listener(TcpListener),
+    Address(String),
+}
+
+impl Endpoint {
+    /// A new connection if there is one, without waiting for it
+    fn poll(&self, connected: bool) -> Option<TcpStream> {
+        match self {
+            Endpoint::Listener(listener) => listener.accept().ok().map(|(stream, _)| stream),
+            Endpoint::Address(_) if connected => None,
+            Endpoint::Address(address) => address
+                .to_socket_addrs()
+                .ok()?
+                .find_map(|address| TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).ok()),
+        }
+    }
+
+    fn retry_delay(&self) -> Duration {
+        match self {
+            Endpoint::Listener(_) => POLL_INTERVAL,
+            Endpoint::Address(_) => RECONNECT_DELAY,
+        }
+    }
+}
+
+/// A connection ready to be served, with what was read from it so far
+struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    // The hello a replacement proved itself with, still to be passed on
+    hello: Option<Message>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        let reader = BufReader::new(prepare(&stream)?);
+        Ok(Self {
+            stream,
+            reader,
+            hello: None,
+        })
+    }
+
+    /// A connection that has said hello, waiting for it as long as the other
+    /// side may stay silent
+    fn greeted(stream: TcpStream) -> Option<Self> {
+        let mut connection = Self::new(stream).ok()?;
+        let mut line = String::new();
+        (&mut connection.reader)
+            .take(MAX_HELLO_BYTES)
+            .read_line(&mut line)
+            .ok()?;
+        match Message::decode(&line) {
+            Ok(hello @ Message::Hello { .. }) => connection.hello = Some(hello),
+            _ => return None,
+        }
+        Some(connection)
+    }
+
+    /// Whether it may take over from the connection being served: it comes
+    /// back for the same game, once the other player is known
+    fn takes_over(&self, expected_game: Option<u64>) -> bool {
+        match &self.hello {
+            Some(Message::Hello { game, .. }) => expected_game.is_none() || *game == expected_game,
+            _ => false,
+        }
+    }
+
+    /// Turn it away, saying why
+    fn refuse(mut self, reason: &str) {
+        let reject = Message::Reject {
+            reason: reason.to_string(),
+        };
+        let _ = writeln!(self.stream, "{}", reject.encode());
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// A connection to the other player's game that keeps coming back. It runs on
+/// its own thread, reconnecting whenever the connection drops, and reports
+/// what happens as events.
+pub struct Link {
+    commands: Sender<LinkCommand>,
+    events: Receiver<LinkEvent>,
+    pub local_address: Option<SocketAddr>,
+}
+
+impl Link {
+    /// Listen for the other player on all interfaces
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let local_address = listener.local_addr().ok();
+        let mut link = Self::start(Endpoint::Listener(listener));
+        link.local_address = local_address;
+        Ok(link)
+    }
+
+    /// Connect to a host given as "address:port"
+    pub fn join(address: &str) -> Self {
+        Self::start(Endpoint::Address(address.to_string()))
+    }
+
+    /// A link that never connects, for when there's nothing to connect with
+    pub fn closed() -> Self {
+        let (commands, _) = crossbeam_channel::unbounded();
+        let (_, events) = crossbeam_channel::unbounded();
+        Self {
+            commands,
+            events,
+            local_address: None,
+        }
+    }
+
+    fn start(endpoint: Endpoint) -> Self {
+        let (commands, command_receiver) = crossbeam_channel::unbounded();
+        let (event_sender, events) = crossbeam_channel::unbounded();
+        thread::spawn(move || run(endpoint, command_receiver, event_sender));
+        Self {
+            commands,
+            events,
+            local_address: None,
+        }
+    }
+
+    /// Send a message if connected, it's dropped otherwise
+    pub fn send(&self, message: Message) {
+        let _ = self.commands.send(LinkCommand::Send(message));
+    }
+
+    /// Keep the connection to the other player of `game` when someone else
+    /// connects, rather than handing over to them
+    pub fn expect_game(&self, game: u64) {
+        let _ = self.commands.send(LinkCommand::Expect(game));
+    }
+
+    /// Hang up, as if the connection had dropped
+    pub fn drop_connection(&self) {
+        let _ = self.commands.send(LinkCommand::Drop);
+    }
+
+    /// Hang up for good
+    pub fn stop(&self) {
+        let _ = self.commands.send(LinkCommand::Stop);
+    }
+
+    pub fn events(&self) -> TryIter<'_, LinkEvent> {
+        self.events.try_iter()
+    }
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(endpoint: Endpoint, commands: Receiver<LinkCommand>, events: Sender<LinkEvent>) {
+    let mut next_connection = None;
+    let mut expected_game = None;
+    loop {
+        let connection = next_connection.take().or_else(|| {
+            endpoint
+                .poll(false)
+                .and_then(|stream| Connection::new(stream).ok())
+        });
+        let connection = match connection {
+            Some(connection) => connection,
+            None => {
+                // Nothing is sent while disconnected, the other side catches
+                // up from the hello once back
+                match commands.recv_timeout(endpoint.retry_delay()) {
+                    Ok(LinkCommand::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                    Ok(LinkCommand::Expect(game)) => expected_game = Some(game),
+                    _ => (),
+                }
+                continue;
+            }
+        };
+
+        match serve(
+            &endpoint,
+            connection,
+            &commands,
+            &events,
+            &mut expected_game,
+        ) {
+            Ok(Some(connection)) => next_connection = Some(connection),
+            Ok(None) => (),
+            Err(Stop) => return,
+        }
+        if events.send(LinkEvent::Disconnected).is_err() {
+            return;
+        }
+    }
+}
+
+struct Stop;
+
+/// Pass messages over a connection until it drops. Returns the connection
+/// replacing it, if that's why it ended.
+fn serve(
+    endpoint: &Endpoint,
+    connection: Connection,
+    commands: &Receiver<LinkCommand>,
+    events: &Sender<LinkEvent>,
+    expected_game: &mut Option<u64>,
+) -> Result<Option<Connection>, Stop> {
+    let Connection {
+        mut stream,
+        reader,
+        hello,
+    } = connection;
+    let peer = match stream.peer_addr() {
+        Ok(peer) => peer,
+        Err(_) => return Ok(None),
+    };
+    if events.send(LinkEvent::Connected(peer)).is_err() {
+        return Err(Stop);
+    }
+    if let Some(hello) = hello {
+        if events.send(LinkEvent::Message(hello)).is_err() {
+            return Err(Stop);
+        }
+    }
+
+    let closed = Arc::new(AtomicBool::new(false));
+    {
+        let closed = closed.clone();
+        let events = events.clone();
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                match Message::decode(&line) {
+                    Ok(Message::Ping) | Err(_) => (),
+                    Ok(message) => {
+                        if events.send(LinkEvent::Message(message)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            closed.store(true, Ordering::Relaxed);
+        });
+    }
+
+    // A new connection is the other side coming back before the old one timed
+    // out, or someone else. It only takes over once its hello checks out.
+    let (candidate_sender, candidates) = crossbeam_channel::unbounded();
+    let mut last_write = Instant::now();
+    let result = loop {
+        if closed.load(Ordering::Relaxed) {
+            break Ok(None);
+        }
+        if let Some(stream) = endpoint.poll(true) {
+            let candidate_sender = candidate_sender.clone();
+            thread::spawn(move || {
+                if let Some(candidate) = Connection::greeted(stream) {
+                    let _ = candidate_sender.send(candidate);
+                }
+            });
+        }
+        if let Ok(candidate) = candidates.try_recv() {
+            if candidate.takes_over(*expected_game) {
+                break Ok(Some(candidate));
+            }
+            candidate.refuse("Someone else is playing this game");
+        }
+
+        let message = match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(LinkCommand::Send(message)) => message,
+            Ok(LinkCommand::Expect(game)) => {
+                *expected_game = Some(game);
+                continue;
+            }
+            Ok(LinkCommand::Drop) => break Ok(None),
+            Ok(LinkCommand::Stop) | Err(RecvTimeoutError::Disconnected) => break Err(Stop),
+            Err(RecvTimeoutError::Timeout) if last_write.elapsed() >= HEARTBEAT_INTERVAL => {
+                Message::Ping
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+        };
+        if writeln!(stream, "{}", message.encode()).is_err() {
+            break Ok(None);
+        }
+        last_write = Instant::now();
+    };
+
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+
+/// Set the connection up for line-by-line messages, returning the half to read from
+fn prepare(stream: &TcpStream) -> io::Result<TcpStream> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    // Pings arrive well within this, so silence means the other side is gone
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+    stream.try_clone()
+}
@@ -0,0 +1,8 @@
+mod protocol;
+pub use protocol::*;
+
+mod link;
+pub use link::*;
+
+mod session;
+pub use session::*;
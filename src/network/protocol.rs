@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::TimeControl,
+    game::{GameResult, Termination},
+    pieces::PieceColor,
+};
+
+/// Bumped whenever a message changes in a way older games can't read. Both
+/// sides must speak the same version to play.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Time left on both clocks
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClockState {
+    pub white: Duration,
+    pub black: Duration,
+}
+
+/// What travels between the two games, or between a game and a server, one
+/// JSON object per line or per WebSocket text message. docs/protocol.md
+/// describes each one for clients written in other languages.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Sent by both sides first thing on every connection, with all they know
+    /// of the game so the other side can catch up after a dropped link
+    Hello {
+        version: u32,
+        // Tells the games apart across reconnections, none from a player
+        // joining for the first time
+        #[serde(default)]
+        game: Option<u64>,
+        // The color the receiver plays, only sent by the host
+        #[serde(default)]
+        your_color: Option<PieceColor>,
+        #[serde(default)]
+        time_control: Option<TimeControl>,
+        // Every move played so far in UCI notation
+        #[serde(default)]
+        moves: Vec<String>,
+        #[serde(default)]
+        clock: Option<ClockState>,
+        #[serde(default)]
+        draw_offer: Option<PieceColor>,
+        #[serde(default)]
+        result: Option<(GameResult, Termination)>,
+        // Handed out by a server with the color, and sent back to take the
+        // same seat after reconnecting
+        #[serde(default)]
+        seat: Option<u64>,
+        // The position after the moves, from a server for clients that don't
+        // keep track of the rules themselves
+        #[serde(default)]
+        fen: Option<String>,
+    },
+    /// Asks a server for an opponent wanting the same time control
+    Seek {
+        #[serde(default)]
+        time_control: Option<TimeControl>,
+    },
+    /// Asks a server for the moves of a game as they're played, or of the
+    /// latest game when none is given
+    Watch {
+        #[serde(default)]
+        game: Option<u64>,
+    },
+    /// Opens a game on a server for someone to join by its id, playing
+    /// `color` or whichever a coin toss gives
+    Create {
+        #[serde(default)]
+        time_control: Option<TimeControl>,
+        #[serde(default)]
+        color: Option<PieceColor>,
+    },
+    /// Takes the open seat in a game opened with `Create`
+    Join {
+        game: u64,
+    },
+    /// Asks a server for a fresh hello with the game as it stands
+    GetState,
+    /// The connection was refused, e.g. for speaking another version
+    Reject {
+        reason: String,
+    },
+    /// Something sent couldn't be done, e.g. an illegal move. The connection
+    /// stays open.
+    Error {
+        reason: String,
+    },
+    Move {
+        // Half-moves played before this one, to drop any sent twice
+        ply: usize,
+        uci: String,
+        // Time left on the mover's clock after the increment
+        #[serde(default)]
+        clock: Option<Duration>,
+        // The position after the move, from a server
+        #[serde(default)]
+        fen: Option<String>,
+    },
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    /// The game ended on the sender's side, e.g. by their flag falling
+    GameOver {
+        result: GameResult,
+        termination: Termination,
+    },
+    Chat {
+        text: String,
+    },
+    /// Keeps the link busy so a dead one is noticed
+    Ping,
+}
+
+impl Message {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("Messages are always serializable")
+    }
+
+    /// Read a line from the other side. Fails for messages from a newer
+    /// version, which are ignored.
+    pub fn decode(line: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(line)
+    }
+}
@@ -0,0 +1,846 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    animation::Animating,
+    board::{MoveEvent, PlayerTurn},
+    clock::GameClock,
+    game::{ChatMessage, DrawOffer, GameAction, GameOutcome, GameOver, PlayerKind, Players},
+    headless::is_headless,
+    history::MoveHistory,
+    pieces::*,
+};
+
+use super::{ClockState, Link, LinkEvent, Message, PROTOCOL_VERSION};
+
+/// Port used when none is given
+pub const DEFAULT_PORT: u16 = 7878;
+/// Chat lines shown above the move input
+const CHAT_LINES: usize = 6;
+
+/// Whether this game is played against someone on another computer
+#[derive(Clone, PartialEq, Default)]
+pub enum NetworkRole {
+    #[default]
+    Offline,
+    // Wait for the other player on a port, playing `color`
+    Host {
+        port: u16,
+        color: PieceColor,
+    },
+    // Connect to a host at "address:port"
+    Join {
+        address: String,
+    },
+    // Ask a game server for an opponent with the same time control
+    Seek {
+        address: String,
+    },
+    // Follow a game on a game server, the latest one when none is given
+    Watch {
+        address: String,
+        game: Option<u64>,
+    },
+}
+
+impl NetworkRole {
+    /// Reads "--host [port] [--color black]" or "--join address[:port]" for
+    /// playing directly, or "--server address[:port]" and
+    /// "--watch address[:port] [game]" for a game server
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let value_after = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .filter(|value| !value.starts_with("--"))
+        };
+
+        if args.iter().any(|arg| arg == "--host") {
+            let port = value_after("--host")
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(DEFAULT_PORT);
+            let color = match value_after("--color").map(String::as_str) {
+                Some("black") => PieceColor::Black,
+                _ => PieceColor::White,
+            };
+            NetworkRole::Host { port, color }
+        } else if let Some(address) = value_after("--join") {
+            NetworkRole::Join {
+                address: with_port(address),
+            }
+        } else if let Some(address) = value_after("--server") {
+            NetworkRole::Seek {
+                address: with_port(address),
+            }
+        } else if let Some(address) = value_after("--watch") {
+            let game = args
+                .iter()
+                .skip_while(|arg| *arg != "--watch")
+                .nth(2)
+                .and_then(|game| game.parse().ok());
+            NetworkRole::Watch {
+                address: with_port(address),
+                game,
+            }
+        } else {
+            NetworkRole::Offline
+        }
+    }
+}
+
+fn with_port(address: &str) -> String {
+    if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum ConnectionStatus {
+    // Nobody has connected yet
+    Waiting,
+    Connected(SocketAddr),
+    // The connection dropped and is being made again
+    Lost,
+    // Not playing, because the other side refused, e.g. for running another
+    // version, or because the link couldn't be set up
+    Rejected(String),
+}
+
+/// A move from the other side waiting to be played here
+struct PendingMove {
+    ply: usize,
+    uci: String,
+}
+
+/// Clock times from the other side, set once the moves they follow are in
+struct ClockSync {
+    moves: usize,
+    white: Option<Duration>,
+    black: Option<Duration>,
+}
+
+/// The game's link to the other player
+pub struct Network {
+    link: Link,
+    pub role: NetworkRole,
+    // None until the host has told a joining player their color, and for
+    // good when watching
+    pub local_color: Option<PieceColor>,
+    // Tells a returning player's game from a stranger's
+    pub game: Option<u64>,
+    // Given by a server to take the same seat after reconnecting
+    seat: Option<u64>,
+    pub status: ConnectionStatus,
+    // Why the server didn't act on the last thing sent, until the game moves on
+    pub error: Option<String>,
+    // Where the host listens
+    pub local_address: Option<SocketAddr>,
+    // The other side's hello arrived on this connection
+    peer_ready: bool,
+    // Someone has joined the host's game, only they may come back to it
+    peer_joined: bool,
+    // Half-moves the other side is known to have
+    peer_moves: usize,
+    // Half-moves sent along with the hello on this connection
+    hello_moves: usize,
+    pending_moves: VecDeque<PendingMove>,
+    clock_sync: Option<ClockSync>,
+    // Ply of an illegal move from the other side, after which the game was
+    // asked for again
+    resync_ply: Option<usize>,
+}
+
+impl Network {
+    pub fn is_connected(&self) -> bool {
+        self.peer_ready
+    }
+
+    /// Hang up as if the connection had dropped, the link then reconnects
+    pub fn drop_connection(&self) {
+        self.link.drop_connection();
+    }
+
+    fn is_host(&self) -> bool {
+        matches!(self.role, NetworkRole::Host { .. })
+    }
+
+    fn is_watching(&self) -> bool {
+        matches!(self.role, NetworkRole::Watch { .. })
+    }
+
+    fn remote_color(&self) -> Option<PieceColor> {
+        self.local_color.map(|color| color.opponent())
+    }
+
+    /// Whether moves of `color` come over the network, which is all of them
+    /// when watching
+    fn is_remote(&self, color: PieceColor) -> bool {
+        self.is_watching() || self.remote_color() == Some(color)
+    }
+
+    /// Every move known here, played or waiting to be
+    fn known_moves(&self, history: &MoveHistory) -> Vec<String> {
+        history
+            .moves
+            .iter()
+            .map(|record| record.uci())
+            .chain(self.pending_moves.iter().map(|pending| pending.uci.clone()))
+            .collect()
+    }
+
+    fn hello(
+        &self,
+        history: &MoveHistory,
+        clock: &GameClock,
+        draw_offer: &DrawOffer,
+        game_over: &GameOver,
+    ) -> Message {
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            game: self.game,
+            your_color: if self.is_host() {
+                self.remote_color()
+            } else {
+                None
+            },
+            time_control: if self.is_host() {
+                clock.time_control
+            } else {
+                None
+            },
+            moves: self.known_moves(history),
+            clock: clock.time_control.map(|_| ClockState {
+                white: clock.white,
+                black: clock.black,
+            }),
+            draw_offer: draw_offer.offered_by,
+            result: game_over
+                .0
+                .map(|outcome| (outcome.result, outcome.termination)),
+            seat: self.seat,
+            fen: None,
+        }
+    }
+
+    /// What to ask a server for once the hello is out, when not back for a game
+    fn request(&self, clock: &GameClock) -> Option<Message> {
+        if self.game.is_some() {
+            return None;
+        }
+        match &self.role {
+            NetworkRole::Seek { .. } => Some(Message::Seek {
+                time_control: clock.time_control,
+            }),
+            NetworkRole::Watch { game, .. } => Some(Message::Watch { game: *game }),
+            _ => None,
+        }
+    }
+
+    /// Catch up with what the other side knows, or say why the games don't fit together
+    #[allow(clippy::too_many_arguments)]
+    fn welcome(
+        &mut self,
+        hello: Message,
+        history: &MoveHistory,
+        players: &mut Players,
+        clock: &mut GameClock,
+        draw_offer: &DrawOffer,
+        game_over: &mut GameOver,
+        actions: &mut EventWriter<GameAction>,
+    ) -> Result<(), String> {
+        let (version, game, your_color, time_control, moves, clock_state, offer, result, seat) =
+            match hello {
+                Message::Hello {
+                    version,
+                    game,
+                    your_color,
+                    time_control,
+                    moves,
+                    clock,
+                    draw_offer,
+                    result,
+                    seat,
+                    ..
+                } => (
+                    version,
+                    game,
+                    your_color,
+                    time_control,
+                    moves,
+                    clock,
+                    draw_offer,
+                    result,
+                    seat,
+                ),
+                _ => return Err("Expected a hello".to_string()),
+            };
+
+        if version != PROTOCOL_VERSION {
+            return Err(format!(
+                "Protocol version {} can't play with version {}",
+                version, PROTOCOL_VERSION
+            ));
+        }
+
+        if self.is_host() {
+            // Only the first player to join comes without the game
+            if game != self.game && (game.is_some() || self.peer_joined) {
+                return Err("That's a different game".to_string());
+            }
+        } else {
+            if self.game.is_some() && game != self.game {
+                return Err("The host started a different game".to_string());
+            }
+            self.game = game;
+            if seat.is_some() {
+                self.seat = seat;
+            }
+            if !self.is_watching() {
+                let color = your_color.ok_or("The host didn't say which color to play")?;
+                if self.local_color != Some(color) {
+                    self.local_color = Some(color);
+                    *players = network_players(color);
+                }
+            }
+            if time_control.is_some() && clock.time_control != time_control {
+                *clock = GameClock::new(time_control);
+            }
+        }
+
+        let known = self.known_moves(history);
+        let common = known.len().min(moves.len());
+        if known[..common] != moves[..common] {
+            return Err("The games have different moves".to_string());
+        }
+        for (ply, uci) in moves.iter().enumerate().skip(known.len()) {
+            self.pending_moves.push_back(PendingMove {
+                ply,
+                uci: uci.clone(),
+            });
+        }
+        // They catch up from our hello, anything played since is sent as it comes
+        self.peer_moves = self.hello_moves.max(moves.len());
+
+        // Both clocks ran on while apart, each side knows best how long it
+        // took itself, and the side further along knows both
+        if let Some(state) = clock_state {
+            let ahead = moves.len() > known.len() || self.is_watching();
+            let time = |color: PieceColor| match color {
+                PieceColor::White => state.white,
+                PieceColor::Black => state.black,
+            };
+            let sync = |color: PieceColor| {
+                if self.is_remote(color) || ahead {
+                    Some(time(color))
+                } else {
+                    None
+                }
+            };
+            self.clock_sync = Some(ClockSync {
+                moves: moves.len(),
+                white: sync(PieceColor::White),
+                black: sync(PieceColor::Black),
+            });
+        }
+
+        if let Some(remote) = self.remote_color() {
+            if offer == Some(remote) && draw_offer.offered_by.is_none() {
+                actions.send(GameAction::OfferDraw(remote));
+            }
+        }
+        if let (None, Some((result, termination))) = (game_over.0, result) {
+            game_over.0 = Some(GameOutcome {
+                result,
+                termination,
+            });
+        }
+
+        if self.is_host() && !self.peer_joined {
+            self.peer_joined = true;
+            if let Some(game) = self.game {
+                self.link.expect_game(game);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lines said by both players, oldest first
+#[derive(Default)]
+pub struct ChatLog {
+    pub messages: Vec<ChatMessage>,
+}
+
+/// The player at this screen plays `color`, the other side is remote
+fn network_players(color: PieceColor) -> Players {
+    let (white, black) = match color {
+        PieceColor::White => (PlayerKind::Human, PlayerKind::Remote),
+        PieceColor::Black => (PlayerKind::Remote, PlayerKind::Human),
+    };
+    Players {
+        white,
+        black,
+        ..Default::default()
+    }
+}
+
+// Component to mark the Text entity showing the connection and chat
+struct NetworkText;
+
+/// Plays against another instance of the game over TCP, started with
+/// `--host` or `--join`, or through a game server with `--server` or `--watch`
+pub struct NetworkPlugin;
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let role = app
+            .world()
+            .get_resource::<NetworkRole>()
+            .cloned()
+            .unwrap_or_default();
+        let (link, local_color, status) = match &role {
+            NetworkRole::Offline => return,
+            NetworkRole::Host { port, color } => match Link::host(*port) {
+                Ok(link) => (link, Some(*color), ConnectionStatus::Waiting),
+                Err(error) => {
+                    let reason = format!("Can't listen on port {}: {}", port, error);
+                    eprintln!("{}", reason);
+                    (
+                        Link::closed(),
+                        Some(*color),
+                        ConnectionStatus::Rejected(reason),
+                    )
+                }
+            },
+            NetworkRole::Join { address }
+            | NetworkRole::Seek { address }
+            | NetworkRole::Watch { address, .. } => {
+                (Link::join(address), None, ConnectionStatus::Waiting)
+            }
+        };
+
+        let game = local_color.map(|_| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64)
+        });
+        // A joining player moves nothing until told which color is theirs
+        let players = match local_color {
+            Some(color) => network_players(color),
+            None => Players {
+                white: PlayerKind::Remote,
+                black: PlayerKind::Remote,
+                ..Default::default()
+            },
+        };
+
+        app.insert_resource(players)
+            .insert_resource(Network {
+                role,
+                local_color,
+                game,
+                seat: None,
+                status,
+                error: None,
+                local_address: link.local_address,
+                peer_ready: false,
+                peer_joined: false,
+                peer_moves: 0,
+                hello_moves: 0,
+                pending_moves: VecDeque::new(),
+                clock_sync: None,
+                resync_ply: None,
+                link,
+            })
+            .init_resource::<ChatLog>()
+            .add_system(receive_messages.system().label("receive_messages"))
+            .add_system(
+                apply_remote_moves
+                    .system()
+                    .label("apply_remote_moves")
+                    .after("receive_messages"),
+            )
+            .add_system(sync_clock.system().after("run_clock"))
+            .add_system(
+                send_local_moves
+                    .system()
+                    .after("run_clock")
+                    .after("receive_messages"),
+            )
+            .add_system(send_local_actions.system().after("receive_messages"))
+            .add_system(send_game_over.system().after("receive_messages"));
+
+        if !is_headless(app) {
+            app.add_startup_system(init_network_text.system())
+                .add_system(network_text_update.system());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_messages(
+    mut network: ResMut<Network>,
+    mut players: ResMut<Players>,
+    mut clock: ResMut<GameClock>,
+    mut game_over: ResMut<GameOver>,
+    draw_offer: Res<DrawOffer>,
+    history: Res<MoveHistory>,
+    mut actions: EventWriter<GameAction>,
+    mut chat: EventWriter<ChatMessage>,
+) {
+    let events: Vec<LinkEvent> = network.link.events().collect();
+    for event in events {
+        let message = match event {
+            LinkEvent::Connected(peer) => {
+                network.status = ConnectionStatus::Connected(peer);
+                network.peer_ready = false;
+                let hello = network.hello(&history, &clock, &draw_offer, &game_over);
+                network.hello_moves = history.moves.len() + network.pending_moves.len();
+                network.link.send(hello);
+                if let Some(request) = network.request(&clock) {
+                    network.link.send(request);
+                }
+                continue;
+            }
+            LinkEvent::Disconnected => {
+                network.peer_ready = false;
+                if !matches!(network.status, ConnectionStatus::Rejected(_)) {
+                    network.status = ConnectionStatus::Lost;
+                }
+                continue;
+            }
+            LinkEvent::Message(message) => message,
+        };
+
+        match message {
+            Message::Hello { .. } => {
+                let welcome = network.welcome(
+                    message,
+                    &history,
+                    &mut players,
+                    &mut clock,
+                    &draw_offer,
+                    &mut game_over,
+                    &mut actions,
+                );
+                match welcome {
+                    Ok(()) => network.peer_ready = true,
+                    Err(reason) => {
+                        network.link.send(Message::Reject {
+                            reason: reason.clone(),
+                        });
+                        reject(&mut network, reason);
+                    }
+                }
+            }
+            Message::Reject { reason } => reject(&mut network, reason),
+            // Nothing counts before the other side has said hello
+            _ if !network.peer_ready => (),
+            Message::Error { reason } => network.error = Some(reason),
+            Message::Move {
+                ply, uci, clock, ..
+            } => {
+                let known = history.moves.len() + network.pending_moves.len();
+                let mover = if ply % 2 == 0 {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                // Already known, or not the other player's to make
+                if ply != known || !network.is_remote(mover) {
+                    continue;
+                }
+                network.pending_moves.push_back(PendingMove { ply, uci });
+                network.peer_moves = ply + 1;
+                network.error = None;
+                if let Some(remaining) = clock {
+                    network.clock_sync = Some(ClockSync {
+                        moves: ply + 1,
+                        white: Some(remaining).filter(|_| mover == PieceColor::White),
+                        black: Some(remaining).filter(|_| mover == PieceColor::Black),
+                    });
+                }
+            }
+            // The other side lost track of the game and wants it all again
+            Message::GetState => {
+                let hello = network.hello(&history, &clock, &draw_offer, &game_over);
+                network.hello_moves = history.moves.len() + network.pending_moves.len();
+                network.link.send(hello);
+            }
+            Message::GameOver {
+                result,
+                termination,
+            } => {
+                if game_over.0.is_none() {
+                    game_over.0 = Some(GameOutcome {
+                        result,
+                        termination,
+                    });
+                }
+            }
+            message => {
+                let remote = match network.remote_color() {
+                    Some(remote) => remote,
+                    None => continue,
+                };
+                match message {
+                    Message::Resign => actions.send(GameAction::Resign(remote)),
+                    Message::OfferDraw => actions.send(GameAction::OfferDraw(remote)),
+                    Message::AcceptDraw => actions.send(GameAction::AcceptDraw(remote)),
+                    Message::DeclineDraw => actions.send(GameAction::DeclineDraw(remote)),
+                    Message::Chat { text } => chat.send(ChatMessage {
+                        color: remote,
+                        text,
+                    }),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// The host hangs up and waits for someone else, a joining player gives up
+fn reject(network: &mut Network, reason: String) {
+    network.peer_ready = false;
+    if network.is_host() {
+        network.link.drop_connection();
+    } else {
+        network.link.stop();
+    }
+    network.status = ConnectionStatus::Rejected(reason);
+}
+
+/// Play the other side's moves one at a time, once the last one is done
+fn apply_remote_moves(
+    mut network: ResMut<Network>,
+    animating: Res<Animating>,
+    turn: Res<PlayerTurn>,
+    board_state: Res<BoardState>,
+    history: Res<MoveHistory>,
+    pieces_query: Query<(Entity, &Piece)>,
+    mut move_event: EventWriter<MoveEvent>,
+) {
+    if animating.0 {
+        return;
+    }
+    match network.pending_moves.front() {
+        Some(pending) if pending.ply == history.moves.len() => (),
+        _ => return,
+    }
+    let pending = network.pending_moves.pop_front().unwrap();
+
+    let pieces: Vec<Piece> = pieces_query.iter().map(|(_, piece)| *piece).collect();
+    let chess_move = match parse_move(&pending.uci, turn.0, &pieces, &board_state) {
+        Ok(chess_move) => chess_move,
+        Err(error) => {
+            let reason = format!("Move {} can't be played here: {}", pending.uci, error);
+            eprintln!("{}", reason);
+            network.pending_moves.clear();
+            // Asking for the game again only helps once, the same move back
+            // means the two games have gone apart
+            if network.resync_ply == Some(pending.ply) {
+                network.link.send(Message::Reject {
+                    reason: reason.clone(),
+                });
+                reject(&mut network, reason);
+            } else {
+                network.resync_ply = Some(pending.ply);
+                network.link.send(Message::Error { reason });
+                network.link.send(Message::GetState);
+            }
+            return;
+        }
+    };
+    if let Some((piece, _)) = pieces_query
+        .iter()
+        .find(|(_, piece)| (piece.x, piece.y) == chess_move.from)
+    {
+        move_event.send(MoveEvent { piece, chess_move });
+    }
+}
+
+/// Set the times the other side reported once the moves they follow are in,
+/// so the increment isn't added twice
+fn sync_clock(
+    mut network: ResMut<Network>,
+    history: Res<MoveHistory>,
+    mut clock: ResMut<GameClock>,
+) {
+    match &network.clock_sync {
+        Some(sync) if history.moves.len() >= sync.moves => (),
+        _ => return,
+    }
+    let sync = network.clock_sync.take().unwrap();
+    if let Some(white) = sync.white {
+        clock.white = white;
+    }
+    if let Some(black) = sync.black {
+        clock.black = black;
+    }
+}
+
+fn send_local_moves(
+    mut network: ResMut<Network>,
+    history: Res<MoveHistory>,
+    clock: Res<GameClock>,
+) {
+    if !network.peer_ready {
+        return;
+    }
+
+    while network.peer_moves < history.moves.len() {
+        let ply = network.peer_moves;
+        let record = &history.moves[ply];
+        network.link.send(Message::Move {
+            ply,
+            uci: record.uci(),
+            clock: clock.time_control.map(|_| clock.remaining(record.color)),
+            fen: None,
+        });
+        network.peer_moves += 1;
+    }
+}
+
+/// Pass on what the player here does besides moving, and keep the chat log
+fn send_local_actions(
+    network: Res<Network>,
+    mut chat_log: ResMut<ChatLog>,
+    mut actions: EventReader<GameAction>,
+    mut chat: EventReader<ChatMessage>,
+) {
+    for action in actions.iter() {
+        let (color, message) = match *action {
+            GameAction::Resign(color) => (color, Message::Resign),
+            GameAction::OfferDraw(color) => (color, Message::OfferDraw),
+            GameAction::AcceptDraw(color) => (color, Message::AcceptDraw),
+            GameAction::DeclineDraw(color) => (color, Message::DeclineDraw),
+        };
+        if Some(color) == network.local_color && network.peer_ready {
+            network.link.send(message);
+        }
+    }
+
+    for message in chat.iter() {
+        if Some(message.color) == network.local_color && network.peer_ready {
+            network.link.send(Message::Chat {
+                text: message.text.clone(),
+            });
+        }
+        chat_log.messages.push(message.clone());
+    }
+}
+
+/// Tell the other side when the game ends here, which matters when it's
+/// something only this side sees, like its own flag falling
+fn send_game_over(network: Res<Network>, game_over: Res<GameOver>) {
+    if !game_over.is_changed() || !network.peer_ready {
+        return;
+    }
+
+    if let Some(outcome) = game_over.0 {
+        network.link.send(Message::GameOver {
+            result: outcome.result,
+            termination: outcome.termination,
+        });
+    }
+}
+
+fn init_network_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(150.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: String::new(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 20.0,
+                            color: Color::GOLD,
+                        },
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(NetworkText);
+}
+
+fn network_text_update(
+    network: Res<Network>,
+    chat_log: Res<ChatLog>,
+    mut query: Query<&mut Text, With<NetworkText>>,
+) {
+    if !network.is_changed() && !chat_log.is_changed() {
+        return;
+    }
+
+    let status = match &network.status {
+        ConnectionStatus::Waiting => match (&network.role, network.local_address) {
+            (_, Some(address)) => {
+                format!("Waiting for the other player on port {}", address.port())
+            }
+            (NetworkRole::Join { .. }, None) => "Connecting to the host".to_string(),
+            _ => "Connecting to the server".to_string(),
+        },
+        ConnectionStatus::Connected(_) if !network.peer_ready => match network.role {
+            NetworkRole::Seek { .. } => "Looking for an opponent".to_string(),
+            _ => "Connecting".to_string(),
+        },
+        ConnectionStatus::Connected(peer) => {
+            let game = network.game.unwrap_or_default();
+            match (&network.role, network.local_color) {
+                (NetworkRole::Watch { .. }, _) => format!("Watching game {}", game),
+                (NetworkRole::Seek { .. }, Some(color)) => {
+                    format!("Playing {} in game {}", color.name(), game)
+                }
+                (_, Some(color)) => format!("Playing {} against {}", color.name(), peer.ip()),
+                _ => format!("Connected to {}", peer.ip()),
+            }
+        }
+        ConnectionStatus::Lost => "Connection lost, reconnecting".to_string(),
+        ConnectionStatus::Rejected(reason) => format!("Not connected: {}", reason),
+    };
+    let status = match &network.error {
+        Some(error) => format!("{}\nThe other side refused: {}", status, error),
+        None => status,
+    };
+
+    let start = chat_log.messages.len().saturating_sub(CHAT_LINES);
+    let mut chat = String::new();
+    for message in chat_log.messages[start..].iter() {
+        chat.push_str(&format!("\n{}: {}", message.color.name(), message.text));
+    }
+    if network.peer_ready {
+        chat.push_str("\nType a move starting with / to chat");
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = status.clone();
+        text.sections[1].value = chat.clone();
+    }
+}
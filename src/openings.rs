@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    headless::is_headless,
+    history::MoveHistory,
+    pieces::{parse_move, Position, STARTING_FEN},
+};
+
+/// The opening table shipped with the game
+const ECO_TABLE: &str = include_str!("../assets/openings/eco.tsv");
+
+/// A named opening from the ECO table
+#[derive(Clone, PartialEq)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+    // The moves reaching it from the starting position, in SAN
+    pub moves: Vec<String>,
+}
+
+/// The bundled ECO table, for naming the opening a game was played with
+pub struct Openings {
+    pub openings: Vec<Opening>,
+    // Zobrist hash of the position each line ends in, to its opening
+    by_hash: HashMap<u64, usize>,
+}
+
+impl Default for Openings {
+    fn default() -> Self {
+        Self::parse(ECO_TABLE).expect("The bundled opening table is broken")
+    }
+}
+
+impl Openings {
+    /// Reads lines of ECO code, name and moves separated by tabs, as in
+    /// "C50<tab>Italian Game<tab>1. e4 e5 2. Nf3 Nc6 3. Bc4"
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let openings = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(
+                |(index, line)| match line.split('\t').collect::<Vec<&str>>()[..] {
+                    [eco, name, moves] => Ok(Opening {
+                        eco: eco.to_string(),
+                        name: name.to_string(),
+                        moves: moves
+                            .split_whitespace()
+                            .filter(|token| !token.ends_with('.'))
+                            .map(str::to_string)
+                            .collect(),
+                    }),
+                    _ => Err(format!("Line {}: expected three columns", index + 1)),
+                },
+            )
+            .collect::<Result<Vec<Opening>, String>>()?;
+
+        let mut by_hash = HashMap::new();
+        for (index, opening) in openings.iter().enumerate() {
+            let mut position =
+                Position::from_fen(STARTING_FEN).map_err(|error| error.to_string())?;
+            for input in opening.moves.iter() {
+                let chess_move =
+                    parse_move(input, position.turn, &position.pieces, &position.state)
+                        .map_err(|error| format!("{} {}: {}", opening.eco, opening.name, error))?;
+                position.play(&chess_move);
+            }
+            // Lines reaching the same position keep the first name listed
+            by_hash.entry(position.zobrist()).or_insert(index);
+        }
+        Ok(Self { openings, by_hash })
+    }
+
+    /// The opening named after the position with this Zobrist hash
+    pub fn get(&self, hash: u64) -> Option<&Opening> {
+        self.by_hash.get(&hash).map(|index| &self.openings[*index])
+    }
+
+    /// The opening of the last named position among those a game went
+    /// through, so move orders transposing into a line are recognized
+    pub fn classify(&self, hashes: &[u64]) -> Option<&Opening> {
+        hashes.iter().rev().find_map(|hash| self.get(*hash))
+    }
+}
+
+/// The opening of the game on the board, as far as it has been played
+#[derive(Default)]
+pub struct CurrentOpening {
+    pub opening: Option<Opening>,
+}
+
+// Component to mark the Text entity showing the opening
+struct OpeningText;
+
+pub struct OpeningsPlugin;
+impl Plugin for OpeningsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Openings>()
+            .init_resource::<CurrentOpening>()
+            .add_system(update_current_opening.system());
+        if !is_headless(app) {
+            app.add_startup_system(init_opening_text.system())
+                .add_system(opening_text_update.system());
+        }
+    }
+}
+
+fn update_current_opening(
+    history: Res<MoveHistory>,
+    openings: Res<Openings>,
+    mut current: ResMut<CurrentOpening>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+
+    let opening = match history.hashes() {
+        Ok(hashes) => openings.classify(&hashes).cloned(),
+        Err(error) => {
+            eprintln!("Can't name the opening: {}", error);
+            None
+        }
+    };
+    if current.opening != opening {
+        current.opening = opening;
+    }
+}
+
+fn init_opening_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(180.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(OpeningText);
+}
+
+fn opening_text_update(
+    current: Res<CurrentOpening>,
+    mut query: Query<&mut Text, With<OpeningText>>,
+) {
+    if !current.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = current.opening.as_ref().map_or(String::new(), |opening| {
+            format!("{} {}", opening.eco, opening.name)
+        });
+    }
+}
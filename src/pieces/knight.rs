@@ -1,58 +1,3 @@
-use bevy::prelude::*;
-
-use super::{Piece, PieceColor, PieceType};
-
-pub fn spawn_knight(
-    commands: &mut Commands,
-    material: Handle<StandardMaterial>,
-    piece_color: PieceColor,
-    position: (u8, u8),
-    asset_server: &AssetServer,
-) {
-    let mesh_1: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0");
-    let mesh_2: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0");
-
-    commands
-        // Spawn parent entity
-        .spawn_bundle(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .insert(Piece {
-            color: piece_color,
-            piece_type: PieceType::Knight,
-            x: position.0,
-            y: position.1,
-        })
-        // Add children to the parent
-        .with_children(|parent| {
-            parent.spawn_bundle(PbrBundle {
-                mesh: mesh_1,
-                material: material.clone(),
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., 0.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-            parent.spawn_bundle(PbrBundle {
-                mesh: mesh_2,
-                material,
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., 0.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-        });
-}
-
 pub fn is_knight_move_valid(current_position: (u8, u8), target_position: (u8, u8)) -> bool {
     let (current_x, current_y) = current_position;
     let (target_x, target_y) = target_position;
@@ -0,0 +1,100 @@
+use super::{Piece, PieceColor, PieceType};
+
+/// Piece types counted in material, kings left out since both sides have one
+const COUNTED: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/// How many queens, rooks, bishops, knights and pawns each side has
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Material {
+    pub white: [u8; 5],
+    pub black: [u8; 5],
+}
+
+impl Material {
+    pub fn of(pieces: &[Piece]) -> Self {
+        let mut material = Self::default();
+        for piece in pieces.iter() {
+            if let Some(index) = COUNTED.iter().position(|kind| *kind == piece.piece_type) {
+                match piece.color {
+                    PieceColor::White => material.white[index] += 1,
+                    PieceColor::Black => material.black[index] += 1,
+                }
+            }
+        }
+        material
+    }
+
+    /// The counts packed into a number, four bits each, for storing
+    pub fn key(&self) -> u64 {
+        self.white
+            .iter()
+            .chain(self.black.iter())
+            .fold(0, |key, count| key << 4 | u64::from(*count & 0xF))
+    }
+
+    pub fn from_key(key: u64) -> Self {
+        let mut material = Self::default();
+        for index in 0..5 {
+            material.white[index] = (key >> (4 * (9 - index)) & 0xF) as u8;
+            material.black[index] = (key >> (4 * (4 - index)) & 0xF) as u8;
+        }
+        material
+    }
+}
+
+/// Material to look for, such as "R+P vs R", with either side on either color
+#[derive(Clone, Copy, PartialEq)]
+pub struct MaterialSignature {
+    pub first: [u8; 5],
+    pub second: [u8; 5],
+}
+
+impl MaterialSignature {
+    /// Reads two sides separated by "vs" or "v", each listing its pieces by
+    /// letter: "KRP vs KR", "R+P vs R" and "Q v" all work
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.to_uppercase();
+        let (first, second) = text
+            .split_once("VS")
+            .or_else(|| text.split_once('V'))
+            .ok_or_else(|| format!("Expected two sides like \"R+P vs R\", got {:?}", text))?;
+        Ok(Self {
+            first: parse_side(first)?,
+            second: parse_side(second)?,
+        })
+    }
+
+    /// The color with the first side's material, if the position has it
+    pub fn matches(&self, material: &Material) -> Option<PieceColor> {
+        if material.white == self.first && material.black == self.second {
+            Some(PieceColor::White)
+        } else if material.black == self.first && material.white == self.second {
+            Some(PieceColor::Black)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_side(text: &str) -> Result<[u8; 5], String> {
+    let mut counts = [0u8; 5];
+    for letter in text.chars().filter(|letter| !letter.is_whitespace()) {
+        let index = match letter {
+            'K' | '+' => continue,
+            'Q' => 0,
+            'R' => 1,
+            'B' => 2,
+            'N' => 3,
+            'P' => 4,
+            _ => return Err(format!("Unknown piece {:?}", letter)),
+        };
+        counts[index] = counts[index].saturating_add(1);
+    }
+    Ok(counts)
+}
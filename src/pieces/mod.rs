@@ -18,3 +18,24 @@ pub use queen::*;
 
 mod rook;
 pub use rook::*;
+
+mod rules;
+pub use rules::*;
+
+mod notation;
+pub use notation::*;
+
+mod piece_set;
+pub use piece_set::*;
+
+mod position;
+pub use position::*;
+
+mod pgn;
+pub use pgn::*;
+
+// Only adds `Position::zobrist`
+mod zobrist;
+
+mod material;
+pub use material::*;
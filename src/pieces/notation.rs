@@ -0,0 +1,293 @@
+use std::fmt;
+
+use super::{
+    all_legal_moves, apply_move, is_in_check, legal_moves, BoardState, ChessMove, Piece,
+    PieceColor, PieceType,
+};
+
+/// Returns the algebraic name of a board position, e.g. (0, 4) => "e1"
+pub fn square_name(position: (u8, u8)) -> String {
+    format!(
+        "{}{}",
+        (b'a' + position.1) as char,
+        (b'1' + position.0) as char
+    )
+}
+
+/// Parses an algebraic square name, e.g. "e1" => (0, 4)
+pub fn parse_square(name: &str) -> Option<(u8, u8)> {
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    match (bytes[0], bytes[1]) {
+        (file @ b'a'..=b'h', rank @ b'1'..=b'8') => Some((rank - b'1', file - b'a')),
+        _ => None,
+    }
+}
+
+impl PieceType {
+    /// Letter used for the piece in algebraic notation, empty for pawns
+    pub fn letter(&self) -> &'static str {
+        match self {
+            PieceType::King => "K",
+            PieceType::Queen => "Q",
+            PieceType::Bishop => "B",
+            PieceType::Knight => "N",
+            PieceType::Rook => "R",
+            PieceType::Pawn => "",
+        }
+    }
+
+    /// Piece type for a letter in either case, pawns included
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'K' => Some(PieceType::King),
+            'Q' => Some(PieceType::Queen),
+            'B' => Some(PieceType::Bishop),
+            'N' => Some(PieceType::Knight),
+            'R' => Some(PieceType::Rook),
+            'P' => Some(PieceType::Pawn),
+            _ => None,
+        }
+    }
+}
+
+/// Standard algebraic notation for a legal move, e.g. "Nbd2", "exd5", "O-O" or "e8=Q+"
+pub fn san(chess_move: &ChessMove, pieces: &[Piece], state: &BoardState) -> String {
+    let piece = match pieces
+        .iter()
+        .find(|piece| (piece.x, piece.y) == chess_move.from)
+    {
+        Some(piece) => piece,
+        None => return String::new(),
+    };
+
+    let mut san = String::new();
+    if let Some((_rook_from, rook_to)) = chess_move.castle {
+        san.push_str(if rook_to.1 == 5 { "O-O" } else { "O-O-O" });
+    } else if piece.piece_type == PieceType::Pawn {
+        if chess_move.capture.is_some() {
+            san.push((b'a' + chess_move.from.1) as char);
+            san.push('x');
+        }
+        san.push_str(&square_name(chess_move.to));
+        if let Some(promotion) = chess_move.promotion {
+            san.push('=');
+            san.push_str(promotion.letter());
+        }
+    } else {
+        san.push_str(piece.piece_type.letter());
+
+        // Other pieces of the same type that could also move to the target square
+        let others: Vec<(u8, u8)> = pieces
+            .iter()
+            .filter(|other| {
+                other.color == piece.color
+                    && other.piece_type == piece.piece_type
+                    && (other.x, other.y) != chess_move.from
+            })
+            .filter(|other| {
+                legal_moves(other, pieces, state)
+                    .iter()
+                    .any(|other_move| other_move.to == chess_move.to)
+            })
+            .map(|other| (other.x, other.y))
+            .collect();
+        if !others.is_empty() {
+            let file = (b'a' + chess_move.from.1) as char;
+            let rank = (b'1' + chess_move.from.0) as char;
+            if others.iter().all(|other| other.1 != chess_move.from.1) {
+                san.push(file);
+            } else if others.iter().all(|other| other.0 != chess_move.from.0) {
+                san.push(rank);
+            } else {
+                san.push(file);
+                san.push(rank);
+            }
+        }
+
+        if chess_move.capture.is_some() {
+            san.push('x');
+        }
+        san.push_str(&square_name(chess_move.to));
+    }
+
+    // Mark check and checkmate
+    let opponent = piece.color.opponent();
+    let after = apply_move(pieces, chess_move);
+    if is_in_check(opponent, &after) {
+        let mut next_state = *state;
+        next_state.update(chess_move, piece);
+        san.push(
+            if all_legal_moves(opponent, &after, &next_state).is_empty() {
+                '#'
+            } else {
+                '+'
+            },
+        );
+    }
+
+    san
+}
+
+pub enum MoveParseError {
+    Invalid,
+    Illegal,
+    // The SAN of every legal move matching the input
+    Ambiguous(Vec<String>),
+}
+
+impl fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveParseError::Invalid => write!(f, "Not a move"),
+            MoveParseError::Illegal => write!(f, "Illegal move"),
+            MoveParseError::Ambiguous(candidates) => {
+                write!(
+                    f,
+                    "Ambiguous move, did you mean {}?",
+                    candidates.join(" or ")
+                )
+            }
+        }
+    }
+}
+
+/// Find the legal move for `color` described by SAN ("Nf3", "exd5", "O-O", "e8=Q")
+/// or UCI ("e2e4", "e7e8q") input
+pub fn parse_move(
+    input: &str,
+    color: PieceColor,
+    pieces: &[Piece],
+    state: &BoardState,
+) -> Result<ChessMove, MoveParseError> {
+    let input = input.trim().trim_end_matches(&['+', '#', '!', '?'][..]);
+    let legal = all_legal_moves(color, pieces, state);
+    let piece_type_at = |position: (u8, u8)| {
+        pieces
+            .iter()
+            .find(|piece| (piece.x, piece.y) == position)
+            .map(|piece| piece.piece_type)
+    };
+
+    // Castling is identified by the file the rook ends up on
+    let castle_rook_file = match input.replace('0', "O").as_str() {
+        "O-O" => Some(5),
+        "O-O-O" => Some(3),
+        _ => None,
+    };
+
+    let candidates: Vec<ChessMove> = if let Some(rook_file) = castle_rook_file {
+        legal
+            .into_iter()
+            .filter(|chess_move| {
+                matches!(chess_move.castle, Some((_rook_from, rook_to)) if rook_to.1 == rook_file)
+            })
+            .collect()
+    } else if let Some((from, to, promotion)) = parse_uci(input) {
+        legal
+            .into_iter()
+            .filter(|chess_move| {
+                chess_move.from == from
+                    && chess_move.to == to
+                    && (promotion.is_none() || chess_move.promotion == promotion)
+            })
+            .collect()
+    } else {
+        let mut chars: Vec<char> = input.chars().filter(|c| *c != '=').collect();
+
+        // A leading capital letter names the piece, otherwise it's a pawn move
+        let piece_type = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let piece_type = PieceType::from_letter(*c).ok_or(MoveParseError::Invalid)?;
+                chars.remove(0);
+                piece_type
+            }
+            _ => PieceType::Pawn,
+        };
+
+        let promotion = match chars.last() {
+            Some(c) if c.is_ascii_uppercase() && piece_type == PieceType::Pawn => {
+                let promotion = PieceType::from_letter(*c).ok_or(MoveParseError::Invalid)?;
+                chars.pop();
+                Some(promotion)
+            }
+            _ => None,
+        };
+
+        let is_capture = chars.contains(&'x');
+        chars.retain(|c| *c != 'x');
+        if chars.len() < 2 {
+            return Err(MoveParseError::Invalid);
+        }
+        let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to = parse_square(&target).ok_or(MoveParseError::Invalid)?;
+
+        // Whatever is left disambiguates the starting square
+        let (mut from_file, mut from_rank) = (None, None);
+        for c in chars {
+            match c {
+                'a'..='h' => from_file = Some(c as u8 - b'a'),
+                '1'..='8' => from_rank = Some(c as u8 - b'1'),
+                _ => return Err(MoveParseError::Invalid),
+            }
+        }
+
+        legal
+            .into_iter()
+            .filter(|chess_move| {
+                chess_move.to == to
+                    && piece_type_at(chess_move.from) == Some(piece_type)
+                    && chess_move.castle.is_none()
+                    && from_file.iter().all(|file| chess_move.from.1 == *file)
+                    && from_rank.iter().all(|rank| chess_move.from.0 == *rank)
+                    && (!is_capture || chess_move.capture.is_some())
+                    && (promotion.is_none() || chess_move.promotion == promotion)
+            })
+            .collect()
+    };
+
+    match candidates.len() {
+        0 => Err(MoveParseError::Illegal),
+        1 => Ok(candidates[0]),
+        _ => Err(MoveParseError::Ambiguous(
+            candidates
+                .iter()
+                .map(|chess_move| san(chess_move, pieces, state))
+                .collect(),
+        )),
+    }
+}
+
+/// Start and end squares and promotion of a move in coordinate notation
+type UciMove = ((u8, u8), (u8, u8), Option<PieceType>);
+
+/// Parses coordinate notation, e.g. "e2e4" or "e7e8q"
+fn parse_uci(input: &str) -> Option<UciMove> {
+    if !input.is_ascii() || (input.len() != 4 && input.len() != 5) {
+        return None;
+    }
+
+    let from = parse_square(&input[0..2])?;
+    let to = parse_square(&input[2..4])?;
+    let promotion = match input[4..].chars().next() {
+        Some(letter) => Some(PieceType::from_letter(letter)?),
+        None => None,
+    };
+
+    Some((from, to, promotion))
+}
+
+/// Coordinate notation for a move as spoken by UCI engines, e.g. "e2e4" or "e7e8q"
+pub fn uci(chess_move: &ChessMove) -> String {
+    let promotion = chess_move.promotion.map_or(String::new(), |piece_type| {
+        piece_type.letter().to_lowercase()
+    });
+    format!(
+        "{}{}{}",
+        square_name(chess_move.from),
+        square_name(chess_move.to),
+        promotion
+    )
+}
@@ -0,0 +1,166 @@
+use super::{parse_move, ChessMove, Position};
+
+/// A game read from PGN, with its moves still in SAN
+#[derive(Clone, Default)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    // The result token at the end of the movetext, "*" when missing
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from, set with the FEN tag if not the usual one
+    pub fn start(&self) -> Result<Position, String> {
+        match self.tag("FEN") {
+            Some(fen) => Position::from_fen(fen).map_err(|error| error.to_string()),
+            None => Ok(Position::default()),
+        }
+    }
+
+    /// Play the moves from the start, failing on the first one that isn't legal
+    pub fn replay(&self) -> Result<(Position, Vec<ChessMove>), String> {
+        let mut position = self.start()?;
+        let mut moves = Vec::new();
+        for (ply, input) in self.moves.iter().enumerate() {
+            let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+                .map_err(|error| format!("Move {} ({}): {}", ply / 2 + 1, input, error))?;
+            position.play(&chess_move);
+            moves.push(chess_move);
+        }
+        Ok((position, moves))
+    }
+}
+
+/// Split a file of PGN games into their tags and moves, skipping comments,
+/// variations, move numbers and annotations
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut in_movetext = false;
+    // Nesting of the (variations) being skipped
+    let mut variation_depth = 0;
+    let mut in_comment = false;
+
+    for line in text.lines() {
+        let mut line = line.trim();
+        if in_comment {
+            match line.find('}') {
+                Some(end) => {
+                    in_comment = false;
+                    line = line[end + 1..].trim();
+                }
+                None => continue,
+            }
+        }
+
+        if line.starts_with('[') && variation_depth == 0 {
+            // Tags after movetext start the next game
+            if in_movetext {
+                games.push(std::mem::take(&mut game));
+                in_movetext = false;
+            }
+            if let Some((name, value)) = parse_tag(line) {
+                game.tags.push((name, value));
+            }
+            continue;
+        }
+        if line.starts_with('%') || line.is_empty() {
+            continue;
+        }
+
+        in_movetext = true;
+        read_movetext(line, &mut game, &mut variation_depth, &mut in_comment);
+        if !game.result.is_empty() {
+            games.push(std::mem::take(&mut game));
+            in_movetext = false;
+        }
+    }
+
+    if in_movetext || !game.tags.is_empty() {
+        if game.result.is_empty() {
+            game.result = "*".to_string();
+        }
+        games.push(game);
+    }
+    games
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.trim_start_matches('[').trim_end_matches(']');
+    let (name, value) = inner.split_once(' ')?;
+    Some((
+        name.to_string(),
+        value
+            .trim()
+            .trim_matches('"')
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\"),
+    ))
+}
+
+fn read_movetext(
+    line: &str,
+    game: &mut PgnGame,
+    variation_depth: &mut usize,
+    in_comment: &mut bool,
+) {
+    let mut token = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                read_token(&mut token, game, *variation_depth);
+                // The comment may go on over the next lines
+                if !chars.any(|c| c == '}') {
+                    *in_comment = true;
+                    return;
+                }
+            }
+            ';' => break,
+            '(' => {
+                read_token(&mut token, game, *variation_depth);
+                *variation_depth += 1;
+            }
+            ')' => {
+                read_token(&mut token, game, *variation_depth);
+                *variation_depth = variation_depth.saturating_sub(1);
+            }
+            c if c.is_whitespace() => read_token(&mut token, game, *variation_depth),
+            c => token.push(c),
+        }
+    }
+    read_token(&mut token, game, *variation_depth);
+}
+
+fn read_token(token: &mut String, game: &mut PgnGame, variation_depth: usize) {
+    let text = std::mem::take(token);
+    if text.is_empty() || variation_depth > 0 {
+        return;
+    }
+
+    match text.as_str() {
+        "1-0" | "0-1" | "1/2-1/2" | "*" => game.result = text,
+        _ if text.starts_with('$') => (),
+        _ => {
+            // Move numbers may be stuck to the move, as in "1.e4" or "3...Nf6"
+            let san = match text.find(|c: char| !c.is_ascii_digit()) {
+                Some(index) if text[index..].starts_with('.') => {
+                    text[index..].trim_start_matches('.')
+                }
+                _ => text.as_str(),
+            };
+            let san = san.trim_end_matches(&['!', '?'][..]);
+            if !san.is_empty() {
+                game.moves.push(san.to_string());
+            }
+        }
+    }
+}
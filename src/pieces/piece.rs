@@ -1,17 +1,45 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{headless::is_headless, theme::Themes};
 
 use super::{
     is_bishop_move_valid, is_black_pawn_move_valid, is_king_move_valid, is_knight_move_valid,
-    is_queen_move_valid, is_rook_move_valid, is_white_pawn_move_valid, spawn_bishop, spawn_king,
-    spawn_knight, spawn_pawn, spawn_queen, spawn_rook,
+    is_queen_move_valid, is_rook_move_valid, is_white_pawn_move_valid, spawn_piece_model,
+    PieceModel, PieceSet, PieceSets,
 };
 pub struct Taken;
-#[derive(Clone, Copy, PartialEq)]
+/// A piece that has been taken and now sits beside the board
+pub struct Captured {
+    pub color: PieceColor,
+    pub piece_type: PieceType,
+}
+/// Marks a pawn that has been promoted and needs its model replaced
+pub struct Promoted;
+/// Marks a piece that follows the cursor instead of moving to its square
+pub struct Dragged;
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PieceColor {
     White,
     Black,
 }
 
+impl PieceColor {
+    pub fn opponent(&self) -> Self {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PieceColor::White => "White",
+            PieceColor::Black => "Black",
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum PieceType {
     King,
@@ -22,6 +50,19 @@ pub enum PieceType {
     Pawn,
 }
 
+impl PieceType {
+    /// Material value in pawns, used to score positions
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::King => 0,
+            PieceType::Queen => 9,
+            PieceType::Rook => 5,
+            PieceType::Bishop | PieceType::Knight => 3,
+            PieceType::Pawn => 1,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Piece {
     pub color: PieceColor,
@@ -33,9 +74,9 @@ pub struct Piece {
 
 impl Piece {
     /// Returns the possible_positions that are available
-    pub fn can_reach_position(&self, target_position: (u8, u8), pieces: &Query<&Piece>) -> bool {
+    pub fn can_reach_position(&self, target_position: (u8, u8), pieces: &[Piece]) -> bool {
         // If there's a piece of the same color in the same square, it can't move
-        if color_of_square(target_position, &pieces) == Some(self.color) {
+        if color_of_square(target_position, pieces) == Some(self.color) {
             return false;
         }
 
@@ -55,210 +96,217 @@ impl Piece {
             },
         }
     }
+}
 
-    pub fn is_move_valid(
-        &self,
-        target_position: (u8, u8),
-        pieces: &Query<&Piece>,
-        is_check: bool,
-    ) -> bool {
-        // can the piece reach the position?
-        if !self.can_reach_position(target_position, pieces) {
-            return false;
-        }
-
-        // if there is check, does this move resolve it?
-        if is_check {
-            // get new set of pieces
-            let pieces_copy: Vec<Piece> = pieces.iter().cloned().collect();
-            let mut filtered: Vec<Piece> = pieces_copy
-                .into_iter()
-                .filter(|piece| !(piece.x == self.x && piece.y == self.y))
-                .collect();
-
-            filtered.push(Piece {
-                x: target_position.0,
-                y: target_position.1,
-                ..*self
-            });
+pub struct PieceMaterials {
+    pub white: Handle<StandardMaterial>,
+    pub black: Handle<StandardMaterial>,
+}
 
-            // check for check
-            // if can_any_piece_reach_king(king, &filtered) {
-            //     return false;
-            // }
+impl PieceMaterials {
+    pub fn get(&self, color: PieceColor) -> Handle<StandardMaterial> {
+        match color {
+            PieceColor::White => self.white.clone(),
+            PieceColor::Black => self.black.clone(),
         }
-
-        // does this move create check against own king?
-
-        true
     }
 }
+
 pub struct PiecesPlugin;
 impl Plugin for PiecesPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(create_pieces.system())
-            .add_system(move_pieces.system());
+        app.init_resource::<PieceSets>();
+        if is_headless(app) {
+            app.add_startup_system(create_headless_pieces.system());
+        } else {
+            app.add_startup_system(create_pieces.system())
+                .add_system(promote_pieces.system())
+                .add_system(apply_piece_set.system());
+        }
     }
 }
 
+pub fn spawn_piece(
+    commands: &mut Commands,
+    material: Handle<StandardMaterial>,
+    piece_color: PieceColor,
+    piece_type: PieceType,
+    position: (u8, u8),
+    piece_set: &PieceSet,
+    asset_server: &AssetServer,
+) -> Entity {
+    commands
+        // Spawn parent entity
+        .spawn_bundle(PbrBundle {
+            transform: Transform::from_translation(Vec3::new(
+                position.0 as f32,
+                0.,
+                position.1 as f32,
+            )),
+            ..Default::default()
+        })
+        .insert(Piece {
+            color: piece_color,
+            piece_type,
+            x: position.0,
+            y: position.1,
+        })
+        // Add the meshes as children
+        .with_children(|parent| {
+            spawn_piece_model(parent, piece_set.model(piece_type), material, asset_server);
+        })
+        .id()
+}
+
+/// The pieces at the start of a game
+pub fn starting_position() -> Vec<Piece> {
+    let back_rank = [
+        PieceType::Rook,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Queen,
+        PieceType::King,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Rook,
+    ];
+    let sides = [(PieceColor::White, 0, 1), (PieceColor::Black, 7, 6)];
+
+    let mut pieces = Vec::new();
+    for (color, back_row, pawn_row) in sides.iter() {
+        for (i, piece_type) in back_rank.iter().enumerate() {
+            pieces.push(Piece {
+                color: *color,
+                piece_type: *piece_type,
+                x: *back_row,
+                y: i as u8,
+            });
+        }
+
+        for i in 0..8 {
+            pieces.push(Piece {
+                color: *color,
+                piece_type: PieceType::Pawn,
+                x: *pawn_row,
+                y: i,
+            });
+        }
+    }
+    pieces
+}
+
 fn create_pieces(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    themes: Res<Themes>,
+    piece_sets: Res<PieceSets>,
 ) {
     // Add some materials
-    let white_material = materials.add(Color::rgb(1., 0.8, 0.8).into());
-    let black_material = materials.add(Color::rgb(0., 0.2, 0.2).into());
+    let theme = themes.current();
+    let piece_materials = PieceMaterials {
+        white: materials.add(theme.piece_material(PieceColor::White, &asset_server)),
+        black: materials.add(theme.piece_material(PieceColor::Black, &asset_server)),
+    };
 
-    spawn_rook(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 0),
-        &asset_server,
-    );
-    spawn_knight(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 1),
-        &asset_server,
-    );
-    spawn_bishop(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 2),
-        &asset_server,
-    );
-    spawn_queen(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 3),
-        &asset_server,
-    );
-    spawn_king(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 4),
-        &asset_server,
-    );
-    spawn_bishop(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 5),
-        &asset_server,
-    );
-    spawn_knight(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 6),
-        &asset_server,
-    );
-    spawn_rook(
-        &mut commands,
-        white_material.clone(),
-        PieceColor::White,
-        (0, 7),
-        &asset_server,
-    );
-
-    for i in 0..8 {
-        spawn_pawn(
+    for piece in starting_position() {
+        spawn_piece(
             &mut commands,
-            white_material.clone(),
-            PieceColor::White,
-            (1, i),
+            piece_materials.get(piece.color),
+            piece.color,
+            piece.piece_type,
+            (piece.x, piece.y),
+            piece_sets.current(),
             &asset_server,
         );
     }
 
-    spawn_rook(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::White,
-        (7, 0),
-        &asset_server,
-    );
-    spawn_knight(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 1),
-        &asset_server,
-    );
-    spawn_bishop(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 2),
-        &asset_server,
-    );
-    spawn_queen(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 3),
-        &asset_server,
-    );
-    spawn_king(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 4),
-        &asset_server,
-    );
-    spawn_bishop(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 5),
-        &asset_server,
-    );
-    spawn_knight(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::Black,
-        (7, 6),
-        &asset_server,
-    );
-    spawn_rook(
-        &mut commands,
-        black_material.clone(),
-        PieceColor::White,
-        (7, 7),
-        &asset_server,
-    );
+    commands.insert_resource(piece_materials);
+}
+
+/// Spawn the pieces without models, for running without a renderer
+fn create_headless_pieces(mut commands: Commands) {
+    for piece in starting_position() {
+        commands.spawn().insert(piece);
+    }
+}
+
+/// Replace the model of promoted pawns with the one of their new piece type,
+/// once they have arrived on their square
+fn promote_pieces(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<PieceMaterials>,
+    piece_sets: Res<PieceSets>,
+    query: Query<(Entity, &Piece, &Transform, &Children), With<Promoted>>,
+) {
+    for (entity, piece, transform, children) in query.iter() {
+        if transform.translation != Vec3::new(piece.x as f32, 0., piece.y as f32) {
+            continue;
+        }
 
-    for i in 0..8 {
-        spawn_pawn(
+        replace_model(
             &mut commands,
-            black_material.clone(),
-            PieceColor::Black,
-            (6, i),
+            entity,
+            children,
+            piece_sets.current().model(piece.piece_type),
+            materials.get(piece.color),
             &asset_server,
         );
+        commands.entity(entity).remove::<Promoted>();
     }
 }
 
-fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
-    for (mut transform, piece) in query.iter_mut() {
-        // Get the direction to move in
-        let direction = Vec3::new(piece.x as f32, 0., piece.y as f32) - transform.translation;
+/// Swap the models of every piece, including taken ones, when another set is picked
+fn apply_piece_set(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Option<Res<PieceMaterials>>,
+    piece_sets: Res<PieceSets>,
+    pieces_query: Query<(Entity, &Piece, &Children)>,
+    captured_query: Query<(Entity, &Captured, &Children)>,
+) {
+    let materials = match materials {
+        Some(materials) if piece_sets.is_changed() => materials,
+        _ => return,
+    };
 
-        // Only move if the piece isn't already there (distance is big)
-        if direction.length() > 0.1 {
-            transform.translation += direction.normalize() * time.delta_seconds();
-        }
+    let pieces = pieces_query
+        .iter()
+        .map(|(entity, piece, children)| (entity, piece.color, piece.piece_type, children));
+    let captured = captured_query.iter().map(|(entity, captured, children)| {
+        (entity, captured.color, captured.piece_type, children)
+    });
+    for (entity, color, piece_type, children) in pieces.chain(captured) {
+        replace_model(
+            &mut commands,
+            entity,
+            children,
+            piece_sets.current().model(piece_type),
+            materials.get(color),
+            &asset_server,
+        );
     }
 }
 
+fn replace_model(
+    commands: &mut Commands,
+    entity: Entity,
+    children: &Children,
+    model: &PieceModel,
+    material: Handle<StandardMaterial>,
+    asset_server: &AssetServer,
+) {
+    for child in children.iter() {
+        commands.entity(*child).despawn_recursive();
+    }
+    commands.entity(entity).with_children(|parent| {
+        spawn_piece_model(parent, model, material, asset_server);
+    });
+}
+
 /// Returns None if square is empty, returns a Some with the color if not
-pub fn color_of_square(pos: (u8, u8), pieces: &Query<&Piece>) -> Option<PieceColor> {
+pub fn color_of_square(pos: (u8, u8), pieces: &[Piece]) -> Option<PieceColor> {
     for piece in pieces.iter() {
         if piece.x == pos.0 && piece.y == pos.1 {
             return Some(piece.color);
@@ -267,7 +315,7 @@ pub fn color_of_square(pos: (u8, u8), pieces: &Query<&Piece>) -> Option<PieceCol
     None
 }
 
-pub fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &Query<&Piece>) -> bool {
+pub fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &[Piece]) -> bool {
     // Same column
     if begin.0 == end.0 {
         for piece in pieces.iter() {
@@ -319,10 +367,3 @@ pub fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &Query<&Piece>) ->
 
     true
 }
-
-pub fn can_any_piece_reach_king(king: &Piece, pieces: &Query<&Piece>) -> bool {
-    pieces
-        .iter()
-        .find(|piece| piece.can_reach_position((king.x, king.y), pieces))
-        .is_some()
-}
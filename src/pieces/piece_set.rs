@@ -0,0 +1,129 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::PieceType;
+
+/// Folder with extra piece set descriptors, a file replaces the built-in set of the same name
+const PIECE_SETS_FOLDER: &str = "assets/piece_sets";
+
+const BUILT_IN_PIECE_SETS: [&str; 1] = [include_str!("../../assets/piece_sets/chess_kit.ron")];
+
+/// How to place the meshes of one piece type on its square
+#[derive(Clone, Deserialize)]
+pub struct PieceModel {
+    // Asset paths of the meshes, e.g. "models/chess_kit/pieces.glb#Mesh0/Primitive0"
+    pub meshes: Vec<String>,
+    pub offset: (f32, f32, f32),
+    pub scale: f32,
+    // Degrees around the vertical axis
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+impl PieceModel {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: Vec3::new(self.offset.0, self.offset.1, self.offset.2),
+            rotation: Quat::from_rotation_y(self.rotation.to_radians()),
+            scale: Vec3::splat(self.scale),
+        }
+    }
+}
+
+/// Models for every piece type, read from a descriptor in `assets/piece_sets`
+#[derive(Clone, Deserialize)]
+pub struct PieceSet {
+    pub name: String,
+    pub king: PieceModel,
+    pub queen: PieceModel,
+    pub rook: PieceModel,
+    pub bishop: PieceModel,
+    pub knight: PieceModel,
+    pub pawn: PieceModel,
+}
+
+impl PieceSet {
+    pub fn model(&self, piece_type: PieceType) -> &PieceModel {
+        match piece_type {
+            PieceType::King => &self.king,
+            PieceType::Queen => &self.queen,
+            PieceType::Rook => &self.rook,
+            PieceType::Bishop => &self.bishop,
+            PieceType::Knight => &self.knight,
+            PieceType::Pawn => &self.pawn,
+        }
+    }
+}
+
+/// All available piece sets and the one in use
+pub struct PieceSets {
+    pub sets: Vec<PieceSet>,
+    pub current: usize,
+}
+
+impl PieceSets {
+    pub fn current(&self) -> &PieceSet {
+        &self.sets[self.current]
+    }
+}
+
+impl Default for PieceSets {
+    /// The built-in sets followed by those found in the piece sets folder
+    fn default() -> Self {
+        let mut sets: Vec<PieceSet> = BUILT_IN_PIECE_SETS
+            .iter()
+            .map(|source| ron::de::from_str(source).expect("Invalid built-in piece set"))
+            .collect();
+
+        let mut paths: Vec<_> = fs::read_dir(PIECE_SETS_FOLDER)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.extension().and_then(|extension| extension.to_str()) == Some("ron")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        for path in paths {
+            let set: PieceSet = match fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|source| ron::de::from_str(&source).map_err(|error| error.to_string()))
+            {
+                Ok(set) => set,
+                Err(error) => {
+                    println!("Skipping piece set {}: {}", path.display(), error);
+                    continue;
+                }
+            };
+            match sets.iter_mut().find(|other| other.name == set.name) {
+                Some(other) => *other = set,
+                None => sets.push(set),
+            }
+        }
+
+        PieceSets { sets, current: 0 }
+    }
+}
+
+/// Add the meshes of a piece as children of its entity
+pub fn spawn_piece_model(
+    parent: &mut ChildBuilder,
+    model: &PieceModel,
+    material: Handle<StandardMaterial>,
+    asset_server: &AssetServer,
+) {
+    for mesh in model.meshes.iter() {
+        parent.spawn_bundle(PbrBundle {
+            mesh: asset_server.load(mesh.as_str()),
+            material: material.clone(),
+            transform: model.transform(),
+            ..Default::default()
+        });
+    }
+}
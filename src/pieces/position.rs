@@ -0,0 +1,263 @@
+use std::fmt;
+
+use super::{
+    all_legal_moves, apply_move, is_in_check, parse_square, square_name, starting_position,
+    BoardState, CastlingRights, ChessMove, Piece, PieceColor, PieceType,
+};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Everything needed to continue a game from a position, as written in FEN
+#[derive(Clone)]
+pub struct Position {
+    pub pieces: Vec<Piece>,
+    pub state: BoardState,
+    pub turn: PieceColor,
+    // Half-moves since the last capture or pawn move, for the fifty-move rule
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+#[derive(Debug)]
+pub struct FenError(pub String);
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid FEN: {}", self.0)
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            pieces: starting_position(),
+            state: BoardState::default(),
+            turn: PieceColor::White,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+}
+
+impl Position {
+    /// Reads a FEN, or the first four fields of an EPD line, with the move
+    /// counters defaulting to a fresh game
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(FenError(format!("expected at least 4 fields in {:?}", fen)));
+        }
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError(format!("expected 8 ranks in {:?}", fields[0])));
+        }
+        let mut pieces = Vec::new();
+        for (i, rank) in ranks.iter().enumerate() {
+            let x = 7 - i as u8;
+            let mut y = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    y += empty as u8;
+                    continue;
+                }
+                let piece_type = PieceType::from_letter(c)
+                    .ok_or_else(|| FenError(format!("unknown piece {:?}", c)))?;
+                let color = if c.is_ascii_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                if y > 7 {
+                    return Err(FenError(format!("rank {:?} is too long", rank)));
+                }
+                pieces.push(Piece {
+                    color,
+                    piece_type,
+                    x,
+                    y,
+                });
+                y += 1;
+            }
+            if y != 8 {
+                return Err(FenError(format!("rank {:?} isn't 8 squares long", rank)));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            other => return Err(FenError(format!("unknown side to move {:?}", other))),
+        };
+
+        let castling = CastlingRights {
+            white_kingside: fields[2].contains('K'),
+            white_queenside: fields[2].contains('Q'),
+            black_kingside: fields[2].contains('k'),
+            black_queenside: fields[2].contains('q'),
+        };
+        let en_passant = match fields[3] {
+            "-" => None,
+            name => Some(
+                parse_square(name).ok_or_else(|| FenError(format!("unknown square {:?}", name)))?,
+            ),
+        };
+
+        let number = |index: usize, default: u32| {
+            fields
+                .get(index)
+                .and_then(|field| field.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Ok(Self {
+            pieces,
+            state: BoardState {
+                castling,
+                en_passant,
+            },
+            turn,
+            halfmove_clock: number(4, 0),
+            fullmove_number: number(5, 1),
+        })
+    }
+
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.fen_fields(self.state.en_passant),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// The first four FEN fields, equal for positions that count as repeated.
+    /// The en passant square is only there when a pawn can take on it.
+    pub fn key(&self) -> String {
+        self.fen_fields(self.en_passant_capture())
+    }
+
+    /// The en passant square, only when a pawn can actually take there, so
+    /// a double step nobody can answer doesn't tell positions apart
+    pub(super) fn en_passant_capture(&self) -> Option<(u8, u8)> {
+        let square = self.state.en_passant?;
+        let rank = match self.turn {
+            PieceColor::White => square.0.checked_sub(1)?,
+            PieceColor::Black => square.0 + 1,
+        };
+        self.pieces
+            .iter()
+            .any(|piece| {
+                piece.color == self.turn
+                    && piece.piece_type == PieceType::Pawn
+                    && piece.x == rank
+                    && (piece.y as i8 - square.1 as i8).abs() == 1
+            })
+            .then_some(square)
+    }
+
+    /// Placement, side to move, castling rights and the given en passant square
+    fn fen_fields(&self, en_passant: Option<(u8, u8)>) -> String {
+        let mut placement = String::new();
+        for x in (0..8).rev() {
+            let mut empty = 0;
+            for y in 0..8 {
+                match self.piece_at((x, y)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let letter = match piece.piece_type {
+                            PieceType::Pawn => "P",
+                            piece_type => piece_type.letter(),
+                        };
+                        placement.push_str(&match piece.color {
+                            PieceColor::White => letter.to_string(),
+                            PieceColor::Black => letter.to_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if x > 0 {
+                placement.push('/');
+            }
+        }
+
+        let turn = match self.turn {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+
+        let rights = self.state.castling;
+        let mut castling: String = [
+            (rights.white_kingside, 'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside, 'k'),
+            (rights.black_queenside, 'q'),
+        ]
+        .iter()
+        .filter(|(allowed, _)| *allowed)
+        .map(|(_, letter)| *letter)
+        .collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = en_passant.map_or("-".to_string(), square_name);
+
+        format!("{} {} {} {}", placement, turn, castling, en_passant)
+    }
+
+    pub fn piece_at(&self, position: (u8, u8)) -> Option<&Piece> {
+        self.pieces
+            .iter()
+            .find(|piece| (piece.x, piece.y) == position)
+    }
+
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        all_legal_moves(self.turn, &self.pieces, &self.state)
+    }
+
+    pub fn is_check(&self) -> bool {
+        is_in_check(self.turn, &self.pieces)
+    }
+
+    /// Make a legal move and pass the turn
+    pub fn play(&mut self, chess_move: &ChessMove) {
+        let piece = match self.piece_at(chess_move.from) {
+            Some(piece) => *piece,
+            None => return,
+        };
+
+        if piece.piece_type == PieceType::Pawn || chess_move.capture.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.state.update(chess_move, &piece);
+        self.pieces = apply_move(&self.pieces, chess_move);
+        self.turn = self.turn.opponent();
+    }
+
+    /// Neither side has enough pieces left to checkmate
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        for piece in self.pieces.iter() {
+            match piece.piece_type {
+                PieceType::King => (),
+                PieceType::Bishop | PieceType::Knight => minor_pieces += 1,
+                _ => return false,
+            }
+        }
+        minor_pieces <= 1
+    }
+}
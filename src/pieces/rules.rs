@@ -0,0 +1,275 @@
+use super::{color_of_square, Piece, PieceColor, PieceType};
+
+/// Which castling moves are still allowed
+#[derive(Clone, Copy, PartialEq)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        Self {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+}
+
+impl CastlingRights {
+    pub fn kingside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::White => self.white_kingside,
+            PieceColor::Black => self.black_kingside,
+        }
+    }
+
+    pub fn queenside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::White => self.white_queenside,
+            PieceColor::Black => self.black_queenside,
+        }
+    }
+
+    /// Moving from or to a king or rook starting square loses the matching rights
+    fn revoke(&mut self, square: (u8, u8)) {
+        match square {
+            (0, 4) => {
+                self.white_kingside = false;
+                self.white_queenside = false;
+            }
+            (7, 4) => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+            (0, 0) => self.white_queenside = false,
+            (0, 7) => self.white_kingside = false,
+            (7, 0) => self.black_queenside = false,
+            (7, 7) => self.black_kingside = false,
+            _ => (),
+        }
+    }
+}
+
+/// The parts of the game state that can't be read from the pieces alone
+#[derive(Clone, Copy, Default)]
+pub struct BoardState {
+    pub castling: CastlingRights,
+    // Square a pawn can move to when taking en passant
+    pub en_passant: Option<(u8, u8)>,
+}
+
+impl BoardState {
+    /// Update the state after `piece` has made `chess_move`
+    pub fn update(&mut self, chess_move: &ChessMove, piece: &Piece) {
+        self.castling.revoke(chess_move.from);
+        self.castling.revoke(chess_move.to);
+
+        let (from, to) = (chess_move.from, chess_move.to);
+        self.en_passant =
+            if piece.piece_type == PieceType::Pawn && (from.0 as i8 - to.0 as i8).abs() == 2 {
+                Some(((from.0 + to.0) / 2, from.1))
+            } else {
+                None
+            };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ChessMove {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    // Square of the taken piece, which is not the target square when taking en passant
+    pub capture: Option<(u8, u8)>,
+    // Start and end squares of the rook when castling
+    pub castle: Option<((u8, u8), (u8, u8))>,
+    pub promotion: Option<PieceType>,
+}
+
+impl ChessMove {
+    fn new(from: (u8, u8), to: (u8, u8), pieces: &[Piece]) -> Self {
+        Self {
+            from,
+            to,
+            capture: color_of_square(to, pieces).map(|_| to),
+            castle: None,
+            promotion: None,
+        }
+    }
+}
+
+const PROMOTION_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// Returns the moves the piece can make without leaving its own king in check
+pub fn legal_moves(piece: &Piece, pieces: &[Piece], state: &BoardState) -> Vec<ChessMove> {
+    let from = (piece.x, piece.y);
+    let mut moves = Vec::new();
+
+    for x in 0..8 {
+        for y in 0..8 {
+            if !piece.can_reach_position((x, y), pieces) {
+                continue;
+            }
+
+            let chess_move = ChessMove::new(from, (x, y), pieces);
+            if piece.piece_type == PieceType::Pawn && (x == 0 || x == 7) {
+                for promotion in PROMOTION_TYPES.iter() {
+                    moves.push(ChessMove {
+                        promotion: Some(*promotion),
+                        ..chess_move
+                    });
+                }
+            } else {
+                moves.push(chess_move);
+            }
+        }
+    }
+
+    if piece.piece_type == PieceType::Pawn {
+        if let Some(target) = state.en_passant {
+            let direction = match piece.color {
+                PieceColor::White => 1,
+                PieceColor::Black => -1,
+            };
+            if target.0 as i8 - piece.x as i8 == direction
+                && (target.1 as i8 - piece.y as i8).abs() == 1
+            {
+                moves.push(ChessMove {
+                    capture: Some((piece.x, target.1)),
+                    ..ChessMove::new(from, target, pieces)
+                });
+            }
+        }
+    }
+
+    if piece.piece_type == PieceType::King {
+        moves.extend(castling_moves(piece, pieces, state));
+    }
+
+    moves
+        .into_iter()
+        .filter(|chess_move| !is_in_check(piece.color, &apply_move(pieces, chess_move)))
+        .collect()
+}
+
+/// Returns every legal move for the given color
+pub fn all_legal_moves(color: PieceColor, pieces: &[Piece], state: &BoardState) -> Vec<ChessMove> {
+    pieces
+        .iter()
+        .filter(|piece| piece.color == color)
+        .flat_map(|piece| legal_moves(piece, pieces, state))
+        .collect()
+}
+
+fn castling_moves(king: &Piece, pieces: &[Piece], state: &BoardState) -> Vec<ChessMove> {
+    let rank = match king.color {
+        PieceColor::White => 0,
+        PieceColor::Black => 7,
+    };
+    let mut moves = Vec::new();
+
+    if (king.x, king.y) != (rank, 4) || is_in_check(king.color, pieces) {
+        return moves;
+    }
+
+    let has_rook = |y: u8| {
+        pieces.iter().any(|piece| {
+            piece.x == rank
+                && piece.y == y
+                && piece.color == king.color
+                && piece.piece_type == PieceType::Rook
+        })
+    };
+    let is_empty = |y: &u8| color_of_square((rank, *y), pieces).is_none();
+    let is_safe = |y: &u8| !is_square_attacked((rank, *y), king.color.opponent(), pieces);
+
+    if state.castling.kingside(king.color)
+        && has_rook(7)
+        && [5, 6].iter().all(is_empty)
+        && [5, 6].iter().all(is_safe)
+    {
+        moves.push(ChessMove {
+            from: (rank, 4),
+            to: (rank, 6),
+            capture: None,
+            castle: Some(((rank, 7), (rank, 5))),
+            promotion: None,
+        });
+    }
+
+    if state.castling.queenside(king.color)
+        && has_rook(0)
+        && [1, 2, 3].iter().all(is_empty)
+        && [2, 3].iter().all(is_safe)
+    {
+        moves.push(ChessMove {
+            from: (rank, 4),
+            to: (rank, 2),
+            capture: None,
+            castle: Some(((rank, 0), (rank, 3))),
+            promotion: None,
+        });
+    }
+
+    moves
+}
+
+/// Returns the pieces as they would be after making the move
+pub fn apply_move(pieces: &[Piece], chess_move: &ChessMove) -> Vec<Piece> {
+    pieces
+        .iter()
+        .filter(|piece| Some((piece.x, piece.y)) != chess_move.capture)
+        .map(|piece| {
+            let mut piece = *piece;
+            if (piece.x, piece.y) == chess_move.from {
+                piece.x = chess_move.to.0;
+                piece.y = chess_move.to.1;
+                if let Some(promotion) = chess_move.promotion {
+                    piece.piece_type = promotion;
+                }
+            } else if let Some((rook_from, rook_to)) = chess_move.castle {
+                if (piece.x, piece.y) == rook_from {
+                    piece.x = rook_to.0;
+                    piece.y = rook_to.1;
+                }
+            }
+            piece
+        })
+        .collect()
+}
+
+/// Returns true if any piece of the `by` color attacks the square
+pub fn is_square_attacked(square: (u8, u8), by: PieceColor, pieces: &[Piece]) -> bool {
+    pieces
+        .iter()
+        .filter(|piece| piece.color == by)
+        .any(|piece| {
+            if piece.piece_type == PieceType::Pawn {
+                // Pawns only take diagonally, whether or not the square is occupied
+                let direction = match piece.color {
+                    PieceColor::White => 1,
+                    PieceColor::Black => -1,
+                };
+                square.0 as i8 - piece.x as i8 == direction
+                    && (square.1 as i8 - piece.y as i8).abs() == 1
+            } else {
+                piece.can_reach_position(square, pieces)
+            }
+        })
+}
+
+pub fn is_in_check(color: PieceColor, pieces: &[Piece]) -> bool {
+    pieces
+        .iter()
+        .filter(|piece| piece.color == color && piece.piece_type == PieceType::King)
+        .any(|king| is_square_attacked((king.x, king.y), color.opponent(), pieces))
+}
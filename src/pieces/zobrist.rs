@@ -0,0 +1,69 @@
+use super::{PieceColor, PieceType, Position};
+
+/// Random numbers for every piece on every square, the side to move, each
+/// castling right and each en passant file. Hashes are stored in the game
+/// database, so the seed and layout must never change.
+const KEYS: [u64; 781] = zobrist_keys();
+const SIDE_KEY: usize = 768;
+const CASTLING_KEYS: usize = 769;
+const EN_PASSANT_KEYS: usize = 773;
+
+/// SplitMix64 from a fixed seed, worked out while compiling
+const fn zobrist_keys() -> [u64; 781] {
+    let mut keys = [0; 781];
+    let mut state: u64 = 0x0123_4567_89AB_CDEF;
+    let mut index = 0;
+    while index < keys.len() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[index] = key ^ (key >> 31);
+        index += 1;
+    }
+    keys
+}
+
+impl Position {
+    /// Zobrist hash of the position, equal for positions reached by
+    /// different move orders
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = 0;
+        for piece in self.pieces.iter() {
+            let kind = match piece.piece_type {
+                PieceType::King => 0,
+                PieceType::Queen => 1,
+                PieceType::Rook => 2,
+                PieceType::Bishop => 3,
+                PieceType::Knight => 4,
+                PieceType::Pawn => 5,
+            } + match piece.color {
+                PieceColor::White => 0,
+                PieceColor::Black => 6,
+            };
+            hash ^= KEYS[kind * 64 + piece.x as usize * 8 + piece.y as usize];
+        }
+
+        if self.turn == PieceColor::Black {
+            hash ^= KEYS[SIDE_KEY];
+        }
+        let rights = self.state.castling;
+        for (index, allowed) in [
+            rights.white_kingside,
+            rights.white_queenside,
+            rights.black_kingside,
+            rights.black_queenside,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if *allowed {
+                hash ^= KEYS[CASTLING_KEYS + index];
+            }
+        }
+        if let Some(square) = self.en_passant_capture() {
+            hash ^= KEYS[EN_PASSANT_KEYS + square.1 as usize];
+        }
+        hash
+    }
+}
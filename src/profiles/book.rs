@@ -0,0 +1,265 @@
+use std::{
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::GameResult, pieces::PieceColor};
+
+use super::Rating;
+
+/// Search depths the built-in engine has a profile for, weakest first
+pub const ENGINE_DEPTHS: RangeInclusive<u32> = 1..=5;
+/// The local player's profile, there from the first start
+pub const DEFAULT_PLAYER: &str = "Player";
+/// Who plays the other side when two people share the screen
+pub const DEFAULT_GUEST: &str = "Guest";
+
+/// The profile the built-in engine plays under at this search depth
+pub fn engine_profile_name(depth: u32) -> String {
+    format!("Built-in depth {}", depth)
+}
+
+/// Wins, draws and losses with one color
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Tally {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// A rated game and the rating it left the profile with
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RatingChange {
+    // Day the game ended in UTC, as in the game database
+    pub date: String,
+    pub color: PieceColor,
+    pub opponent: String,
+    pub result: GameResult,
+    pub rating: Rating,
+}
+
+/// A named player, a person or the engine at one depth, and how they fared
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    // The built-in engine's search depth, for engine profiles
+    #[serde(default)]
+    pub engine_depth: Option<u32>,
+    #[serde(default)]
+    pub rating: Rating,
+    #[serde(default)]
+    pub as_white: Tally,
+    #[serde(default)]
+    pub as_black: Tally,
+    // Oldest first
+    #[serde(default)]
+    pub history: Vec<RatingChange>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            engine_depth: None,
+            rating: Rating::default(),
+            as_white: Tally::default(),
+            as_black: Tally::default(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn engine(depth: u32) -> Self {
+        Self {
+            engine_depth: Some(depth),
+            ..Self::new(&engine_profile_name(depth))
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.as_white.games() + self.as_black.games()
+    }
+
+    fn tally_mut(&mut self, color: PieceColor) -> &mut Tally {
+        match color {
+            PieceColor::White => &mut self.as_white,
+            PieceColor::Black => &mut self.as_black,
+        }
+    }
+}
+
+/// Every profile, kept in one RON file written again after each rated game
+pub struct ProfileBook {
+    pub path: PathBuf,
+    pub profiles: Vec<Profile>,
+    // A file that couldn't be read isn't written over, which would lose the
+    // profiles in it
+    writable: bool,
+}
+
+/// Where the profiles are kept
+pub struct ProfileFile {
+    pub path: PathBuf,
+}
+
+impl Default for ProfileFile {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("profiles/profiles.ron"),
+        }
+    }
+}
+
+impl ProfileBook {
+    /// Read the profiles in the file, adding the default ones it lacks. The
+    /// file is created with the first rated game.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let profiles = match fs::read_to_string(path) {
+            Ok(source) => ron::de::from_str(&source).map_err(|error| error.to_string())?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut book = Self {
+            path: path.to_path_buf(),
+            profiles,
+            writable: true,
+        };
+        book.add_defaults();
+        Ok(book)
+    }
+
+    /// Open the profiles in the file, or only the default ones when it can't
+    /// be read. No game is rated into a file that couldn't be read.
+    pub fn load(path: &Path) -> Self {
+        Self::open(path).unwrap_or_else(|error| {
+            eprintln!("Skipping the profiles in {}: {}", path.display(), error);
+            let mut book = Self {
+                path: path.to_path_buf(),
+                profiles: Vec::new(),
+                writable: false,
+            };
+            book.add_defaults();
+            book
+        })
+    }
+
+    fn check_writable(&self) -> Result<(), String> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} couldn't be read, so it's left as it is",
+                self.path.display()
+            ))
+        }
+    }
+
+    pub fn write(&self) -> Result<(), String> {
+        self.check_writable()?;
+        if let Some(folder) = self.path.parent() {
+            fs::create_dir_all(folder).map_err(|error| error.to_string())?;
+        }
+        let source = ron::ser::to_string_pretty(&self.profiles, Default::default())
+            .map_err(|error| error.to_string())?;
+        fs::write(&self.path, source).map_err(|error| error.to_string())
+    }
+
+    fn add_defaults(&mut self) {
+        for name in [DEFAULT_PLAYER, DEFAULT_GUEST].iter() {
+            if self.get(name).is_none() {
+                self.profiles.push(Profile::new(name));
+            }
+        }
+        for depth in ENGINE_DEPTHS {
+            if self.get(&engine_profile_name(depth)).is_none() {
+                self.profiles.push(Profile::engine(depth));
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    fn get_or_add(&mut self, name: &str) -> &mut Profile {
+        let index = match self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+        {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile::new(name));
+                self.profiles.len() - 1
+            }
+        };
+        &mut self.profiles[index]
+    }
+
+    /// A new profile for a person, named after the ones already there
+    pub fn add_player(&mut self) -> String {
+        let name = (2..)
+            .map(|number| format!("{} {}", DEFAULT_PLAYER, number))
+            .find(|name| self.get(name).is_none())
+            .unwrap_or_default();
+        self.profiles.push(Profile::new(&name));
+        name
+    }
+
+    /// Rate a finished game between two profiles, adding the ones missing.
+    /// Both ratings are updated from the other's rating going into the game.
+    pub fn record_game(
+        &mut self,
+        (white, black): (&str, &str),
+        result: GameResult,
+        date: &str,
+    ) -> Result<(), String> {
+        self.check_writable()?;
+        if white == black {
+            return Err(format!("{} can't be rated against themselves", white));
+        }
+        let white_rating = self.get_or_add(white).rating;
+        let black_rating = self.get_or_add(black).rating;
+
+        for (color, name, opponent, opponent_rating) in [
+            (PieceColor::White, white, black, black_rating),
+            (PieceColor::Black, black, white, white_rating),
+        ]
+        .iter()
+        {
+            let profile = self.get_or_add(name);
+            let tally = profile.tally_mut(*color);
+            let score = match result.winner() {
+                Some(winner) if winner == *color => {
+                    tally.wins += 1;
+                    1.
+                }
+                Some(_) => {
+                    tally.losses += 1;
+                    0.
+                }
+                None => {
+                    tally.draws += 1;
+                    0.5
+                }
+            };
+            profile.rating = profile.rating.update(&[(*opponent_rating, score)]);
+            profile.history.push(RatingChange {
+                date: date.to_string(),
+                color: *color,
+                opponent: opponent.to_string(),
+                result,
+                rating: profile.rating,
+            });
+        }
+        Ok(())
+    }
+}
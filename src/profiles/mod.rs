@@ -0,0 +1,11 @@
+mod rating;
+pub use rating::*;
+
+mod book;
+pub use book::*;
+
+mod seats;
+pub use seats::*;
+
+mod page;
+pub use page::*;
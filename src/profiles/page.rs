@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+
+use crate::{game::Players, pieces::PieceColor};
+
+use super::{Profile, ProfileBook, Seats, Tally};
+
+/// Rated games listed for the profile shown, the latest ones
+const MAX_HISTORY: usize = 8;
+
+/// The screen listing the profiles and one profile's stats, opened from the
+/// settings menu
+#[derive(Default)]
+pub struct ProfilePage {
+    pub open: bool,
+    // Name of the profile whose stats are shown
+    pub selected: Option<String>,
+}
+
+// Component to mark the node holding the page
+struct ProfilePanel;
+
+#[derive(Clone)]
+enum ProfileButton {
+    Select(String),
+    Sit(PieceColor),
+    NewPlayer,
+    Close,
+}
+
+struct ProfileMaterials {
+    panel: Handle<ColorMaterial>,
+    button: Handle<ColorMaterial>,
+    selected: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+pub struct ProfilePagePlugin;
+impl Plugin for ProfilePagePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ProfilePage>()
+            .add_startup_system(init_profile_materials.system())
+            .add_system(profile_buttons.system().label("profile_buttons"))
+            .add_system(profile_page_update.system().after("profile_buttons"));
+    }
+}
+
+fn init_profile_materials(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ProfileMaterials {
+        panel: color_materials.add(Color::rgba(0.05, 0.05, 0.05, 0.95).into()),
+        button: color_materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+        selected: color_materials.add(Color::rgb(0.6, 0.5, 0.1).into()),
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+    });
+}
+
+fn profile_buttons(
+    mut page: ResMut<ProfilePage>,
+    mut book: ResMut<ProfileBook>,
+    mut seats: ResMut<Seats>,
+    query: Query<(&Interaction, &ProfileButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            ProfileButton::Select(name) => page.selected = Some(name.clone()),
+            ProfileButton::Sit(color) => {
+                let name = page.selected.clone();
+                match color {
+                    PieceColor::White => seats.white = name,
+                    PieceColor::Black => seats.black = name,
+                }
+            }
+            ProfileButton::NewPlayer => {
+                let name = book.add_player();
+                if let Err(error) = book.write() {
+                    eprintln!("Couldn't store the new profile: {}", error);
+                }
+                page.selected = Some(name);
+            }
+            ProfileButton::Close => page.open = false,
+        }
+    }
+}
+
+/// Lay the page out again whenever the profile shown, the seats or the
+/// ratings change
+fn profile_page_update(
+    mut commands: Commands,
+    page: Res<ProfilePage>,
+    book: Res<ProfileBook>,
+    (seats, players): (Res<Seats>, Res<Players>),
+    materials: Res<ProfileMaterials>,
+    panel_query: Query<Entity, With<ProfilePanel>>,
+) {
+    if !page.is_changed() && !book.is_changed() && !seats.is_changed() && !players.is_changed() {
+        return;
+    }
+
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !page.open {
+        return;
+    }
+
+    let (white, black) = seats.names(&players);
+    let selected = page.selected.as_deref().and_then(|name| book.get(name));
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(40.),
+                    right: Val::Px(40.),
+                    top: Val::Px(40.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                padding: Rect::all(Val::Px(10.)),
+                ..Default::default()
+            },
+            material: materials.panel.clone(),
+            ..Default::default()
+        })
+        .insert(ProfilePanel)
+        .with_children(|parent| {
+            spawn_label(
+                parent,
+                &materials,
+                format!("Profiles: {} plays White, {} plays Black", white, black),
+            );
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        flex_wrap: FlexWrap::Wrap,
+                        ..Default::default()
+                    },
+                    material: materials.panel.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for profile in book.profiles.iter() {
+                        spawn_button(
+                            parent,
+                            &materials,
+                            format!("{} ({:.0})", profile.name, profile.rating.rating),
+                            page.selected.as_ref() == Some(&profile.name),
+                            ProfileButton::Select(profile.name.clone()),
+                        );
+                    }
+                    spawn_button(
+                        parent,
+                        &materials,
+                        "New player".to_string(),
+                        false,
+                        ProfileButton::NewPlayer,
+                    );
+                });
+
+            if let Some(profile) = selected {
+                for line in stats_lines(profile) {
+                    spawn_label(parent, &materials, line);
+                }
+                for color in [PieceColor::White, PieceColor::Black].iter() {
+                    spawn_button(
+                        parent,
+                        &materials,
+                        format!("Play {} as {}", color.name(), profile.name),
+                        false,
+                        ProfileButton::Sit(*color),
+                    );
+                }
+            }
+
+            spawn_button(
+                parent,
+                &materials,
+                "Close".to_string(),
+                false,
+                ProfileButton::Close,
+            );
+        });
+}
+
+/// The profile's rating, results with each color and latest rated games
+fn stats_lines(profile: &Profile) -> Vec<String> {
+    let mut lines = vec![
+        format!(
+            "{}: rated {:.0} +/- {:.0}, {} games",
+            profile.name,
+            profile.rating.rating,
+            profile.rating.deviation * 2.,
+            profile.games()
+        ),
+        tally_line("With White", &profile.as_white),
+        tally_line("With Black", &profile.as_black),
+    ];
+    if profile.history.is_empty() {
+        lines.push("No rated games yet".to_string());
+    }
+    for change in profile.history.iter().rev().take(MAX_HISTORY) {
+        lines.push(format!(
+            "{}  {} vs {}  {}  rated {:.0}",
+            change.date,
+            change.color.name(),
+            change.opponent,
+            change.result.pgn(),
+            change.rating.rating
+        ));
+    }
+    lines
+}
+
+/// "With White: 12 games, +5 =3 -4"
+fn tally_line(label: &str, tally: &Tally) -> String {
+    format!(
+        "{}: {} games, +{} ={} -{}",
+        label,
+        tally.games(),
+        tally.wins,
+        tally.draws,
+        tally.losses
+    )
+}
+
+fn spawn_label(parent: &mut ChildBuilder, materials: &ProfileMaterials, label: String) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(4.)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            label,
+            TextStyle {
+                font: materials.font.clone(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    materials: &ProfileMaterials,
+    label: String,
+    selected: bool,
+    button: ProfileButton,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(2.)),
+                padding: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: if selected {
+                materials.selected.clone()
+            } else {
+                materials.button.clone()
+            },
+            ..Default::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    label,
+                    TextStyle {
+                        font: materials.font.clone(),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
@@ -0,0 +1,127 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Glicko-2's scale factor between the Elo-like rating and its own units
+const SCALE: f64 = 173.7178;
+/// How much the volatility may change from one game to the next
+const TAU: f64 = 0.5;
+/// When the volatility iteration stops
+const TOLERANCE: f64 = 0.000_001;
+
+/// A Glicko-2 rating: a strength on the Elo scale, how sure it is and how
+/// erratically the player performs
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    // Rating deviation, large while few games have been played
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: 1500.,
+            deviation: 350.,
+            volatility: 0.06,
+        }
+    }
+}
+
+impl Rating {
+    /// Expected score against `opponent`, from 0 to 1
+    pub fn expected_score(&self, opponent: &Rating) -> f64 {
+        let (mu, _) = self.scaled();
+        let (opponent_mu, opponent_phi) = opponent.scaled();
+        expectation(mu, opponent_mu, opponent_phi)
+    }
+
+    /// The rating after a period of games, each with the opponent's rating
+    /// going in and the score taken from 0 to 1
+    pub fn update(&self, results: &[(Rating, f64)]) -> Rating {
+        let (mu, phi) = self.scaled();
+        if results.is_empty() {
+            // Only grows less sure of itself
+            let phi = (phi * phi + self.volatility * self.volatility).sqrt();
+            return Self::unscaled(mu, phi, self.volatility);
+        }
+
+        let mut variance_inverse = 0.;
+        let mut improvement = 0.;
+        for (opponent, score) in results.iter() {
+            let (opponent_mu, opponent_phi) = opponent.scaled();
+            let g = weight(opponent_phi);
+            let expected = expectation(mu, opponent_mu, opponent_phi);
+            variance_inverse += g * g * expected * (1. - expected);
+            improvement += g * (score - expected);
+        }
+        let variance = 1. / variance_inverse;
+        let delta = variance * improvement;
+
+        let volatility = self.next_volatility(phi, variance, delta);
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1. / (1. / (phi_star * phi_star) + variance_inverse).sqrt();
+        let new_mu = mu + new_phi * new_phi * improvement;
+        Self::unscaled(new_mu, new_phi, volatility)
+    }
+
+    /// Solves for the new volatility with the Illinois algorithm, as in
+    /// Glickman's description of Glicko-2
+    fn next_volatility(&self, phi: f64, variance: f64, delta: f64) -> f64 {
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let exp = x.exp();
+            let denominator = phi * phi + variance + exp;
+            exp * (delta * delta - denominator) / (2. * denominator * denominator)
+                - (x - a) / (TAU * TAU)
+        };
+
+        let mut low = a;
+        let mut high = if delta * delta > phi * phi + variance {
+            (delta * delta - phi * phi - variance).ln()
+        } else {
+            let mut k = 1.;
+            while f(a - k * TAU) < 0. {
+                k += 1.;
+            }
+            a - k * TAU
+        };
+        let mut f_low = f(low);
+        let mut f_high = f(high);
+        while (high - low).abs() > TOLERANCE {
+            let middle = low + (low - high) * f_low / (f_high - f_low);
+            let f_middle = f(middle);
+            if f_middle * f_high <= 0. {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.;
+            }
+            high = middle;
+            f_high = f_middle;
+        }
+        (low / 2.).exp()
+    }
+
+    fn scaled(&self) -> (f64, f64) {
+        ((self.rating - 1500.) / SCALE, self.deviation / SCALE)
+    }
+
+    fn unscaled(mu: f64, phi: f64, volatility: f64) -> Self {
+        Self {
+            rating: mu * SCALE + 1500.,
+            deviation: phi * SCALE,
+            volatility,
+        }
+    }
+}
+
+/// How much a game counts given how unsure the opponent's rating is
+fn weight(phi: f64) -> f64 {
+    1. / (1. + 3. * phi * phi / (PI * PI)).sqrt()
+}
+
+fn expectation(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1. / (1. + (-weight(opponent_phi) * (mu - opponent_mu)).exp())
+}
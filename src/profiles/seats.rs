@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::{
+    database::today,
+    game::{GameOver, PlayerKind, Players},
+    headless::is_headless,
+    history::MoveHistory,
+    pieces::PieceColor,
+    save::GameLoaded,
+};
+
+use super::{
+    engine_profile_name, ProfileBook, ProfileFile, ProfilePagePlugin, DEFAULT_GUEST, DEFAULT_PLAYER,
+};
+
+/// The profiles picked to play each color, the others going by who sits there
+#[derive(Default)]
+pub struct Seats {
+    pub white: Option<String>,
+    pub black: Option<String>,
+}
+
+impl Seats {
+    /// The profile name playing `color`, as written in the database and PGN
+    pub fn name(&self, color: PieceColor, players: &Players) -> String {
+        let picked = match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        };
+        if let Some(name) = picked {
+            return name.clone();
+        }
+        match players.get(color) {
+            // Two people sharing the screen
+            PlayerKind::Human
+                if color == PieceColor::Black
+                    && players.get(PieceColor::White) == PlayerKind::Human =>
+            {
+                DEFAULT_GUEST.to_string()
+            }
+            PlayerKind::Human => DEFAULT_PLAYER.to_string(),
+            PlayerKind::Computer => {
+                engine_profile_name(players.engine(color).unwrap_or_default().depth)
+            }
+            PlayerKind::Remote => "Opponent".to_string(),
+        }
+    }
+
+    pub fn names(&self, players: &Players) -> (String, String) {
+        (
+            self.name(PieceColor::White, players),
+            self.name(PieceColor::Black, players),
+        )
+    }
+}
+
+/// Rates every finished game between the profiles seated, and shows their
+/// stats when there's a window. Needs the `SavePlugin`, so games loaded back
+/// aren't rated twice.
+pub struct ProfilesPlugin;
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ProfileFile>();
+        let path = app
+            .world()
+            .get_resource::<ProfileFile>()
+            .unwrap()
+            .path
+            .clone();
+        app.insert_resource(ProfileBook::load(&path))
+            .init_resource::<Seats>()
+            .add_system(rate_finished_games.system().after("load_games"));
+
+        if !is_headless(app) {
+            app.add_plugin(ProfilePagePlugin);
+        }
+    }
+}
+
+fn rate_finished_games(
+    mut book: ResMut<ProfileBook>,
+    seats: Res<Seats>,
+    game_over: Res<GameOver>,
+    history: Res<MoveHistory>,
+    players: Res<Players>,
+    mut loaded: EventReader<GameLoaded>,
+    mut rated: Local<bool>,
+) {
+    // A finished game that was loaded was rated when it was played
+    if loaded.iter().count() > 0 {
+        *rated = game_over.0.is_some();
+        return;
+    }
+
+    let outcome = match game_over.0 {
+        Some(outcome) => outcome,
+        None => {
+            *rated = false;
+            return;
+        }
+    };
+    if *rated || history.moves.is_empty() {
+        return;
+    }
+    *rated = true;
+
+    // Whoever is on the other end of the network has no profile here
+    if players.white == PlayerKind::Remote || players.black == PlayerKind::Remote {
+        return;
+    }
+    let (white, black) = seats.names(&players);
+    let recorded = book
+        .record_game((&white, &black), outcome.result, &today())
+        .and_then(|_| book.write());
+    if let Err(error) = recorded {
+        eprintln!("Couldn't rate the game: {}", error);
+    }
+}
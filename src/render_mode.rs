@@ -0,0 +1,252 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::{
+    board::Square,
+    camera::CameraView,
+    history::{HistoryView, PastPositionPiece},
+    move_input::MoveInput,
+    pieces::*,
+};
+
+/// Sheet with one column per piece type, in the order of `sprite_index`,
+/// and a row of white pieces above a row of black ones
+const PIECE_SPRITE_SHEET: &str = "sprites/pieces.png";
+const SPRITE_SIZE: f32 = 128.;
+/// Share of a square a sprite covers
+const SPRITE_SCALE: f32 = 0.9;
+/// Lift above the board, so the sprites aren't hidden inside the squares
+const SPRITE_HEIGHT: f32 = 0.01;
+
+/// Whether the board is drawn in 3D with models or from above with sprites
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum RenderMode {
+    #[default]
+    ThreeD,
+    TwoD,
+}
+
+impl RenderMode {
+    /// 3D unless the game was started with `--2d`
+    pub fn from_args() -> Self {
+        if std::env::args().any(|arg| arg == "--2d") {
+            RenderMode::TwoD
+        } else {
+            RenderMode::ThreeD
+        }
+    }
+}
+
+/// Marks the sprite drawn for a piece in 2D, a child of the piece entity
+struct PieceSprite;
+
+struct PieceSpriteSheet(Handle<TextureAtlas>);
+
+pub struct RenderModePlugin;
+impl Plugin for RenderModePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<RenderMode>()
+            .add_startup_system(load_piece_sprites.system())
+            .add_system(render_mode_hotkeys.system())
+            .add_system(add_piece_sprites.system().label("add_piece_sprites"))
+            .add_system(orient_piece_sprites.system().after("add_piece_sprites"))
+            .add_system(show_pieces.system().after("add_piece_sprites"))
+            .add_system(light_squares.system());
+    }
+}
+
+fn load_piece_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let atlas = TextureAtlas::from_grid(
+        asset_server.load(PIECE_SPRITE_SHEET),
+        Vec2::new(SPRITE_SIZE, SPRITE_SIZE),
+        6,
+        2,
+    );
+    commands.insert_resource(PieceSpriteSheet(atlases.add(atlas)));
+}
+
+/// V switches between 3D and 2D
+fn render_mode_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut render_mode: ResMut<RenderMode>,
+) {
+    // The key is part of the move being typed
+    if move_input.active {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::V) {
+        *render_mode = match *render_mode {
+            RenderMode::ThreeD => RenderMode::TwoD,
+            RenderMode::TwoD => RenderMode::ThreeD,
+        };
+    }
+}
+
+fn sprite_index(color: PieceColor, piece_type: PieceType) -> u32 {
+    let row = match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    };
+    let column = match piece_type {
+        PieceType::King => 0,
+        PieceType::Queen => 1,
+        PieceType::Rook => 2,
+        PieceType::Bishop => 3,
+        PieceType::Knight => 4,
+        PieceType::Pawn => 5,
+    };
+    row * 6 + column
+}
+
+/// Lay a sprite flat on the board, its top towards the far side of the screen
+fn sprite_transform(orientation: PieceColor) -> Transform {
+    let turn = match orientation {
+        PieceColor::White => -FRAC_PI_2,
+        PieceColor::Black => FRAC_PI_2,
+    };
+    Transform {
+        translation: Vec3::new(0., SPRITE_HEIGHT, 0.),
+        rotation: Quat::from_rotation_y(turn) * Quat::from_rotation_x(-FRAC_PI_2),
+        scale: Vec3::splat(SPRITE_SCALE / SPRITE_SIZE),
+    }
+}
+
+/// Give every piece on the board, in the trays or in a past position a sprite.
+/// Promoted pieces get a new one along with their new model.
+#[allow(clippy::type_complexity)]
+fn add_piece_sprites(
+    mut commands: Commands,
+    render_mode: Res<RenderMode>,
+    sprite_sheet: Res<PieceSpriteSheet>,
+    view: Res<CameraView>,
+    query: Query<(
+        Entity,
+        Option<&Children>,
+        Option<&Piece>,
+        Option<&Captured>,
+        Option<&PastPositionPiece>,
+    )>,
+    sprites_query: Query<&TextureAtlasSprite, With<PieceSprite>>,
+) {
+    for (entity, children, piece, captured, past_piece) in query.iter() {
+        let (color, piece_type) = match (piece, captured, past_piece) {
+            (Some(piece), _, _) | (_, _, Some(PastPositionPiece(piece))) => {
+                (piece.color, piece.piece_type)
+            }
+            (_, Some(captured), _) => (captured.color, captured.piece_type),
+            _ => continue,
+        };
+        let index = sprite_index(color, piece_type);
+
+        let sprites: Vec<(Entity, u32)> = children
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|child| {
+                        sprites_query
+                            .get(*child)
+                            .ok()
+                            .map(|sprite| (*child, sprite.index))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if sprites
+            .iter()
+            .any(|(_, sprite_index)| *sprite_index == index)
+        {
+            continue;
+        }
+
+        for (sprite, _) in sprites {
+            commands.entity(sprite).despawn_recursive();
+        }
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteSheetBundle {
+                    texture_atlas: sprite_sheet.0.clone(),
+                    sprite: TextureAtlasSprite::new(index),
+                    transform: sprite_transform(view.orientation),
+                    visible: Visible {
+                        is_visible: *render_mode == RenderMode::TwoD,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(PieceSprite);
+        });
+    }
+}
+
+/// Turn the sprites with the board when it's flipped
+fn orient_piece_sprites(
+    view: Res<CameraView>,
+    mut query: Query<&mut Transform, With<PieceSprite>>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    let transform = sprite_transform(view.orientation);
+    for mut sprite_transform in query.iter_mut() {
+        if *sprite_transform != transform {
+            *sprite_transform = transform;
+        }
+    }
+}
+
+/// Show either the models or the sprites of the pieces, and hide the live
+/// pieces while a past position is shown
+#[allow(clippy::type_complexity)]
+fn show_pieces(
+    render_mode: Res<RenderMode>,
+    history_view: Res<HistoryView>,
+    pieces_query: Query<
+        (&Children, Option<&Piece>),
+        Or<(With<Piece>, With<Captured>, With<PastPositionPiece>)>,
+    >,
+    mut visible_query: Query<(&mut Visible, Option<&PieceSprite>)>,
+) {
+    for (children, piece) in pieces_query.iter() {
+        let is_shown = piece.is_none() || history_view.ply.is_none();
+        for child in children.iter() {
+            if let Ok((mut visible, sprite)) = visible_query.get_mut(*child) {
+                let is_visible = is_shown
+                    && match *render_mode {
+                        RenderMode::ThreeD => sprite.is_none(),
+                        RenderMode::TwoD => sprite.is_some(),
+                    };
+                if visible.is_visible != is_visible {
+                    visible.is_visible = is_visible;
+                }
+            }
+        }
+    }
+}
+
+/// Draw the squares in their flat theme colors in 2D, without shading
+fn light_squares(
+    render_mode: Res<RenderMode>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<&Handle<StandardMaterial>, Added<Square>>,
+    squares_query: Query<&Handle<StandardMaterial>, With<Square>>,
+) {
+    let handles: Vec<&Handle<StandardMaterial>> = if render_mode.is_changed() {
+        squares_query.iter().collect()
+    } else {
+        query.iter().collect()
+    };
+
+    for handle in handles {
+        if let Some(material) = materials.get_mut(handle) {
+            material.unlit = *render_mode == RenderMode::TwoD;
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{PlayerTurn, ResetSelectedEvent},
+    clock::{GameClock, TimeControl},
+    engine::SearchLimits,
+    game::{DrawOffer, GameOutcome, GameOver, GameResult, PlayerKind, Players, Termination},
+    headless::is_headless,
+    history::{HistoryView, MoveHistory, MoveRecord},
+    move_input::MoveInput,
+    network::Network,
+    pieces::*,
+};
+
+/// Bumped whenever saved games change in a way older versions can't read
+const SAVE_VERSION: u32 = 1;
+
+/// Everything needed to carry on with a game later, written as RON
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    // FEN of the position the game started from
+    pub start: String,
+    // Every move so far in UCI notation, though SAN reads as well
+    pub moves: Vec<String>,
+    // Plies that came with a draw offer, for the PGN
+    #[serde(default)]
+    pub draw_offers: Vec<usize>,
+    // An offer still waiting for an answer
+    #[serde(default)]
+    pub draw_offer: Option<PieceColor>,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    #[serde(default)]
+    pub white_clock: Duration,
+    #[serde(default)]
+    pub black_clock: Duration,
+    pub white: PlayerKind,
+    pub black: PlayerKind,
+    // How hard the engine searches for each side it plays
+    #[serde(default)]
+    pub white_engine: Option<SearchLimits>,
+    #[serde(default)]
+    pub black_engine: Option<SearchLimits>,
+    #[serde(default)]
+    pub result: Option<(GameResult, Termination)>,
+}
+
+/// A saved game played through again, ready to be put on the board
+pub struct ReplayedGame {
+    pub records: Vec<MoveRecord>,
+    pub position: Position,
+    // Pieces taken along the way, in the order they fell
+    pub captured: Vec<Piece>,
+}
+
+impl Default for SavedGame {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            start: STARTING_FEN.to_string(),
+            moves: Vec::new(),
+            draw_offers: Vec::new(),
+            draw_offer: None,
+            time_control: None,
+            white_clock: Duration::default(),
+            black_clock: Duration::default(),
+            white: PlayerKind::Human,
+            black: PlayerKind::Human,
+            white_engine: None,
+            black_engine: None,
+            result: None,
+        }
+    }
+}
+
+impl SavedGame {
+    pub fn new(
+        history: &MoveHistory,
+        clock: &GameClock,
+        players: &Players,
+        draw_offer: &DrawOffer,
+        game_over: &GameOver,
+    ) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            start: history
+                .start
+                .clone()
+                .unwrap_or_else(|| STARTING_FEN.to_string()),
+            moves: history.moves.iter().map(MoveRecord::uci).collect(),
+            draw_offers: history
+                .moves
+                .iter()
+                .enumerate()
+                .filter(|(_, record)| record.draw_offered)
+                .map(|(ply, _)| ply)
+                .collect(),
+            draw_offer: draw_offer.offered_by,
+            time_control: clock.time_control,
+            white_clock: clock.white,
+            black_clock: clock.black,
+            white: players.white,
+            black: players.black,
+            white_engine: players.engine(PieceColor::White),
+            black_engine: players.engine(PieceColor::Black),
+            result: game_over
+                .0
+                .map(|outcome| (outcome.result, outcome.termination)),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let saved: Self = ron::de::from_str(&source).map_err(|error| error.to_string())?;
+        if saved.version != SAVE_VERSION {
+            return Err(format!(
+                "Saved by version {} of the game, this is version {}",
+                saved.version, SAVE_VERSION
+            ));
+        }
+        Ok(saved)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).map_err(|error| error.to_string())?;
+        }
+        let source = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|error| error.to_string())?;
+        fs::write(path, source).map_err(|error| error.to_string())
+    }
+
+    /// Play the moves from the start, failing on the first one that isn't legal
+    pub fn replay(&self) -> Result<ReplayedGame, String> {
+        let mut position = Position::from_fen(&self.start).map_err(|error| error.to_string())?;
+        let mut records = Vec::new();
+        let mut captured = Vec::new();
+        for (ply, input) in self.moves.iter().enumerate() {
+            let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+                .map_err(|error| format!("Move {} ({}): {}", ply / 2 + 1, input, error))?;
+            if let Some(piece) = chess_move
+                .capture
+                .and_then(|square| position.piece_at(square))
+            {
+                captured.push(*piece);
+            }
+            let san = san(&chess_move, &position.pieces, &position.state);
+            let color = position.turn;
+            position.play(&chess_move);
+            records.push(MoveRecord {
+                color,
+                from: chess_move.from,
+                to: chess_move.to,
+                san,
+                draw_offered: self.draw_offers.contains(&ply),
+                position: position.pieces.clone(),
+            });
+        }
+        Ok(ReplayedGame {
+            records,
+            position,
+            captured,
+        })
+    }
+}
+
+/// Where games are saved, inserted before the plugin to use other files
+pub struct SaveFiles {
+    // Written and read with Ctrl+S and Ctrl+L or from the settings menu
+    pub game: PathBuf,
+    // Written when the window closes, and offered when the game starts again
+    pub autosave: PathBuf,
+}
+
+impl Default for SaveFiles {
+    fn default() -> Self {
+        Self {
+            game: PathBuf::from("saves/game.ron"),
+            autosave: PathBuf::from("saves/autosave.ron"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum SaveAction {
+    Save(PathBuf),
+    Load(PathBuf),
+    // Show a game from elsewhere, starting from its first move
+    Replay(SavedGame),
+}
+
+/// Sent once a game has been put on the board in place of the one played
+pub struct GameLoaded;
+
+/// How the last save or load went
+#[derive(Default)]
+pub struct SaveStatus {
+    pub message: Option<String>,
+}
+
+// Component to mark the Text entity showing the save status
+struct SaveStatusText;
+
+// Component to mark the button resuming the autosaved game
+struct ContinueButton;
+
+/// Saves games to files and resumes them, including the one left when the
+/// window was closed. Start with `--continue` to resume that one right away.
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SaveFiles>()
+            .init_resource::<SaveStatus>()
+            .add_event::<SaveAction>()
+            .add_event::<GameLoaded>()
+            .add_startup_system(continue_from_args.system())
+            .add_system(save_games.system())
+            .add_system(load_games.system().label("load_games"))
+            .add_system_to_stage(CoreStage::Last, autosave_on_exit.system());
+
+        if !is_headless(app) {
+            app.add_startup_system(init_save_status_text.system())
+                .add_startup_system(init_continue_button.system())
+                .add_system(save_hotkeys.system())
+                .add_system(continue_button.system())
+                .add_system(save_status_text_update.system());
+        }
+    }
+}
+
+fn continue_from_args(files: Res<SaveFiles>, mut actions: EventWriter<SaveAction>) {
+    if std::env::args().any(|arg| arg == "--continue") {
+        actions.send(SaveAction::Load(files.autosave.clone()));
+    }
+}
+
+fn save_games(
+    mut actions: EventReader<SaveAction>,
+    mut status: ResMut<SaveStatus>,
+    history: Res<MoveHistory>,
+    clock: Res<GameClock>,
+    players: Res<Players>,
+    draw_offer: Res<DrawOffer>,
+    game_over: Res<GameOver>,
+) {
+    for action in actions.iter() {
+        let path = match action {
+            SaveAction::Save(path) => path,
+            _ => continue,
+        };
+        let saved = SavedGame::new(&history, &clock, &players, &draw_offer, &game_over);
+        status.message = Some(match saved.write(path) {
+            Ok(()) => format!("Saved the game to {}", path.display()),
+            Err(error) => format!("Couldn't save the game: {}", error),
+        });
+    }
+}
+
+/// Put a saved game on the board in place of the one being played
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn load_games(
+    mut commands: Commands,
+    mut actions: EventReader<SaveAction>,
+    mut status: ResMut<SaveStatus>,
+    network: Option<Res<Network>>,
+    (materials, asset_server, piece_sets): (
+        Option<Res<PieceMaterials>>,
+        Option<Res<AssetServer>>,
+        Res<PieceSets>,
+    ),
+    (mut history, mut view, mut turn, mut board_state): (
+        ResMut<MoveHistory>,
+        ResMut<HistoryView>,
+        ResMut<PlayerTurn>,
+        ResMut<BoardState>,
+    ),
+    (mut clock, mut players, mut draw_offer, mut game_over): (
+        ResMut<GameClock>,
+        ResMut<Players>,
+        ResMut<DrawOffer>,
+        ResMut<GameOver>,
+    ),
+    (mut reset_selected, mut loaded_events): (
+        EventWriter<ResetSelectedEvent>,
+        EventWriter<GameLoaded>,
+    ),
+    pieces_query: Query<Entity, Or<(With<Piece>, With<Captured>)>>,
+) {
+    for action in actions.iter() {
+        let (name, saved) = match action {
+            SaveAction::Load(path) => (path.display().to_string(), SavedGame::read(path)),
+            SaveAction::Replay(saved) => ("the game".to_string(), Ok(saved.clone())),
+            SaveAction::Save(_) => continue,
+        };
+        // The other player's game wouldn't follow
+        if network.is_some() {
+            status.message = Some("Games over the network can't be replaced".to_string());
+            continue;
+        }
+        let loaded = saved.and_then(|saved| saved.replay().map(|game| (saved, game)));
+        let (saved, game) = match loaded {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                status.message = Some(format!("Couldn't load {}: {}", name, error));
+                continue;
+            }
+        };
+
+        for entity in pieces_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        let models = match (&materials, &asset_server) {
+            (Some(materials), Some(asset_server)) => Some((&**materials, &**asset_server)),
+            _ => None,
+        };
+        for piece in game.position.pieces.iter() {
+            spawn_saved_piece(&mut commands, piece, models, piece_sets.current());
+        }
+        for piece in game.captured.iter() {
+            let entity = spawn_saved_piece(&mut commands, piece, models, piece_sets.current());
+            commands.entity(entity).remove::<Piece>().insert(Captured {
+                color: piece.color,
+                piece_type: piece.piece_type,
+            });
+        }
+
+        history.start = Some(saved.start.clone()).filter(|start| start != STARTING_FEN);
+        history.moves = game.records;
+        view.ply = match action {
+            SaveAction::Replay(_) if !history.moves.is_empty() => Some(0),
+            _ => None,
+        };
+        turn.0 = game.position.turn;
+        *board_state = game.position.state;
+        *clock = GameClock::resumed(
+            saved.time_control,
+            saved.white_clock,
+            saved.black_clock,
+            history.moves.len(),
+        );
+        *players = Players {
+            white: saved.white,
+            black: saved.black,
+            white_engine: saved.white_engine.unwrap_or_default(),
+            black_engine: saved.black_engine.unwrap_or_default(),
+        };
+        draw_offer.offered_by = saved.draw_offer;
+        game_over.0 = saved.result.map(|(result, termination)| GameOutcome {
+            result,
+            termination,
+        });
+        reset_selected.send(ResetSelectedEvent);
+        loaded_events.send(GameLoaded);
+        status.message = Some(format!("Loaded {}", name));
+    }
+}
+
+/// Spawn a piece the way the game starts with them, or without a model when
+/// there's nothing to draw it with
+fn spawn_saved_piece(
+    commands: &mut Commands,
+    piece: &Piece,
+    models: Option<(&PieceMaterials, &AssetServer)>,
+    piece_set: &PieceSet,
+) -> Entity {
+    match models {
+        Some((materials, asset_server)) => spawn_piece(
+            commands,
+            materials.get(piece.color),
+            piece.color,
+            piece.piece_type,
+            (piece.x, piece.y),
+            piece_set,
+            asset_server,
+        ),
+        None => commands.spawn().insert(*piece).id(),
+    }
+}
+
+/// Keep an unfinished game for next time when the app closes, and forget a
+/// finished one so it isn't offered again
+#[allow(clippy::too_many_arguments)]
+fn autosave_on_exit(
+    mut exit: EventReader<AppExit>,
+    files: Res<SaveFiles>,
+    network: Option<Res<Network>>,
+    history: Res<MoveHistory>,
+    clock: Res<GameClock>,
+    players: Res<Players>,
+    draw_offer: Res<DrawOffer>,
+    game_over: Res<GameOver>,
+) {
+    if exit.iter().next().is_none() || network.is_some() {
+        return;
+    }
+
+    if game_over.0.is_some() {
+        let _ = fs::remove_file(&files.autosave);
+    } else if !history.moves.is_empty() {
+        let saved = SavedGame::new(&history, &clock, &players, &draw_offer, &game_over);
+        if let Err(error) = saved.write(&files.autosave) {
+            eprintln!("Couldn't save the game for next time: {}", error);
+        }
+    }
+}
+
+/// Ctrl+S saves the game and Ctrl+L loads the saved one
+fn save_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Option<Res<MoveInput>>,
+    files: Res<SaveFiles>,
+    mut actions: EventWriter<SaveAction>,
+) {
+    // The keys are part of the move being typed
+    if move_input.is_some_and(|input| input.active) {
+        return;
+    }
+    if !keyboard_input.pressed(KeyCode::LControl) && !keyboard_input.pressed(KeyCode::RControl) {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::S) {
+        actions.send(SaveAction::Save(files.game.clone()));
+    } else if keyboard_input.just_pressed(KeyCode::L) {
+        actions.send(SaveAction::Load(files.game.clone()));
+    }
+}
+
+fn init_save_status_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(110.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(SaveStatusText);
+}
+
+fn save_status_text_update(
+    status: Res<SaveStatus>,
+    mut query: Query<&mut Text, With<SaveStatusText>>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = status.message.clone().unwrap_or_default();
+    }
+}
+
+/// Offer to carry on with the game left when the window was last closed
+fn init_continue_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    files: Res<SaveFiles>,
+    network: Option<Res<Network>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if network.is_some() || !files.autosave.exists() {
+        return;
+    }
+
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(140.),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: color_materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            ..Default::default()
+        })
+        .insert(ContinueButton)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Continue last game",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+/// Resume the autosaved game when clicked, the offer goes away once a new
+/// game gets going
+fn continue_button(
+    mut commands: Commands,
+    files: Res<SaveFiles>,
+    history: Res<MoveHistory>,
+    mut actions: EventWriter<SaveAction>,
+    query: Query<(Entity, &Interaction), With<ContinueButton>>,
+) {
+    for (entity, interaction) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            actions.send(SaveAction::Load(files.autosave.clone()));
+        } else if history.moves.is_empty() {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use serde_json::Value;
+use tungstenite::{Message as Frame, WebSocket};
+
+use crate::network::{Message, HEARTBEAT_INTERVAL, PEER_TIMEOUT};
+
+/// How often the listener checks whether the server is shutting down
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+/// How long a WebSocket connection reads before sending what the hub has, and
+/// how often a new connection is checked for its first bytes
+const WEBSOCKET_POLL: Duration = Duration::from_millis(20);
+
+/// What the connection threads tell the hub
+pub enum ServerEvent {
+    // Messages for the client go to the sender, dropping it hangs up
+    Connected(u64, SocketAddr, Sender<Message>),
+    Message(u64, Message),
+    // Something the client sent that couldn't be read, and why
+    Malformed(u64, String),
+    Disconnected(u64),
+    Stop,
+}
+
+/// Accept clients until `stopped` is set, giving each its own threads
+pub fn accept(listener: TcpListener, events: Sender<ServerEvent>, stopped: Arc<AtomicBool>) {
+    let mut next_id = 0;
+    while !stopped.load(Ordering::Relaxed) {
+        let (stream, peer) = match listener.accept() {
+            Ok(connection) => connection,
+            Err(_) => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+        };
+        next_id += 1;
+        let id = next_id;
+        let events = events.clone();
+        thread::spawn(move || serve(id, stream, peer, events));
+    }
+}
+
+/// Talk to a client in whichever way it speaks: JSON lines like a game does,
+/// or WebSocket text messages like a browser does
+fn serve(id: u64, stream: TcpStream, peer: SocketAddr, events: Sender<ServerEvent>) {
+    let reader = match prepare(&stream) {
+        Ok(reader) => reader,
+        Err(_) => return,
+    };
+    if is_websocket(&stream) {
+        if let Ok(socket) = tungstenite::accept(stream) {
+            serve_websocket(id, socket, peer, events);
+        }
+        return;
+    }
+
+    let (sender, outgoing) = crossbeam_channel::unbounded();
+    if events
+        .send(ServerEvent::Connected(id, peer, sender))
+        .is_err()
+    {
+        return;
+    }
+    thread::spawn(move || write_messages(stream, outgoing));
+    read_messages(id, reader, events);
+}
+
+/// Same settings as a game's own link, so silence means the client is gone
+fn prepare(stream: &TcpStream) -> io::Result<TcpStream> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+    stream.try_clone()
+}
+
+/// Whether the client opened with an HTTP request, which can only be the
+/// start of a WebSocket handshake
+fn is_websocket(stream: &TcpStream) -> bool {
+    let mut start = [0; 4];
+    let started = Instant::now();
+    loop {
+        match stream.peek(&mut start) {
+            Ok(4) => return &start == b"GET ",
+            Ok(read) if read > 0 && started.elapsed() < PEER_TIMEOUT => {
+                thread::sleep(WEBSOCKET_POLL)
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Send what the hub has for the client, pinging when there's nothing, until
+/// the hub hangs up or the connection drops
+fn write_messages(mut stream: TcpStream, outgoing: Receiver<Message>) {
+    loop {
+        let message = match outgoing.recv_timeout(HEARTBEAT_INTERVAL) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => Message::Ping,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if writeln!(stream, "{}", message.encode()).is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+fn read_messages(id: u64, reader: TcpStream, events: Sender<ServerEvent>) {
+    for line in BufReader::new(&reader).lines().map_while(Result::ok) {
+        if !forward(id, &line, &events) {
+            break;
+        }
+    }
+    // Wakes the writer up if it's the client that went away
+    let _ = reader.shutdown(Shutdown::Both);
+    let _ = events.send(ServerEvent::Disconnected(id));
+}
+
+fn serve_websocket(
+    id: u64,
+    mut socket: WebSocket<TcpStream>,
+    peer: SocketAddr,
+    events: Sender<ServerEvent>,
+) {
+    // Reads give up quickly so messages for the client go out between them
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(WEBSOCKET_POLL))
+        .is_err()
+    {
+        return;
+    }
+    let (sender, outgoing) = crossbeam_channel::unbounded();
+    if events
+        .send(ServerEvent::Connected(id, peer, sender))
+        .is_err()
+    {
+        return;
+    }
+    let _ = exchange_frames(id, &mut socket, &outgoing, &events);
+    let _ = socket.get_ref().shutdown(Shutdown::Both);
+    let _ = events.send(ServerEvent::Disconnected(id));
+}
+
+/// Pass messages both ways over a WebSocket until either side hangs up
+fn exchange_frames(
+    id: u64,
+    socket: &mut WebSocket<TcpStream>,
+    outgoing: &Receiver<Message>,
+    events: &Sender<ServerEvent>,
+) -> Option<()> {
+    let mut last_read = Instant::now();
+    let mut last_write = Instant::now();
+    loop {
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => {
+                    socket.write_message(Frame::Text(message.encode())).ok()?;
+                    last_write = Instant::now();
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return socket.close(None).ok(),
+            }
+        }
+        // Browsers answer WebSocket pings by themselves
+        if last_write.elapsed() >= HEARTBEAT_INTERVAL {
+            socket.write_message(Frame::Ping(Vec::new())).ok()?;
+            last_write = Instant::now();
+        }
+
+        match socket.read_message() {
+            Ok(Frame::Text(text)) => {
+                last_read = Instant::now();
+                if !forward(id, &text, events) {
+                    return None;
+                }
+            }
+            Ok(Frame::Close(_)) => return None,
+            Ok(_) => last_read = Instant::now(),
+            Err(tungstenite::Error::Io(error))
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if last_read.elapsed() >= PEER_TIMEOUT {
+                    return None;
+                }
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Hand what a client sent to the hub, or why it couldn't be read. Returns
+/// false once the hub is gone.
+fn forward(id: u64, text: &str, events: &Sender<ServerEvent>) -> bool {
+    let event = match read(text) {
+        Ok(Some(message)) => ServerEvent::Message(id, message),
+        Ok(None) => return true,
+        Err(reason) => ServerEvent::Malformed(id, reason),
+    };
+    events.send(event).is_ok()
+}
+
+/// Read a message from a client. Pings and messages of a type this server
+/// doesn't know are left out, anything else that can't be read is an error.
+fn read(text: &str) -> Result<Option<Message>, String> {
+    let error = match Message::decode(text) {
+        Ok(Message::Ping) => return Ok(None),
+        Ok(message) => return Ok(Some(message)),
+        Err(error) => error,
+    };
+    // Only a newer type is reported as an unknown variant naming the type
+    let value: Value =
+        serde_json::from_str(text).map_err(|error| format!("Malformed message: {}", error))?;
+    match value.get("type").and_then(Value::as_str) {
+        Some(name)
+            if error
+                .to_string()
+                .starts_with(&format!("unknown variant `{}`", name)) =>
+        {
+            Ok(None)
+        }
+        _ => Err(format!("Malformed message: {}", error)),
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::TimeControl,
+    game::{GameOutcome, GameResult, Termination},
+    network::ClockState,
+    pieces::*,
+};
+
+/// How long an unfinished game everyone has left waits for its players to come back
+pub const ABANDONED_GAME_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+/// One side of a game on the server, kept for the player while they're away
+pub struct Seat {
+    // Proves a reconnecting player is the one who sat here
+    pub token: u64,
+    // The connection playing from this seat, if it's connected
+    pub client: Option<u64>,
+}
+
+/// A game the server referees: the moves are checked against the rules and
+/// the clocks run here, so neither player has the last word
+pub struct ServerGame {
+    pub time_control: Option<TimeControl>,
+    pub white: Seat,
+    pub black: Seat,
+    pub spectators: Vec<u64>,
+    // Every move so far in UCI notation
+    pub moves: Vec<String>,
+    pub draw_offer: Option<PieceColor>,
+    pub outcome: Option<GameOutcome>,
+    // The seat of a created game nobody has taken yet
+    pub open_seat: Option<PieceColor>,
+    position: Position,
+    repetitions: HashMap<String, u32>,
+    clock: ClockState,
+    // When the player to move started thinking
+    turn_started: Instant,
+    // When the last player or spectator left
+    deserted_since: Option<Instant>,
+}
+
+impl ServerGame {
+    pub fn new(time_control: Option<TimeControl>, white: Seat, black: Seat) -> Self {
+        let position = Position::default();
+        let mut repetitions = HashMap::new();
+        repetitions.insert(position.key(), 1);
+        let base = time_control.map_or(Duration::from_secs(0), |time_control| time_control.base);
+        Self {
+            time_control,
+            white,
+            black,
+            spectators: Vec::new(),
+            moves: Vec::new(),
+            draw_offer: None,
+            outcome: None,
+            open_seat: None,
+            position,
+            repetitions,
+            clock: ClockState {
+                white: base,
+                black: base,
+            },
+            turn_started: Instant::now(),
+            deserted_since: None,
+        }
+    }
+
+    pub fn seat(&self, color: PieceColor) -> &Seat {
+        match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        }
+    }
+
+    pub fn seat_mut(&mut self, color: PieceColor) -> &mut Seat {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+
+    /// The color played from a connection, if it's seated here
+    pub fn color_of(&self, client: u64) -> Option<PieceColor> {
+        [PieceColor::White, PieceColor::Black]
+            .iter()
+            .copied()
+            .find(|color| self.seat(*color).client == Some(client))
+    }
+
+    pub fn turn(&self) -> PieceColor {
+        self.position.turn
+    }
+
+    pub fn fen(&self) -> String {
+        self.position.to_fen()
+    }
+
+    /// Everyone following the game: both players and the spectators
+    pub fn audience(&self) -> Vec<u64> {
+        let players = self.white.client.iter().chain(self.black.client.iter());
+        players.chain(self.spectators.iter()).copied().collect()
+    }
+
+    /// Whether anyone is still connected to the game
+    pub fn is_deserted(&self) -> bool {
+        self.audience().is_empty()
+    }
+
+    /// Whether the game can be forgotten: finished games and games nobody
+    /// joined go once nobody is following them, the others once everyone
+    /// has been gone for `ABANDONED_GAME_EXPIRY`
+    pub fn has_expired(&mut self, now: Instant) -> bool {
+        if !self.is_deserted() {
+            self.deserted_since = None;
+            return false;
+        }
+        if self.outcome.is_some() || self.open_seat.is_some() {
+            return true;
+        }
+        let since = *self.deserted_since.get_or_insert(now);
+        now.saturating_duration_since(since) >= ABANDONED_GAME_EXPIRY
+    }
+
+    /// Time left on both clocks, counting down for the player to move once
+    /// the first move is in
+    pub fn clock(&self, now: Instant) -> ClockState {
+        let mut clock = self.clock;
+        if self.time_control.is_some() && !self.moves.is_empty() && self.outcome.is_none() {
+            let thinking = now.saturating_duration_since(self.turn_started);
+            match self.turn() {
+                PieceColor::White => clock.white = clock.white.saturating_sub(thinking),
+                PieceColor::Black => clock.black = clock.black.saturating_sub(thinking),
+            }
+        }
+        clock
+    }
+
+    fn remaining(&self, color: PieceColor, now: Instant) -> Duration {
+        let clock = self.clock(now);
+        match color {
+            PieceColor::White => clock.white,
+            PieceColor::Black => clock.black,
+        }
+    }
+
+    /// End the game if the player to move has run out of time
+    pub fn check_flag(&mut self, now: Instant) -> Option<GameOutcome> {
+        if self.time_control.is_none() || self.moves.is_empty() || self.outcome.is_some() {
+            return None;
+        }
+        let turn = self.turn();
+        if self.remaining(turn, now) > Duration::from_secs(0) {
+            return None;
+        }
+        self.end(GameOutcome {
+            result: GameResult::win_for(turn.opponent()),
+            termination: Termination::Timeout,
+        })
+    }
+
+    /// Play a move given in UCI notation for `color`, returning the time left
+    /// on their clock after the increment
+    pub fn play(
+        &mut self,
+        color: PieceColor,
+        text: &str,
+        now: Instant,
+    ) -> Result<Duration, String> {
+        if self.outcome.is_some() {
+            return Err("The game is over".to_string());
+        }
+        if self.open_seat.is_some() {
+            return Err("Nobody has joined the game yet".to_string());
+        }
+        if color != self.turn() {
+            return Err(format!("It's {}'s turn", self.turn().name()));
+        }
+        let chess_move = parse_move(text, color, &self.position.pieces, &self.position.state)
+            .map_err(|error| format!("Illegal move {}: {}", text, error))?;
+
+        let mut remaining = self.remaining(color, now);
+        if let Some(time_control) = self.time_control {
+            remaining += time_control.increment;
+        }
+        match color {
+            PieceColor::White => self.clock.white = remaining,
+            PieceColor::Black => self.clock.black = remaining,
+        }
+        self.turn_started = now;
+
+        self.moves.push(uci(&chess_move));
+        self.position.play(&chess_move);
+        *self.repetitions.entry(self.position.key()).or_insert(0) += 1;
+        // An offer lapses once the other player moves instead of answering
+        if self.draw_offer.is_some() && self.draw_offer != Some(color) {
+            self.draw_offer = None;
+        }
+        Ok(remaining)
+    }
+
+    /// End the game if the rules say it's over after the last move
+    pub fn check_rules(&mut self) -> Option<GameOutcome> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let repetitions = self
+            .repetitions
+            .get(&self.position.key())
+            .copied()
+            .unwrap_or(0);
+        let outcome = GameOutcome::by_rules(&self.position, repetitions)?;
+        self.end(outcome)
+    }
+
+    /// Stop the clocks and record the result
+    pub fn end(&mut self, outcome: GameOutcome) -> Option<GameOutcome> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        self.clock = self.clock(Instant::now());
+        self.draw_offer = None;
+        self.outcome = Some(outcome);
+        Some(outcome)
+    }
+}
@@ -0,0 +1,545 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    hash::{BuildHasher, Hasher},
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+
+use super::{accept, Seat, ServerEvent, ServerGame};
+use crate::{
+    clock::TimeControl,
+    game::{GameOutcome, GameResult, Termination},
+    network::{Message, PROTOCOL_VERSION},
+    pieces::PieceColor,
+};
+
+/// How often the clocks are checked for a fallen flag
+const FLAG_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A game server running on its own threads, which stops when dropped
+pub struct Server {
+    events: Sender<ServerEvent>,
+    stopped: Arc<AtomicBool>,
+    hub: Option<JoinHandle<()>>,
+    pub local_address: SocketAddr,
+}
+
+impl Server {
+    /// Listen for games on all interfaces
+    pub fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let local_address = listener.local_addr()?;
+
+        let (events, event_receiver) = crossbeam_channel::unbounded();
+        let stopped = Arc::new(AtomicBool::new(false));
+        {
+            let events = events.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || accept(listener, events, stopped));
+        }
+        let hub = thread::spawn(move || Hub::default().run(event_receiver));
+
+        Ok(Self {
+            events,
+            stopped,
+            hub: Some(hub),
+            local_address,
+        })
+    }
+
+    /// Serve until the process is stopped
+    pub fn wait(mut self) {
+        if let Some(hub) = self.hub.take() {
+            let _ = hub.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        let _ = self.events.send(ServerEvent::Stop);
+    }
+}
+
+struct Client {
+    address: SocketAddr,
+    sender: Sender<Message>,
+    // Set once the client has said hello in the right version
+    greeted: bool,
+}
+
+/// A player waiting for an opponent
+struct Seek {
+    client: u64,
+    time_control: Option<TimeControl>,
+}
+
+/// Owns everything the server knows, with the connection threads reporting to it
+#[derive(Default)]
+struct Hub {
+    clients: HashMap<u64, Client>,
+    seeks: Vec<Seek>,
+    games: BTreeMap<u64, ServerGame>,
+    next_game: u64,
+}
+
+impl Hub {
+    fn run(mut self, events: Receiver<ServerEvent>) {
+        loop {
+            match events.recv_timeout(FLAG_INTERVAL) {
+                Ok(ServerEvent::Connected(id, address, sender)) => {
+                    println!("{} connected", address);
+                    self.clients.insert(
+                        id,
+                        Client {
+                            address,
+                            sender,
+                            greeted: false,
+                        },
+                    );
+                }
+                Ok(ServerEvent::Message(id, message)) => self.receive(id, message),
+                Ok(ServerEvent::Malformed(id, reason)) => self.send(id, Message::Error { reason }),
+                Ok(ServerEvent::Disconnected(id)) => self.disconnect(id),
+                Ok(ServerEvent::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+            self.check_flags();
+            self.expire_games(Instant::now());
+        }
+    }
+
+    fn send(&self, client: u64, message: Message) {
+        if let Some(client) = self.clients.get(&client) {
+            let _ = client.sender.send(message);
+        }
+    }
+
+    /// Turn a client away, hanging up once the reason is sent
+    fn reject(&mut self, id: u64, reason: String) {
+        self.send(id, Message::Reject { reason });
+        self.disconnect(id);
+    }
+
+    /// Forget a client, keeping their seat for when they come back
+    fn disconnect(&mut self, id: u64) {
+        let client = match self.clients.remove(&id) {
+            Some(client) => client,
+            None => return,
+        };
+        println!("{} disconnected", client.address);
+
+        self.seeks.retain(|seek| seek.client != id);
+        for game in self.games.values_mut() {
+            game.spectators.retain(|spectator| *spectator != id);
+            if let Some(color) = game.color_of(id) {
+                game.seat_mut(color).client = None;
+            }
+        }
+        self.expire_games(Instant::now());
+    }
+
+    /// Forget the games nobody is coming back for
+    fn expire_games(&mut self, now: Instant) {
+        self.games.retain(|_, game| !game.has_expired(now));
+    }
+
+    /// The game a client plays or watches
+    fn game_of(&self, client: u64) -> Option<u64> {
+        self.games
+            .iter()
+            .find(|(_, game)| game.audience().contains(&client))
+            .map(|(id, _)| *id)
+    }
+
+    /// The game and color a client plays, if they're seated
+    fn seat_of(&self, client: u64) -> Option<(u64, PieceColor)> {
+        self.games
+            .iter()
+            .find_map(|(id, game)| game.color_of(client).map(|color| (*id, color)))
+    }
+
+    fn receive(&mut self, id: u64, message: Message) {
+        match message {
+            Message::Hello { .. } => self.hello(id, message),
+            // Nothing counts before the client has said hello
+            _ if !self.clients.get(&id).is_some_and(|client| client.greeted) => (),
+            Message::Seek { time_control } => self.seek(id, time_control),
+            Message::Watch { game } => self.watch(id, game),
+            Message::Create {
+                time_control,
+                color,
+            } => self.create(id, time_control, color),
+            Message::Join { game } => self.join(id, game),
+            Message::GetState => {
+                if let Some(game) = self.game_of(id) {
+                    self.welcome(id, game);
+                }
+            }
+            Message::Reject { .. } => self.disconnect(id),
+            message => {
+                if let Some((game, color)) = self.seat_of(id) {
+                    self.play(game, color, message);
+                }
+            }
+        }
+    }
+
+    fn hello(&mut self, id: u64, hello: Message) {
+        let (version, game, moves, seat) = match hello {
+            Message::Hello {
+                version,
+                game,
+                moves,
+                seat,
+                ..
+            } => (version, game, moves, seat),
+            _ => return,
+        };
+        if version != PROTOCOL_VERSION {
+            let reason = format!(
+                "The server plays version {} of the game, not {}",
+                PROTOCOL_VERSION, version
+            );
+            return self.reject(id, reason);
+        }
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.greeted = true;
+        }
+
+        // A new client asks for a seek or a game to watch next
+        let game_id = match game {
+            Some(game_id) => game_id,
+            None => return,
+        };
+        let game = match self.games.get_mut(&game_id) {
+            Some(game) => game,
+            None => return self.reject(id, format!("There's no game {} here", game_id)),
+        };
+        let color = match seat {
+            None => {
+                game.spectators.push(id);
+                return self.welcome(id, game_id);
+            }
+            Some(token) => [PieceColor::White, PieceColor::Black]
+                .iter()
+                .copied()
+                .find(|color| game.seat(*color).token == token),
+        };
+        let color = match color {
+            Some(color) => color,
+            None => return self.reject(id, "That seat belongs to someone else".to_string()),
+        };
+
+        // The player is back, perhaps before their old connection timed out
+        let old = game.seat_mut(color).client.replace(id);
+        if let Some(old) = old.filter(|old| *old != id) {
+            self.disconnect(old);
+        }
+        self.welcome(id, game_id);
+
+        // Moves made while away follow on from what the server has
+        let known = self.games[&game_id].moves.len();
+        if moves.len() > known && moves[..known] == self.games[&game_id].moves[..] {
+            for (ply, uci) in moves.into_iter().enumerate().skip(known) {
+                self.play(
+                    game_id,
+                    color,
+                    Message::Move {
+                        ply,
+                        uci,
+                        clock: None,
+                        fen: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Tell a client everything about the game they're now part of
+    fn welcome(&self, id: u64, game_id: u64) {
+        let game = &self.games[&game_id];
+        let color = game.color_of(id);
+        self.send(
+            id,
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                game: Some(game_id),
+                your_color: color,
+                time_control: game.time_control,
+                moves: game.moves.clone(),
+                clock: Some(game.clock(Instant::now())),
+                draw_offer: game.draw_offer,
+                result: game
+                    .outcome
+                    .map(|outcome| (outcome.result, outcome.termination)),
+                seat: color.map(|color| game.seat(color).token),
+                fen: Some(game.fen()),
+            },
+        );
+    }
+
+    /// Pair the client with someone wanting the same time control, or wait
+    fn seek(&mut self, id: u64, time_control: Option<TimeControl>) {
+        if self.is_busy(id) {
+            return;
+        }
+        let opponent = self
+            .seeks
+            .iter()
+            .position(|seek| seek.time_control == time_control);
+        let opponent = match opponent {
+            Some(index) => self.seeks.remove(index).client,
+            None => {
+                self.seeks.push(Seek {
+                    client: id,
+                    time_control,
+                });
+                return;
+            }
+        };
+
+        // Whoever waited takes the color a coin toss gives them
+        let (white, black) = match coin_toss() {
+            PieceColor::White => (opponent, id),
+            PieceColor::Black => (id, opponent),
+        };
+        let game_id = self.new_game(time_control, Some(white), Some(black));
+        println!(
+            "Game {} started: {} vs {}",
+            game_id, self.clients[&white].address, self.clients[&black].address
+        );
+        self.welcome(white, game_id);
+        self.welcome(black, game_id);
+    }
+
+    /// Open a game for someone to join by its id
+    fn create(&mut self, id: u64, time_control: Option<TimeControl>, color: Option<PieceColor>) {
+        if self.is_busy(id) {
+            return;
+        }
+        let color = color.unwrap_or_else(coin_toss);
+        let (white, black) = match color {
+            PieceColor::White => (Some(id), None),
+            PieceColor::Black => (None, Some(id)),
+        };
+        let game_id = self.new_game(time_control, white, black);
+        self.games.get_mut(&game_id).unwrap().open_seat = Some(color.opponent());
+        println!("Game {} opened by {}", game_id, self.clients[&id].address);
+        self.welcome(id, game_id);
+    }
+
+    fn join(&mut self, id: u64, game_id: u64) {
+        if self.is_busy(id) {
+            return;
+        }
+        let game = match self.games.get_mut(&game_id) {
+            Some(game) => game,
+            None => return self.reject(id, format!("There's no game {} here", game_id)),
+        };
+        let color = match game.open_seat.take() {
+            Some(color) => color,
+            None => return self.reject(id, format!("Game {} has no open seat", game_id)),
+        };
+        game.seat_mut(color).client = Some(id);
+        let creator = game.seat(color.opponent()).client;
+        println!(
+            "Game {} started: {} joined",
+            game_id, self.clients[&id].address
+        );
+        self.welcome(id, game_id);
+        // A fresh hello tells the creator their opponent is here
+        if let Some(creator) = creator {
+            self.welcome(creator, game_id);
+        }
+    }
+
+    /// Whether a client already plays or waits for a game
+    fn is_busy(&self, id: u64) -> bool {
+        self.seat_of(id).is_some() || self.seeks.iter().any(|seek| seek.client == id)
+    }
+
+    fn new_game(
+        &mut self,
+        time_control: Option<TimeControl>,
+        white: Option<u64>,
+        black: Option<u64>,
+    ) -> u64 {
+        let seat = |client| Seat {
+            token: random(),
+            client,
+        };
+        self.next_game += 1;
+        self.games.insert(
+            self.next_game,
+            ServerGame::new(time_control, seat(white), seat(black)),
+        );
+        self.next_game
+    }
+
+    /// Send the client the moves of a game as they're played, the latest
+    /// game still going if none is given
+    fn watch(&mut self, id: u64, game: Option<u64>) {
+        let game_id = match game {
+            Some(game_id) => Some(game_id).filter(|game_id| self.games.contains_key(game_id)),
+            None => self
+                .games
+                .iter()
+                .rev()
+                .find(|(_, game)| game.outcome.is_none())
+                .map(|(game_id, _)| *game_id),
+        };
+        match game_id {
+            Some(game_id) => {
+                self.games.get_mut(&game_id).unwrap().spectators.push(id);
+                self.welcome(id, game_id);
+            }
+            None => self.reject(id, "There's no game to watch".to_string()),
+        }
+    }
+
+    /// Act on something a seated player did in their game
+    fn play(&mut self, game_id: u64, color: PieceColor, message: Message) {
+        let now = Instant::now();
+        let game = self.games.get_mut(&game_id).unwrap();
+        let opponent = game.seat(color.opponent()).client;
+
+        match message {
+            Message::Move { ply, uci, .. } => {
+                // Already played, e.g. sent again after reconnecting
+                if ply < game.moves.len() {
+                    return;
+                }
+                if let Some(outcome) = game.check_flag(now) {
+                    return self.finish(game_id, outcome);
+                }
+                if ply > game.moves.len() {
+                    let reason = format!(
+                        "Move {} is for ply {}, the game is at ply {}",
+                        uci,
+                        ply,
+                        game.moves.len()
+                    );
+                    if let Some(player) = game.seat(color).client {
+                        self.send(player, Message::Error { reason });
+                    }
+                    return;
+                }
+                let remaining = match game.play(color, &uci, now) {
+                    Ok(remaining) => remaining,
+                    Err(reason) => {
+                        let player = game.seat(color).client;
+                        if let Some(player) = player {
+                            self.send(player, Message::Error { reason });
+                        }
+                        return;
+                    }
+                };
+                let relayed = Message::Move {
+                    ply,
+                    uci: game.moves[ply].clone(),
+                    clock: game.time_control.map(|_| remaining),
+                    fen: Some(game.fen()),
+                };
+                let outcome = game.check_rules();
+                let mover = game.seat(color).client;
+                let audience = game.audience();
+                for client in audience.into_iter().filter(|client| Some(*client) != mover) {
+                    self.send(client, relayed.clone());
+                }
+                if let Some(outcome) = outcome {
+                    self.finish(game_id, outcome);
+                }
+            }
+            _ if game.outcome.is_some() => (),
+            Message::Resign => {
+                if let Some(outcome) = game.end(GameOutcome {
+                    result: GameResult::win_for(color.opponent()),
+                    termination: Termination::Resignation,
+                }) {
+                    self.finish(game_id, outcome);
+                }
+            }
+            Message::OfferDraw if game.draw_offer.is_none() => {
+                game.draw_offer = Some(color);
+                if let Some(opponent) = opponent {
+                    self.send(opponent, Message::OfferDraw);
+                }
+            }
+            Message::AcceptDraw if game.draw_offer == Some(color.opponent()) => {
+                if let Some(outcome) = game.end(GameOutcome {
+                    result: GameResult::Draw,
+                    termination: Termination::DrawAgreement,
+                }) {
+                    if let Some(opponent) = opponent {
+                        self.send(opponent, Message::AcceptDraw);
+                    }
+                    self.finish(game_id, outcome);
+                }
+            }
+            Message::DeclineDraw if game.draw_offer == Some(color.opponent()) => {
+                game.draw_offer = None;
+                if let Some(opponent) = opponent {
+                    self.send(opponent, Message::DeclineDraw);
+                }
+            }
+            Message::Chat { text } => {
+                if let Some(opponent) = opponent {
+                    self.send(opponent, Message::Chat { text });
+                }
+            }
+            // The server calls the result, whatever the players' games think
+            _ => (),
+        }
+    }
+
+    /// Tell everyone in a game how it ended
+    fn finish(&mut self, game_id: u64, outcome: GameOutcome) {
+        let game = &self.games[&game_id];
+        println!("Game {}: {}", game_id, outcome.description());
+        for client in game.audience() {
+            self.send(
+                client,
+                Message::GameOver {
+                    result: outcome.result,
+                    termination: outcome.termination,
+                },
+            );
+        }
+    }
+
+    fn check_flags(&mut self) {
+        let now = Instant::now();
+        let flagged: Vec<(u64, GameOutcome)> = self
+            .games
+            .iter_mut()
+            .filter_map(|(game_id, game)| game.check_flag(now).map(|outcome| (*game_id, outcome)))
+            .collect();
+        for (game_id, outcome) in flagged {
+            self.finish(game_id, outcome);
+        }
+    }
+}
+
+/// A number nobody can guess, for seat tokens
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn coin_toss() -> PieceColor {
+    if random() % 2 == 0 {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    }
+}
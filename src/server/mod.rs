@@ -0,0 +1,8 @@
+mod game;
+pub use game::*;
+
+mod connection;
+pub use connection::*;
+
+mod hub;
+pub use hub::*;
@@ -0,0 +1,207 @@
+use std::io::Cursor;
+
+use bevy::{
+    audio::{play_queued_audio_system, AudioOutput, Decodable},
+    ecs::system::IntoExclusiveSystem,
+    prelude::*,
+    reflect::TypeUuid,
+};
+use rodio::{source::Amplify, Source};
+
+use crate::{
+    board::MoveEvent, check::Check, clock::LowTime, game::GameOver, move_input::MoveInput,
+};
+
+/// Volume change per press of [ or ]
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Castle,
+    Promotion,
+    Check,
+    // For a clock running low, sent by whatever keeps time
+    LowTime,
+    GameOver,
+}
+
+impl SoundEffect {
+    fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Move => "sounds/move.mp3",
+            SoundEffect::Capture => "sounds/capture.mp3",
+            SoundEffect::Castle => "sounds/castle.mp3",
+            SoundEffect::Promotion => "sounds/promotion.mp3",
+            SoundEffect::Check => "sounds/check.mp3",
+            SoundEffect::LowTime => "sounds/low_time.mp3",
+            SoundEffect::GameOver => "sounds/game_over.mp3",
+        }
+    }
+}
+
+pub struct SoundSettings {
+    // From 0 for silence to 1 for the volume of the files
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.7,
+            muted: false,
+        }
+    }
+}
+
+/// A loaded sound together with the volume to play it at
+#[derive(TypeUuid)]
+#[uuid = "2bbb8efe-4d27-4052-9536-330e4751899e"]
+pub struct ScaledAudio {
+    source: AudioSource,
+    volume: f32,
+}
+
+impl Decodable for ScaledAudio {
+    type Decoder = Amplify<rodio::Decoder<Cursor<AudioSource>>>;
+
+    fn decoder(&self) -> Self::Decoder {
+        self.source.decoder().amplify(self.volume)
+    }
+}
+
+/// The files of every sound effect, loaded at startup
+struct SoundHandles(Vec<(SoundEffect, Handle<AudioSource>)>);
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SoundSettings>()
+            .add_event::<SoundEffect>()
+            // Plays the sounds like bevy's AudioPlugin, with the volume applied
+            .init_non_send_resource::<AudioOutput<ScaledAudio>>()
+            .add_asset::<ScaledAudio>()
+            .init_resource::<Audio<ScaledAudio>>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                play_queued_audio_system::<ScaledAudio>.exclusive_system(),
+            )
+            .add_startup_system(load_sounds.system())
+            .add_system(sound_hotkeys.system())
+            .add_system(move_sounds.system().label("move_sounds"))
+            .add_system(check_sounds.system().label("check_sounds"))
+            .add_system(low_time_sounds.system().label("low_time_sounds"))
+            .add_system(game_over_sounds.system().label("game_over_sounds"))
+            .add_system(
+                play_sounds
+                    .system()
+                    .after("move_sounds")
+                    .after("check_sounds")
+                    .after("low_time_sounds")
+                    .after("game_over_sounds"),
+            );
+    }
+}
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let effects = [
+        SoundEffect::Move,
+        SoundEffect::Capture,
+        SoundEffect::Castle,
+        SoundEffect::Promotion,
+        SoundEffect::Check,
+        SoundEffect::LowTime,
+        SoundEffect::GameOver,
+    ];
+    commands.insert_resource(SoundHandles(
+        effects
+            .iter()
+            .map(|effect| (*effect, asset_server.load(effect.path())))
+            .collect(),
+    ));
+}
+
+/// M mutes and unmutes, [ and ] change the volume
+fn sound_hotkeys(
+    keyboard_input: Res<Input<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut settings: ResMut<SoundSettings>,
+) {
+    // The keys are part of the move being typed
+    if move_input.active {
+        return;
+    }
+
+    for key in keyboard_input.get_just_pressed() {
+        match key {
+            KeyCode::M => settings.muted = !settings.muted,
+            KeyCode::LBracket => settings.volume = (settings.volume - VOLUME_STEP).max(0.),
+            KeyCode::RBracket => settings.volume = (settings.volume + VOLUME_STEP).min(1.),
+            _ => (),
+        }
+    }
+}
+
+/// Each move makes one sound, the most special kind it is
+fn move_sounds(mut events: EventReader<MoveEvent>, mut sounds: EventWriter<SoundEffect>) {
+    for event in events.iter() {
+        let chess_move = &event.chess_move;
+        sounds.send(if chess_move.promotion.is_some() {
+            SoundEffect::Promotion
+        } else if chess_move.castle.is_some() {
+            SoundEffect::Castle
+        } else if chess_move.capture.is_some() {
+            SoundEffect::Capture
+        } else {
+            SoundEffect::Move
+        });
+    }
+}
+
+fn check_sounds(check: Res<Check>, mut sounds: EventWriter<SoundEffect>) {
+    if check.is_changed() && check.is_check && !check.is_checkmate {
+        sounds.send(SoundEffect::Check);
+    }
+}
+
+fn low_time_sounds(mut events: EventReader<LowTime>, mut sounds: EventWriter<SoundEffect>) {
+    for _event in events.iter() {
+        sounds.send(SoundEffect::LowTime);
+    }
+}
+
+fn game_over_sounds(game_over: Res<GameOver>, mut sounds: EventWriter<SoundEffect>) {
+    if game_over.is_changed() && game_over.0.is_some() {
+        sounds.send(SoundEffect::GameOver);
+    }
+}
+
+fn play_sounds(
+    mut events: EventReader<SoundEffect>,
+    settings: Res<SoundSettings>,
+    handles: Res<SoundHandles>,
+    sources: Res<Assets<AudioSource>>,
+    mut scaled_sources: ResMut<Assets<ScaledAudio>>,
+    audio: Res<Audio<ScaledAudio>>,
+) {
+    for effect in events.iter() {
+        if settings.muted || settings.volume <= 0. {
+            continue;
+        }
+
+        // Sounds that failed or haven't finished loading are skipped
+        let source = handles
+            .0
+            .iter()
+            .find(|(handle_effect, _)| handle_effect == effect)
+            .and_then(|(_, handle)| sources.get(handle));
+        if let Some(source) = source {
+            audio.play(scaled_sources.add(ScaledAudio {
+                source: source.clone(),
+                volume: settings.volume,
+            }));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::pieces::{PieceColor, PieceMaterials};
+
+/// Folder with extra themes in RON or TOML, a file replaces the built-in theme
+/// of the same name
+const THEMES_FOLDER: &str = "assets/themes";
+
+const BUILT_IN_THEMES: [&str; 4] = [
+    include_str!("../assets/themes/classic.ron"),
+    include_str!("../assets/themes/walnut.ron"),
+    include_str!("../assets/themes/tournament.ron"),
+    include_str!("../assets/themes/metal.ron"),
+];
+
+/// A color as red, green and blue between 0 and 1
+pub type Rgb = (f32, f32, f32);
+
+pub fn color(rgb: Rgb) -> Color {
+    Color::rgb(rgb.0, rgb.1, rgb.2)
+}
+
+#[derive(Clone, Deserialize)]
+pub struct PieceStyle {
+    pub color: Rgb,
+    pub metallic: f32,
+    pub roughness: f32,
+    // Image in the assets folder laid over the color
+    #[serde(default)]
+    pub texture: Option<String>,
+}
+
+/// Colors of the board and its highlights and the look of the pieces
+#[derive(Clone, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub light_square: Rgb,
+    pub dark_square: Rgb,
+    pub last_move_light: Rgb,
+    pub last_move_dark: Rgb,
+    pub hover: Rgb,
+    pub selected: Rgb,
+    pub destination: Rgb,
+    pub capture: Rgb,
+    pub castle: Rgb,
+    pub promotion: Rgb,
+    pub check: Rgb,
+    pub white_pieces: PieceStyle,
+    pub black_pieces: PieceStyle,
+}
+
+impl Theme {
+    pub fn pieces(&self, piece_color: PieceColor) -> &PieceStyle {
+        match piece_color {
+            PieceColor::White => &self.white_pieces,
+            PieceColor::Black => &self.black_pieces,
+        }
+    }
+
+    /// Material for the pieces of one color
+    pub fn piece_material(
+        &self,
+        piece_color: PieceColor,
+        asset_server: &AssetServer,
+    ) -> StandardMaterial {
+        let style = self.pieces(piece_color);
+        StandardMaterial {
+            base_color: color(style.color),
+            base_color_texture: style
+                .texture
+                .as_ref()
+                .map(|texture| asset_server.load(texture.as_str())),
+            metallic: style.metallic,
+            roughness: style.roughness,
+            ..Default::default()
+        }
+    }
+}
+
+/// All available themes and the one in use
+pub struct Themes {
+    pub themes: Vec<Theme>,
+    pub current: usize,
+}
+
+impl Themes {
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    fn add(&mut self, theme: Theme) {
+        match self
+            .themes
+            .iter_mut()
+            .find(|other| other.name == theme.name)
+        {
+            Some(other) => *other = theme,
+            None => self.themes.push(theme),
+        }
+    }
+}
+
+impl Default for Themes {
+    /// The built-in themes followed by those found in the themes folder
+    fn default() -> Self {
+        let mut themes = Themes {
+            themes: BUILT_IN_THEMES
+                .iter()
+                .map(|source| ron::de::from_str(source).expect("Invalid built-in theme"))
+                .collect(),
+            current: 0,
+        };
+
+        let mut paths: Vec<_> = fs::read_dir(THEMES_FOLDER)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        for path in paths {
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(_) => continue,
+            };
+            let theme = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("ron") => ron::de::from_str(&source).map_err(|error| error.to_string()),
+                Some("toml") => toml::from_str(&source).map_err(|error| error.to_string()),
+                _ => continue,
+            };
+            match theme {
+                Ok(theme) => themes.add(theme),
+                Err(error) => println!("Skipping theme {}: {}", path.display(), error),
+            }
+        }
+
+        themes
+    }
+}
+
+pub struct ThemePlugin;
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Themes>()
+            .add_system(apply_piece_theme.system());
+    }
+}
+
+/// Restyle the piece materials when switching themes
+fn apply_piece_theme(
+    themes: Res<Themes>,
+    asset_server: Res<AssetServer>,
+    piece_materials: Option<Res<PieceMaterials>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let piece_materials = match piece_materials {
+        Some(piece_materials) if themes.is_changed() => piece_materials,
+        _ => return,
+    };
+
+    for piece_color in [PieceColor::White, PieceColor::Black].iter() {
+        if let Some(material) = materials.get_mut(piece_materials.get(*piece_color)) {
+            *material = themes.current().piece_material(*piece_color, &asset_server);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::HashMap, str::FromStr, time::Duration, time::Instant};
+
+pub use crate::clock::TimeControl;
+use crate::{game::GameResult, openings::Openings, pieces::*};
+
+use super::{key_values, Player};
+
+/// Time left on both clocks
+#[derive(Clone, Copy)]
+pub struct Clocks {
+    pub white: Duration,
+    pub black: Duration,
+    pub increment: Duration,
+}
+
+impl Clocks {
+    pub fn new(time_control: &TimeControl) -> Self {
+        Self {
+            white: time_control.base,
+            black: time_control.base,
+            increment: time_control.increment,
+        }
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    /// Time to spend on a move, enough to leave some for the rest of the game
+    pub fn budget(&self, color: PieceColor) -> Duration {
+        let remaining = self.remaining(color);
+        (remaining / 30 + self.increment * 3 / 4).min(remaining / 2)
+    }
+
+    /// Take the time spent off the clock and add the increment, or return
+    /// false when the flag fell
+    fn spend(&mut self, color: PieceColor, spent: Duration) -> bool {
+        let clock = match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        };
+        match clock.checked_sub(spent) {
+            Some(left) => {
+                *clock = left + self.increment;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Ending games early on the engines' scores
+#[derive(Clone, Copy)]
+pub struct ScoreRule {
+    // Centipawns
+    pub score: i32,
+    // Consecutive moves the score has to hold for
+    pub count: u32,
+    // Full move the rule starts applying from
+    pub from_move: u32,
+}
+
+impl FromStr for ScoreRule {
+    type Err = String;
+
+    /// Reads "score=10,count=8,movenumber=40", leaving out the move number starts right away
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut rule = ScoreRule {
+            score: 0,
+            count: 1,
+            from_move: 0,
+        };
+        for (key, value) in key_values(text)? {
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("{} must be a number", key))
+            };
+            match key {
+                "score" => rule.score = number()? as i32,
+                "count" => rule.count = number()?.max(1),
+                "movenumber" => rule.from_move = number()?,
+                _ => return Err(format!("Unknown adjudication setting {}", key)),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Adjudication {
+    // Full moves after which the game is a draw
+    pub max_moves: Option<u32>,
+    // Both engines see a score this close to even
+    pub draw: Option<ScoreRule>,
+    // An engine sees itself this far behind
+    pub resign: Option<ScoreRule>,
+}
+
+/// A finished game, ready to be written as PGN
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub start: Position,
+    pub moves: Vec<String>,
+    // Zobrist hash of the start position and of the position after each move
+    pub hashes: Vec<u64>,
+    pub result: GameResult,
+    pub reason: String,
+}
+
+impl GameRecord {
+    pub fn to_pgn(&self, event: &str, round: usize, openings: &Openings) -> String {
+        let mut pgn = String::new();
+        pgn.push_str(&format!("[Event \"{}\"]\n", event));
+        pgn.push_str("[Site \"rust_chess\"]\n");
+        pgn.push_str(&format!("[Round \"{}\"]\n", round));
+        pgn.push_str(&format!("[White \"{}\"]\n", self.white));
+        pgn.push_str(&format!("[Black \"{}\"]\n", self.black));
+        pgn.push_str(&format!("[Result \"{}\"]\n", self.result.pgn()));
+        let fen = self.start.to_fen();
+        if fen != STARTING_FEN {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+        }
+        if let Some(opening) = openings.classify(&self.hashes) {
+            pgn.push_str(&format!("[ECO \"{}\"]\n", opening.eco));
+            pgn.push_str(&format!("[Opening \"{}\"]\n", opening.name));
+        }
+        pgn.push('\n');
+
+        let mut movetext = Vec::new();
+        let mut number = self.start.fullmove_number;
+        let mut turn = self.start.turn;
+        if turn == PieceColor::Black && !self.moves.is_empty() {
+            movetext.push(format!("{}...", number));
+        }
+        for san in self.moves.iter() {
+            if turn == PieceColor::White {
+                movetext.push(format!("{}.", number));
+            } else {
+                number += 1;
+            }
+            movetext.push(san.clone());
+            turn = turn.opponent();
+        }
+        movetext.push(format!("{{{}}}", self.reason));
+        movetext.push(self.result.pgn().to_string());
+
+        pgn.push_str(&movetext.join(" "));
+        pgn.push_str("\n\n");
+        pgn
+    }
+}
+
+/// Play a game between two engines from `start` until it's decided or adjudicated
+pub fn play_game(
+    white_player: &mut dyn Player,
+    black_player: &mut dyn Player,
+    (white_name, black_name): (&str, &str),
+    start: &Position,
+    time_control: &TimeControl,
+    adjudication: &Adjudication,
+) -> GameRecord {
+    let mut record = GameRecord {
+        white: white_name.to_string(),
+        black: black_name.to_string(),
+        start: start.clone(),
+        moves: Vec::new(),
+        hashes: vec![start.zobrist()],
+        result: GameResult::Draw,
+        reason: String::new(),
+    };
+    let name = |color: PieceColor| match color {
+        PieceColor::White => white_name,
+        PieceColor::Black => black_name,
+    };
+
+    let new_game = white_player
+        .new_game()
+        .map_err(|error| (PieceColor::White, error))
+        .and_then(|_| {
+            black_player
+                .new_game()
+                .map_err(|error| (PieceColor::Black, error))
+        });
+    if let Err((color, error)) = new_game {
+        record.result = GameResult::win_for(color.opponent());
+        record.reason = format!("{} failed to start a game: {}", name(color), error);
+        return record;
+    }
+
+    let mut position = start.clone();
+    let mut moves: Vec<ChessMove> = Vec::new();
+    let mut clocks = Clocks::new(time_control);
+    let mut repetitions: HashMap<String, u32> = HashMap::new();
+    repetitions.insert(position.key(), 1);
+    // Consecutive moves each side has been lost in its own eyes
+    let mut losing_moves = [0, 0];
+    // Consecutive plies the engines have seen a dead even game
+    let mut drawn_plies = 0;
+
+    let (result, reason) = loop {
+        let turn = position.turn;
+        let legal = position.legal_moves();
+        if legal.is_empty() {
+            break if position.is_check() {
+                (
+                    GameResult::win_for(turn.opponent()),
+                    format!("{} mates", name(turn.opponent())),
+                )
+            } else {
+                (GameResult::Draw, "Stalemate".to_string())
+            };
+        }
+        if position.is_insufficient_material() {
+            break (GameResult::Draw, "Insufficient material".to_string());
+        }
+        if position.halfmove_clock >= 100 {
+            break (GameResult::Draw, "Fifty-move rule".to_string());
+        }
+        if repetitions.get(&position.key()).copied().unwrap_or(0) >= 3 {
+            break (GameResult::Draw, "Threefold repetition".to_string());
+        }
+        if let Some(max_moves) = adjudication.max_moves {
+            if position.fullmove_number > max_moves {
+                break (GameResult::Draw, "Move limit".to_string());
+            }
+        }
+
+        let player: &mut dyn Player = match turn {
+            PieceColor::White => &mut *white_player,
+            PieceColor::Black => &mut *black_player,
+        };
+        let started = Instant::now();
+        let thought = player.think(start, &moves, &position, &clocks);
+        let spent = started.elapsed();
+
+        let thought = match thought {
+            Ok(thought) => thought,
+            Err(error) => {
+                break (
+                    GameResult::win_for(turn.opponent()),
+                    format!("{} disconnects: {}", name(turn), error),
+                )
+            }
+        };
+        if !clocks.spend(turn, spent) {
+            break (
+                GameResult::win_for(turn.opponent()),
+                format!("{} loses on time", name(turn)),
+            );
+        }
+        if !legal.contains(&thought.chess_move) {
+            break (
+                GameResult::win_for(turn.opponent()),
+                format!("{} makes an illegal move", name(turn)),
+            );
+        }
+
+        record
+            .moves
+            .push(san(&thought.chess_move, &position.pieces, &position.state));
+        position.play(&thought.chess_move);
+        record.hashes.push(position.zobrist());
+        moves.push(thought.chess_move);
+        *repetitions.entry(position.key()).or_insert(0) += 1;
+
+        let side = match turn {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        };
+        let number = position.fullmove_number;
+        if let (Some(rule), Some(score)) = (adjudication.resign, thought.score) {
+            losing_moves[side] = if score <= -rule.score && number >= rule.from_move {
+                losing_moves[side] + 1
+            } else {
+                0
+            };
+            if losing_moves[side] >= rule.count {
+                break (
+                    GameResult::win_for(turn.opponent()),
+                    format!("{} resigns", name(turn)),
+                );
+            }
+        }
+        if let Some(rule) = adjudication.draw {
+            drawn_plies = match thought.score {
+                Some(score) if score.abs() <= rule.score && number >= rule.from_move => {
+                    drawn_plies + 1
+                }
+                _ => 0,
+            };
+            if drawn_plies >= rule.count * 2 {
+                break (GameResult::Draw, "Draw by adjudication".to_string());
+            }
+        }
+    };
+
+    record.result = result;
+    record.reason = reason;
+    record
+}
@@ -0,0 +1,14 @@
+mod stats;
+pub use stats::*;
+
+mod player;
+pub use player::*;
+
+mod game;
+pub use game::*;
+
+mod openings;
+pub use openings::*;
+
+mod runner;
+pub use runner::*;
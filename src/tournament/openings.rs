@@ -0,0 +1,37 @@
+use std::{fs, path::Path};
+
+use crate::pieces::*;
+
+/// Read the positions to start games from, one per line of an EPD file or
+/// the end of each game in a PGN file
+pub fn load_openings(path: &Path) -> Result<Vec<Position>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("Can't read {}: {}", path.display(), error))?;
+
+    let openings = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("epd") | Some("fen") => text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .enumerate()
+            .map(|(index, line)| {
+                Position::from_fen(line).map_err(|error| format!("Line {}: {}", index + 1, error))
+            })
+            .collect::<Result<Vec<Position>, String>>()?,
+        Some("pgn") => parse_pgn(&text)
+            .iter()
+            .enumerate()
+            .map(|(index, game)| {
+                game.replay()
+                    .map(|(position, _moves)| position)
+                    .map_err(|error| format!("Game {}: {}", index + 1, error))
+            })
+            .collect::<Result<Vec<Position>, String>>()?,
+        _ => return Err(format!("{} isn't an .epd or .pgn file", path.display())),
+    };
+
+    if openings.is_empty() {
+        return Err(format!("No openings in {}", path.display()));
+    }
+    Ok(openings)
+}
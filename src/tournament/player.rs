@@ -0,0 +1,319 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{search, SearchLimits, MATE_SCORE},
+    pieces::*,
+    profiles::engine_profile_name,
+};
+
+use super::{key_values, Clocks};
+
+/// How long an engine gets to start up and answer isready
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Extra time allowed for a bestmove to arrive after the clock ran out,
+/// so the move still gets played and the loss is put down to time
+const BESTMOVE_GRACE: Duration = Duration::from_secs(1);
+
+/// A move along with the engine's opinion of the position
+pub struct Thought {
+    pub chess_move: ChessMove,
+    // Centipawns for the side that moved, if the engine said
+    pub score: Option<i32>,
+}
+
+/// An engine taking part in the tournament
+pub trait Player {
+    fn new_game(&mut self) -> io::Result<()>;
+
+    /// Pick a move in `position`, reached by playing `moves` from `start`
+    fn think(
+        &mut self,
+        start: &Position,
+        moves: &[ChessMove],
+        position: &Position,
+        clocks: &Clocks,
+    ) -> io::Result<Thought>;
+}
+
+#[derive(Clone)]
+pub enum EngineKind {
+    BuiltIn(SearchLimits),
+    Uci {
+        command: String,
+        // Sent with setoption after the handshake
+        options: Vec<(String, String)>,
+    },
+}
+
+/// How to start one of the engines
+#[derive(Clone)]
+pub struct EngineSpec {
+    pub name: String,
+    pub kind: EngineKind,
+}
+
+impl EngineSpec {
+    pub fn start(&self) -> io::Result<Box<dyn Player>> {
+        Ok(match &self.kind {
+            EngineKind::BuiltIn(limits) => Box::new(BuiltInPlayer { limits: *limits }),
+            EngineKind::Uci { command, options } => Box::new(UciPlayer::start(command, options)?),
+        })
+    }
+}
+
+impl FromStr for EngineSpec {
+    type Err = String;
+
+    /// Reads "builtin,depth=3,quiescence=off,name=Shallow" for our AI or
+    /// "uci=/usr/bin/stockfish,option.Hash=16,name=Stockfish" for an external engine
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut items = text.splitn(2, ',');
+        let first = items.next().unwrap_or_default();
+        let settings = key_values(items.next().unwrap_or_default())?;
+
+        let mut name = None;
+        let mut kind = match first.split_once('=') {
+            None if first == "builtin" => EngineKind::BuiltIn(SearchLimits::default()),
+            Some(("uci", command)) if !command.is_empty() => EngineKind::Uci {
+                command: command.to_string(),
+                options: Vec::new(),
+            },
+            _ => {
+                return Err(format!(
+                    "Expected builtin or uci=<command>, got {:?}",
+                    first
+                ))
+            }
+        };
+
+        for (key, value) in settings {
+            match (&mut kind, key) {
+                (_, "name") => name = Some(value.to_string()),
+                (EngineKind::BuiltIn(limits), "depth") => {
+                    limits.depth = value
+                        .parse()
+                        .map_err(|_| "depth must be a number".to_string())?
+                }
+                (EngineKind::BuiltIn(limits), "quiescence") => {
+                    limits.quiescence = match value {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err("quiescence must be on or off".to_string()),
+                    }
+                }
+                (EngineKind::Uci { options, .. }, key) if key.starts_with("option.") => {
+                    options.push((key["option.".len()..].to_string(), value.to_string()))
+                }
+                _ => return Err(format!("Unknown engine setting {}", key)),
+            }
+        }
+
+        let name = name.unwrap_or_else(|| match &kind {
+            EngineKind::BuiltIn(limits) => engine_profile_name(limits.depth),
+            EngineKind::Uci { command, .. } => command.clone(),
+        });
+        Ok(EngineSpec { name, kind })
+    }
+}
+
+/// Our own AI, searching until its depth or share of the clock runs out
+pub struct BuiltInPlayer {
+    limits: SearchLimits,
+}
+
+impl Player for BuiltInPlayer {
+    fn new_game(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn think(
+        &mut self,
+        _start: &Position,
+        _moves: &[ChessMove],
+        position: &Position,
+        clocks: &Clocks,
+    ) -> io::Result<Thought> {
+        let limits = SearchLimits {
+            time: Some(clocks.budget(position.turn)),
+            ..self.limits
+        };
+        let result = search(position, &limits);
+        match result.best_move {
+            Some(chess_move) => Ok(Thought {
+                chess_move,
+                score: Some(result.score),
+            }),
+            None => Err(io::Error::other("No legal moves")),
+        }
+    }
+}
+
+/// An external engine speaking UCI over its standard input and output
+pub struct UciPlayer {
+    child: Child,
+    stdin: ChildStdin,
+    // Lines read from the engine's output by a separate thread, so reads can time out
+    lines: Receiver<String>,
+}
+
+impl UciPlayer {
+    pub fn start(command: &str, options: &[(String, String)]) -> io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut player = UciPlayer {
+            child,
+            stdin,
+            lines,
+        };
+        player.send("uci")?;
+        player.wait_for("uciok", HANDSHAKE_TIMEOUT)?;
+        for (name, value) in options {
+            player.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        player.send("isready")?;
+        player.wait_for("readyok", HANDSHAKE_TIMEOUT)?;
+        Ok(player)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines
+            .recv_timeout(timeout)
+            .map_err(|error| match error {
+                RecvTimeoutError::Timeout => {
+                    io::Error::new(io::ErrorKind::TimedOut, "Engine didn't answer in time")
+                }
+                RecvTimeoutError::Disconnected => {
+                    io::Error::new(io::ErrorKind::BrokenPipe, "Engine quit")
+                }
+            })
+    }
+
+    fn wait_for(&self, answer: &str, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        while self.read_line(deadline)?.trim() != answer {}
+        Ok(())
+    }
+}
+
+impl Player for UciPlayer {
+    fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE_TIMEOUT)
+    }
+
+    fn think(
+        &mut self,
+        start: &Position,
+        moves: &[ChessMove],
+        position: &Position,
+        clocks: &Clocks,
+    ) -> io::Result<Thought> {
+        let mut command = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            command.push_str(" moves");
+            for chess_move in moves {
+                command.push(' ');
+                command.push_str(&uci(chess_move));
+            }
+        }
+        self.send(&command)?;
+        self.send(&format!(
+            "go wtime {} btime {} winc {} binc {}",
+            clocks.white.as_millis(),
+            clocks.black.as_millis(),
+            clocks.increment.as_millis(),
+            clocks.increment.as_millis()
+        ))?;
+
+        let deadline = Instant::now() + clocks.remaining(position.turn) + BESTMOVE_GRACE;
+        let mut score = None;
+        loop {
+            let line = self.read_line(deadline)?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.first() {
+                Some(&"info") => {
+                    if let Some(index) = words.iter().position(|word| *word == "score") {
+                        score = match (words.get(index + 1), words.get(index + 2)) {
+                            (Some(&"cp"), Some(value)) => value.parse().ok().or(score),
+                            (Some(&"mate"), Some(value)) => value
+                                .parse::<i32>()
+                                .ok()
+                                .map(|moves| {
+                                    if moves > 0 {
+                                        MATE_SCORE - moves
+                                    } else {
+                                        -MATE_SCORE - moves
+                                    }
+                                })
+                                .or(score),
+                            _ => score,
+                        };
+                    }
+                }
+                Some(&"bestmove") => {
+                    let best = words.get(1).copied().unwrap_or_default();
+                    let chess_move =
+                        parse_move(best, position.turn, &position.pieces, &position.state)
+                            .map_err(|error| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("{}: {}", error, best),
+                                )
+                            })?;
+                    return Ok(Thought { chess_move, score });
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Give it a moment to quit by itself before pulling the plug
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
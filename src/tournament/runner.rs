@@ -0,0 +1,197 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use crate::pieces::*;
+
+use super::{
+    play_game, Adjudication, EngineSpec, GameRecord, Score, Sprt, SprtStatus, TimeControl,
+};
+
+pub struct TournamentConfig {
+    // The first engine is the one being tested, scores are from its side
+    pub engines: [EngineSpec; 2],
+    pub games: usize,
+    // Games played at the same time
+    pub concurrency: usize,
+    // Every opening is played twice with colors reversed, the usual start
+    // position is used when there are none
+    pub openings: Vec<Position>,
+    pub time_control: TimeControl,
+    pub adjudication: Adjudication,
+    // Stop as soon as the test is decided
+    pub sprt: Option<Sprt>,
+}
+
+pub struct FinishedGame {
+    // Order the game was scheduled in, starting at 0
+    pub index: usize,
+    pub first_engine_white: bool,
+    pub record: GameRecord,
+}
+
+/// How a game went for one of the engines
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl FinishedGame {
+    /// The result from the first engine's side
+    pub fn first_engine_outcome(&self) -> Outcome {
+        let first_engine = if self.first_engine_white {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        match self.record.result.winner() {
+            None => Outcome::Draw,
+            Some(winner) if winner == first_engine => Outcome::Win,
+            Some(_) => Outcome::Loss,
+        }
+    }
+}
+
+/// Where the tournament stands after each game
+pub struct Standings {
+    pub score: Score,
+    pub sprt: Option<SprtStatus>,
+}
+
+impl Standings {
+    fn add(&mut self, game: &FinishedGame) {
+        match game.first_engine_outcome() {
+            Outcome::Win => self.score.wins += 1,
+            Outcome::Draw => self.score.draws += 1,
+            Outcome::Loss => self.score.losses += 1,
+        }
+    }
+}
+
+/// Play the games on `concurrency` threads, calling `on_game` in the order
+/// they finish. Fails if an engine can't be started.
+pub fn run_tournament(
+    config: &TournamentConfig,
+    mut on_game: impl FnMut(&FinishedGame, &Standings),
+) -> io::Result<Standings> {
+    let next_game = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel::<io::Result<FinishedGame>>();
+
+    let workers: Vec<_> = (0..config.concurrency.clamp(1, config.games.max(1)))
+        .map(|_| {
+            let next_game = next_game.clone();
+            let stop = stop.clone();
+            let sender = sender.clone();
+            let engines = config.engines.clone();
+            let openings = config.openings.clone();
+            let time_control = config.time_control;
+            let adjudication = config.adjudication;
+            let games = config.games;
+
+            thread::spawn(move || {
+                let mut players = Vec::new();
+                for engine in engines.iter() {
+                    match engine.start() {
+                        Ok(player) => players.push(player),
+                        Err(error) => {
+                            let _ = sender.send(Err(io::Error::new(
+                                error.kind(),
+                                format!("Can't start {}: {}", engine.name, error),
+                            )));
+                            return;
+                        }
+                    }
+                }
+
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= games {
+                        break;
+                    }
+
+                    let start = if openings.is_empty() {
+                        Position::default()
+                    } else {
+                        openings[(index / 2) % openings.len()].clone()
+                    };
+                    let first_engine_white = index % 2 == 0;
+                    let (first, second) = players.split_at_mut(1);
+                    let (first, second) = (first[0].as_mut(), second[0].as_mut());
+                    let (first_name, second_name) =
+                        (engines[0].name.as_str(), engines[1].name.as_str());
+
+                    let record = if first_engine_white {
+                        play_game(
+                            first,
+                            second,
+                            (first_name, second_name),
+                            &start,
+                            &time_control,
+                            &adjudication,
+                        )
+                    } else {
+                        play_game(
+                            second,
+                            first,
+                            (second_name, first_name),
+                            &start,
+                            &time_control,
+                            &adjudication,
+                        )
+                    };
+                    let finished = FinishedGame {
+                        index,
+                        first_engine_white,
+                        record,
+                    };
+                    if sender.send(Ok(finished)).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    // Only the workers hold senders now, so the loop ends when they're all done
+    drop(sender);
+
+    let mut standings = Standings {
+        score: Score::default(),
+        sprt: config.sprt.map(|_| SprtStatus::Continue),
+    };
+    let mut failure = None;
+    for message in receiver {
+        match message {
+            Ok(game) => {
+                standings.add(&game);
+                if let Some(sprt) = config.sprt {
+                    let status = sprt.status(&standings.score);
+                    standings.sprt = Some(status);
+                    if status != SprtStatus::Continue {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+                on_game(&game, &standings);
+            }
+            Err(error) => {
+                stop.store(true, Ordering::Relaxed);
+                failure.get_or_insert(error);
+            }
+        }
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(standings),
+    }
+}
@@ -0,0 +1,192 @@
+use std::{fmt, str::FromStr};
+
+/// Wins, draws and losses of the first engine against the second
+#[derive(Clone, Copy, Default)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, between 0 and 1
+    pub fn ratio(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.) / self.games() as f64
+    }
+
+    /// Variance of the points of a single game
+    fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        let ratio = self.ratio();
+        [(self.wins, 1.), (self.draws, 0.5), (self.losses, 0.)]
+            .iter()
+            .map(|(count, points)| *count as f64 / games * (points - ratio).powi(2))
+            .sum()
+    }
+
+    /// Elo difference the score is expected from
+    pub fn elo(&self) -> f64 {
+        elo_from_ratio(self.ratio())
+    }
+
+    /// Half the width of the 95% confidence interval of the Elo difference
+    pub fn elo_error(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.;
+        }
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let ratio = self.ratio();
+        (elo_from_ratio(ratio + margin) - elo_from_ratio(ratio - margin)) / 2.
+    }
+
+    /// Likelihood of superiority, the chance the first engine is the stronger one
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0. {
+            return 0.5;
+        }
+        0.5 * (1. + erf((self.wins as f64 - self.losses as f64) / (2. * decisive).sqrt()))
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} - {} [{:.3}] {} games",
+            self.wins,
+            self.losses,
+            self.draws,
+            self.ratio(),
+            self.games()
+        )
+    }
+}
+
+/// Elo difference for a points ratio, capped at a win or loss of every game
+pub fn elo_from_ratio(ratio: f64) -> f64 {
+    let ratio = ratio.clamp(1e-6, 1. - 1e-6);
+    -400. * (1. / ratio - 1.).log10()
+}
+
+/// Points ratio expected from an Elo difference
+pub fn ratio_from_elo(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+/// Abramowitz and Stegun's approximation of the error function
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.327_591_1 * x.abs());
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let result = 1. - polynomial * (-x * x).exp();
+    if x < 0. {
+        -result
+    } else {
+        result
+    }
+}
+
+/// Sequential probability ratio test of whether the first engine is `elo1`
+/// stronger rather than `elo0`, with the chances of wrongly accepting either
+#[derive(Clone, Copy)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SprtStatus {
+    Continue,
+    // The first engine is about `elo0` stronger, or weaker
+    AcceptH0,
+    // The first engine is about `elo1` stronger, or more
+    AcceptH1,
+}
+
+impl Sprt {
+    /// Bounds for the log-likelihood ratio, passing either one ends the test
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1. - self.alpha)).ln(),
+            ((1. - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of the hypotheses, approximating the result of a
+    /// game by a normal distribution with the observed variance
+    pub fn llr(&self, score: &Score) -> f64 {
+        let variance = score.variance();
+        // Nothing to tell the hypotheses apart with before the results differ
+        if score.games() == 0 || variance <= 0. {
+            return 0.;
+        }
+        let (ratio0, ratio1) = (ratio_from_elo(self.elo0), ratio_from_elo(self.elo1));
+        score.games() as f64 * (ratio1 - ratio0) * (2. * score.ratio() - ratio0 - ratio1)
+            / (2. * variance)
+    }
+
+    pub fn status(&self, score: &Score) -> SprtStatus {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtStatus::AcceptH1
+        } else if llr <= lower {
+            SprtStatus::AcceptH0
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+impl FromStr for Sprt {
+    type Err = String;
+
+    /// Reads "elo0=0,elo1=5,alpha=0.05,beta=0.05", the error rates default to 5%
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut sprt = Sprt {
+            elo0: 0.,
+            elo1: 5.,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        for (key, value) in key_values(text)? {
+            let value: f64 = value
+                .parse()
+                .map_err(|_| format!("{} must be a number", key))?;
+            match key {
+                "elo0" => sprt.elo0 = value,
+                "elo1" => sprt.elo1 = value,
+                "alpha" => sprt.alpha = value,
+                "beta" => sprt.beta = value,
+                _ => return Err(format!("Unknown SPRT setting {}", key)),
+            }
+        }
+        if sprt.elo1 <= sprt.elo0 {
+            return Err("elo1 must be larger than elo0".to_string());
+        }
+        Ok(sprt)
+    }
+}
+
+/// Splits "key=value,key=value" settings
+pub fn key_values(text: &str) -> Result<Vec<(&str, &str)>, String> {
+    text.split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got {:?}", item))
+        })
+        .collect()
+}
@@ -1,20 +1,24 @@
-use crate::{board::*, pieces::*};
+use crate::{
+    board::*,
+    captured::*,
+    database::GameBrowser,
+    game::*,
+    history::*,
+    pieces::PieceSets,
+    profiles::ProfilePage,
+    save::{SaveAction, SaveFiles},
+    theme::Themes,
+};
 use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::component::Component;
+use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
 // Component to mark the Text entity
 struct NextMoveText;
 
-/// Initialize UiCamera and text
-fn init_next_move_text(
-    mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
-    mut color_materials: ResMut<Assets<ColorMaterial>>,
-) {
-    // let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let material = color_materials.add(Color::NONE.into());
-
-    commands.spawn_bundle(UiCameraBundle::default());
-    // root node
+/// Initialize the text showing whose turn it is
+fn init_next_move_text(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn_bundle(TextBundle {
             style: Style {
@@ -26,100 +30,109 @@ fn init_next_move_text(
                 },
                 ..Default::default()
             },
-            // material,
+            text: Text::with_section(
+                "Next move: White",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
             ..Default::default()
         })
-        .with_children(|parent| {
-            parent
-                .spawn_bundle(TextBundle {
-                    text: Text::with_section(
-                        // Accepts a `String` or any type that converts into a `String`, such as `&str`
-                        "Next move: white".to_string(),
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: 100.0,
-                            color: Color::WHITE,
-                        },
-                        // Note: You can use `Default::default()` in place of the `TextAlignment`
-                        TextAlignment {
-                            horizontal: HorizontalAlign::Center,
-                            ..Default::default()
-                        },
-                    ),
-                    ..Default::default()
-                })
-                .insert(NextMoveText);
-        });
+        .insert(NextMoveText);
 }
 
 /// Update text with the correct turn
-
-fn next_move_text_update(turn: Res<PlayerTurn>, mut query: Query<(&mut Text, &NextMoveText)>) {
+fn next_move_text_update(turn: Res<PlayerTurn>, mut query: Query<&mut Text, With<NextMoveText>>) {
     if !turn.is_changed() {
         return;
     }
 
-    for (mut text, _tag) in query.iter_mut() {
-        text.sections[1].value = format!(
-            "Next move: {}",
-            match turn.0 {
-                PieceColor::White => "White",
-                PieceColor::Black => "Black",
-            }
-        );
-    }
-}
-
-pub struct UIPlugin;
-impl Plugin for UIPlugin {
-    fn build(&self, app: &mut AppBuilder) {
-        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
-            .add_startup_system(setup.system())
-            .add_system(text_update_system.system())
-            .add_system(text_color_system.system());
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Next move: {}", turn.0.name());
     }
 }
 
-// A unit struct to help identify the FPS UI component, since there may be many Text components
-struct FpsText;
-
-// A unit struct to help identify the color-changing Text component
-struct ColorText;
+// Component to mark the Text entity showing the material advantage
+struct MaterialBalanceText;
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // UI camera
-    commands.spawn_bundle(UiCameraBundle::default());
-    // Text with one section
+fn init_material_balance_text(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn_bundle(TextBundle {
             style: Style {
-                align_self: AlignSelf::FlexEnd,
                 position_type: PositionType::Absolute,
                 position: Rect {
-                    bottom: Val::Px(5.0),
-                    right: Val::Px(15.0),
+                    left: Val::Px(10.),
+                    top: Val::Px(45.),
                     ..Default::default()
                 },
                 ..Default::default()
             },
-            // Use the `Text::with_section` constructor
             text: Text::with_section(
-                // Accepts a `String` or any type that converts into a `String`, such as `&str`
-                "hello\nbevy!",
+                "",
                 TextStyle {
                     font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: 100.0,
-                    color: Color::WHITE,
-                },
-                // Note: You can use `Default::default()` in place of the `TextAlignment`
-                TextAlignment {
-                    horizontal: HorizontalAlign::Center,
-                    ..Default::default()
+                    font_size: 24.0,
+                    color: Color::GOLD,
                 },
+                Default::default(),
             ),
             ..Default::default()
         })
-        .insert(ColorText);
+        .insert(MaterialBalanceText);
+}
+
+/// Show the side that is ahead in material, e.g. "White +3"
+fn material_balance_text_update(
+    balance: Res<MaterialBalance>,
+    mut query: Query<&mut Text, With<MaterialBalanceText>>,
+) {
+    if !balance.is_changed() {
+        return;
+    }
+
+    let value = match balance.leader {
+        Some((color, lead)) => format!("{} +{}", color.name(), lead),
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+pub struct UIPlugin;
+impl Plugin for UIPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .init_resource::<MoveListScroll>()
+            .add_startup_system(setup.system())
+            .add_startup_system(init_next_move_text.system())
+            .add_startup_system(init_material_balance_text.system())
+            .add_startup_system(init_game_controls.system())
+            .add_startup_system(init_move_list.system())
+            .add_system(text_update_system.system())
+            .add_system(next_move_text_update.system())
+            .add_system(material_balance_text_update.system())
+            .add_system(scroll_move_list.system().label("scroll_move_list"))
+            .add_system(move_list_update.system().after("scroll_move_list"))
+            .add_system(move_list_buttons.system())
+            .init_resource::<SettingsMenu>()
+            .add_startup_system(init_settings_menu.system())
+            .add_system(settings_buttons.system().label("settings_buttons"))
+            .add_system(settings_menu_update.system().after("settings_buttons"))
+            .add_system(game_control_buttons.system())
+            .add_system(game_status_text_update.system());
+    }
+}
+
+// A unit struct to help identify the FPS UI component, since there may be many Text components
+struct FpsText;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // UI camera
+    commands.spawn_bundle(UiCameraBundle::default());
     // Rich text with multiple sections
     commands
         .spawn_bundle(TextBundle {
@@ -166,16 +179,717 @@ fn text_update_system(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text,
     }
 }
 
-fn text_color_system(time: Res<Time>, mut query: Query<&mut Text, With<ColorText>>) {
+// Component to mark the buttons for resigning and draw offers
+#[derive(Clone, Copy)]
+enum GameControlButton {
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+}
+
+// Component to mark the Text entity showing draw offers and the result
+struct GameStatusText;
+
+fn init_game_controls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+    let panel_material = color_materials.add(Color::NONE.into());
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: panel_material.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(GameStatusText);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    material: panel_material,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (GameControlButton::Resign, "Resign"),
+                        (GameControlButton::OfferDraw, "Offer draw"),
+                        (GameControlButton::AcceptDraw, "Accept draw"),
+                        (GameControlButton::DeclineDraw, "Decline draw"),
+                    ]
+                    .iter()
+                    {
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    margin: Rect::all(Val::Px(4.)),
+                                    padding: Rect::all(Val::Px(6.)),
+                                    ..Default::default()
+                                },
+                                material: button_material.clone(),
+                                ..Default::default()
+                            })
+                            .insert(*button)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        *label,
+                                        TextStyle {
+                                            font: font.clone(),
+                                            font_size: 20.0,
+                                            color: Color::WHITE,
+                                        },
+                                        Default::default(),
+                                    ),
+                                    ..Default::default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+/// Turn button clicks into game actions for the player they concern
+fn game_control_buttons(
+    turn: Res<PlayerTurn>,
+    draw_offer: Res<DrawOffer>,
+    players: Res<Players>,
+    mut actions: EventWriter<GameAction>,
+    query: Query<(&Interaction, &GameControlButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            GameControlButton::Resign | GameControlButton::OfferDraw => {
+                let color = match players.acting_color(turn.0) {
+                    Some(color) => color,
+                    None => continue,
+                };
+                actions.send(match button {
+                    GameControlButton::Resign => GameAction::Resign(color),
+                    _ => GameAction::OfferDraw(color),
+                });
+            }
+            GameControlButton::AcceptDraw | GameControlButton::DeclineDraw => {
+                // Only a human can answer an offer from this panel
+                let answering = match draw_offer.offered_by {
+                    Some(color) if players.get(color.opponent()) == PlayerKind::Human => {
+                        color.opponent()
+                    }
+                    _ => continue,
+                };
+
+                actions.send(match button {
+                    GameControlButton::AcceptDraw => GameAction::AcceptDraw(answering),
+                    _ => GameAction::DeclineDraw(answering),
+                });
+            }
+        }
+    }
+}
+
+fn game_status_text_update(
+    game_over: Res<GameOver>,
+    draw_offer: Res<DrawOffer>,
+    mut query: Query<&mut Text, With<GameStatusText>>,
+) {
+    if !game_over.is_changed() && !draw_offer.is_changed() {
+        return;
+    }
+
+    let status = if let Some(outcome) = &game_over.0 {
+        format!("{} {}", outcome.description(), outcome.result.pgn())
+    } else if let Some(color) = draw_offer.offered_by {
+        format!("{} offers a draw", color.name())
+    } else {
+        String::new()
+    };
+
     for mut text in query.iter_mut() {
-        let seconds = time.seconds_since_startup() as f32;
-        // We used the `Text::with_section` helper method, but it is still just a `Text`,
-        // so to update it, we are still updating the one and only section
-        text.sections[0].style.color = Color::Rgba {
-            red: (1.25 * seconds).sin() / 2.0 + 0.5,
-            green: (0.75 * seconds).sin() / 2.0 + 0.5,
-            blue: (0.50 * seconds).sin() / 2.0 + 0.5,
-            alpha: 1.0,
-        };
+        text.sections[0].value = status.clone();
     }
 }
+
+/// Number of move pairs shown in the move list at once
+const MOVE_LIST_ROWS: usize = 16;
+
+/// How many rows the move list is scrolled up from the latest move
+#[derive(Default)]
+struct MoveListScroll {
+    rows_from_bottom: usize,
+}
+
+// Component to mark the move list panel, which scrolls while hovered
+struct MoveListPanel;
+
+// Component to mark the node holding the move list rows
+struct MoveListRows;
+
+// Component to mark the move list button showing a half-move
+struct MoveListEntry(usize);
+
+// Component to mark the button going back to the live position
+struct LiveButton;
+
+struct MoveListMaterials {
+    entry: Handle<ColorMaterial>,
+    viewed: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+fn init_move_list(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let materials = MoveListMaterials {
+        entry: color_materials.add(Color::NONE.into()),
+        viewed: color_materials.add(Color::rgb(0.6, 0.5, 0.1).into()),
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+    };
+    let panel_material = color_materials.add(Color::rgba(0.1, 0.1, 0.1, 0.8).into());
+    let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(190.), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: panel_material,
+            ..Default::default()
+        })
+        // Track hovering, so the board doesn't react to clicks on the panel
+        .insert(Interaction::default())
+        .insert(FocusPolicy::Block)
+        .insert(MoveListPanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        ..Default::default()
+                    },
+                    material: materials.entry.clone(),
+                    ..Default::default()
+                })
+                .insert(MoveListRows);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(4.)),
+                        padding: Rect::all(Val::Px(6.)),
+                        ..Default::default()
+                    },
+                    material: button_material,
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(LiveButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Back to live position",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 18.0,
+                                color: Color::WHITE,
+                            },
+                            Default::default(),
+                        ),
+                        visible: Visible {
+                            is_visible: false,
+                            is_transparent: true,
+                        },
+                        ..Default::default()
+                    });
+                });
+        });
+
+    commands.insert_resource(materials);
+}
+
+/// Scroll through older moves with the mouse wheel while hovering the panel
+fn scroll_move_list(
+    mut wheel_events: EventReader<MouseWheel>,
+    history: Res<MoveHistory>,
+    mut scroll: ResMut<MoveListScroll>,
+    panel_query: Query<&Interaction, With<MoveListPanel>>,
+) {
+    let is_hovered = panel_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    let rows = history.moves.len().div_ceil(2);
+    let max_scroll = rows.saturating_sub(MOVE_LIST_ROWS);
+
+    let mut rows_from_bottom = scroll.rows_from_bottom as isize;
+    for event in wheel_events.iter() {
+        if is_hovered {
+            rows_from_bottom += event.y.signum() as isize;
+        }
+    }
+    let rows_from_bottom = rows_from_bottom.max(0).min(max_scroll as isize) as usize;
+
+    // A new move scrolls back down to show it
+    if history.is_changed() {
+        scroll.rows_from_bottom = 0;
+    } else if scroll.rows_from_bottom != rows_from_bottom {
+        scroll.rows_from_bottom = rows_from_bottom;
+    }
+}
+
+/// Rebuild the rows of the move list, e.g. "1. e4 e5"
+#[allow(clippy::too_many_arguments)]
+fn move_list_update(
+    mut commands: Commands,
+    history: Res<MoveHistory>,
+    view: Res<HistoryView>,
+    scroll: Res<MoveListScroll>,
+    materials: Res<MoveListMaterials>,
+    rows_query: Query<(Entity, Option<&Children>), With<MoveListRows>>,
+    live_button_query: Query<&Children, With<LiveButton>>,
+    mut visible_query: Query<&mut Visible>,
+) {
+    if !history.is_changed() && !view.is_changed() && !scroll.is_changed() {
+        return;
+    }
+
+    let rows = history.moves.len().div_ceil(2);
+    let last_row = rows - scroll.rows_from_bottom.min(rows);
+    let first_row = last_row.saturating_sub(MOVE_LIST_ROWS);
+
+    let text_style = TextStyle {
+        font: materials.font.clone(),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+
+    for (container, children) in rows_query.iter() {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            commands.entity(*child).despawn_recursive();
+        }
+
+        commands.entity(container).with_children(|parent| {
+            for row in first_row..last_row {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..Default::default()
+                        },
+                        material: materials.entry.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(40.), Val::Auto),
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                format!("{}.", row + 1),
+                                text_style.clone(),
+                                Default::default(),
+                            ),
+                            ..Default::default()
+                        });
+
+                        for ply in (row * 2..row * 2 + 2).filter(|ply| *ply < history.moves.len()) {
+                            parent
+                                .spawn_bundle(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(70.), Val::Auto),
+                                        padding: Rect::all(Val::Px(2.)),
+                                        ..Default::default()
+                                    },
+                                    material: if view.ply == Some(ply) {
+                                        materials.viewed.clone()
+                                    } else {
+                                        materials.entry.clone()
+                                    },
+                                    ..Default::default()
+                                })
+                                .insert(MoveListEntry(ply))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(TextBundle {
+                                        text: Text::with_section(
+                                            history.moves[ply].san.clone(),
+                                            text_style.clone(),
+                                            Default::default(),
+                                        ),
+                                        ..Default::default()
+                                    });
+                                });
+                        }
+                    });
+            }
+        });
+    }
+
+    // The way back is only shown while looking at a past position
+    for children in live_button_query.iter() {
+        for child in children.iter() {
+            if let Ok(mut visible) = visible_query.get_mut(*child) {
+                visible.is_visible = view.ply.is_some();
+            }
+        }
+    }
+}
+
+/// Show the position after a clicked move, or go back to the live one
+fn move_list_buttons(
+    history: Res<MoveHistory>,
+    mut view: ResMut<HistoryView>,
+    mut reset_selected_event: EventWriter<ResetSelectedEvent>,
+    entry_query: Query<(&Interaction, &MoveListEntry), Changed<Interaction>>,
+    live_query: Query<&Interaction, (Changed<Interaction>, With<LiveButton>)>,
+    mut live_visible_query: Query<&mut Visible, With<LiveButton>>,
+) {
+    for (interaction, entry) in entry_query.iter() {
+        if *interaction == Interaction::Clicked {
+            // The latest move is the position on the board already
+            view.ply = if entry.0 + 1 < history.moves.len() {
+                Some(entry.0)
+            } else {
+                None
+            };
+            reset_selected_event.send(ResetSelectedEvent);
+        }
+    }
+
+    for interaction in live_query.iter() {
+        if *interaction == Interaction::Clicked {
+            view.ply = None;
+        }
+    }
+
+    for mut visible in live_visible_query.iter_mut() {
+        visible.is_visible = view.ply.is_some();
+    }
+}
+
+#[derive(Default)]
+struct SettingsMenu {
+    open: bool,
+}
+
+// Component to mark the button opening and closing the settings menu
+struct SettingsButton;
+
+// Component to mark the node holding the settings menu entries
+struct SettingsPanel;
+
+// Component to mark the button switching to a theme
+struct ThemeButton(usize);
+
+// Component to mark the button switching to a piece set
+struct PieceSetButton(usize);
+
+// Component to mark the button saving the game
+struct SaveGameButton;
+
+// Component to mark the button loading the saved game
+struct LoadGameButton;
+
+// Component to mark the button opening the game database
+struct DatabaseButton;
+
+// Component to mark the button opening the player profiles
+struct ProfilesButton;
+
+struct SettingsMaterials {
+    button: Handle<ColorMaterial>,
+    current: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+fn init_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let materials = SettingsMaterials {
+        button: color_materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+        current: color_materials.add(Color::rgb(0.6, 0.5, 0.1).into()),
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+    };
+    let panel_material = color_materials.add(Color::NONE.into());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            material: panel_material.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Stretch,
+                        ..Default::default()
+                    },
+                    material: panel_material,
+                    ..Default::default()
+                })
+                .insert(SettingsPanel);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(4.)),
+                        padding: Rect::all(Val::Px(6.)),
+                        ..Default::default()
+                    },
+                    material: materials.button.clone(),
+                    ..Default::default()
+                })
+                .insert(SettingsButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Settings",
+                            TextStyle {
+                                font: materials.font.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    });
+                });
+        });
+
+    commands.insert_resource(materials);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn settings_buttons(
+    mut menu: ResMut<SettingsMenu>,
+    mut themes: ResMut<Themes>,
+    mut piece_sets: ResMut<PieceSets>,
+    settings_query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    theme_query: Query<(&Interaction, &ThemeButton), Changed<Interaction>>,
+    piece_set_query: Query<(&Interaction, &PieceSetButton), Changed<Interaction>>,
+    files: Res<SaveFiles>,
+    mut save_actions: EventWriter<SaveAction>,
+    save_query: Query<&Interaction, (Changed<Interaction>, With<SaveGameButton>)>,
+    load_query: Query<&Interaction, (Changed<Interaction>, With<LoadGameButton>)>,
+    mut browser: ResMut<GameBrowser>,
+    database_query: Query<&Interaction, (Changed<Interaction>, With<DatabaseButton>)>,
+    mut profile_page: ResMut<ProfilePage>,
+    profiles_query: Query<&Interaction, (Changed<Interaction>, With<ProfilesButton>)>,
+) {
+    for interaction in settings_query.iter() {
+        if *interaction == Interaction::Clicked {
+            menu.open = !menu.open;
+        }
+    }
+
+    for (interaction, button) in theme_query.iter() {
+        if *interaction == Interaction::Clicked && themes.current != button.0 {
+            themes.current = button.0;
+        }
+    }
+
+    for (interaction, button) in piece_set_query.iter() {
+        if *interaction == Interaction::Clicked && piece_sets.current != button.0 {
+            piece_sets.current = button.0;
+        }
+    }
+
+    for interaction in save_query.iter() {
+        if *interaction == Interaction::Clicked {
+            save_actions.send(SaveAction::Save(files.game.clone()));
+        }
+    }
+
+    for interaction in load_query.iter() {
+        if *interaction == Interaction::Clicked {
+            save_actions.send(SaveAction::Load(files.game.clone()));
+        }
+    }
+
+    for interaction in database_query.iter() {
+        if *interaction == Interaction::Clicked {
+            browser.open = !browser.open;
+            menu.open = false;
+        }
+    }
+
+    for interaction in profiles_query.iter() {
+        if *interaction == Interaction::Clicked {
+            profile_page.open = !profile_page.open;
+            menu.open = false;
+        }
+    }
+}
+
+/// List the themes and piece sets while the menu is open, marking the ones in
+/// use, along with saving and loading the game, the game database and the
+/// player profiles
+fn settings_menu_update(
+    mut commands: Commands,
+    menu: Res<SettingsMenu>,
+    themes: Res<Themes>,
+    piece_sets: Res<PieceSets>,
+    materials: Res<SettingsMaterials>,
+    panel_query: Query<(Entity, Option<&Children>), With<SettingsPanel>>,
+) {
+    if !menu.is_changed() && !themes.is_changed() && !piece_sets.is_changed() {
+        return;
+    }
+
+    for (panel, children) in panel_query.iter() {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            commands.entity(*child).despawn_recursive();
+        }
+
+        if !menu.open {
+            continue;
+        }
+
+        commands.entity(panel).with_children(|parent| {
+            spawn_settings_entry(
+                parent,
+                &materials,
+                "Save game".to_string(),
+                false,
+                SaveGameButton,
+            );
+            spawn_settings_entry(
+                parent,
+                &materials,
+                "Load game".to_string(),
+                false,
+                LoadGameButton,
+            );
+            spawn_settings_entry(
+                parent,
+                &materials,
+                "Game database".to_string(),
+                false,
+                DatabaseButton,
+            );
+            spawn_settings_entry(
+                parent,
+                &materials,
+                "Player profiles".to_string(),
+                false,
+                ProfilesButton,
+            );
+            for (index, theme) in themes.themes.iter().enumerate() {
+                spawn_settings_entry(
+                    parent,
+                    &materials,
+                    format!("Theme: {}", theme.name),
+                    index == themes.current,
+                    ThemeButton(index),
+                );
+            }
+            for (index, piece_set) in piece_sets.sets.iter().enumerate() {
+                spawn_settings_entry(
+                    parent,
+                    &materials,
+                    format!("Pieces: {}", piece_set.name),
+                    index == piece_sets.current,
+                    PieceSetButton(index),
+                );
+            }
+        });
+    }
+}
+
+fn spawn_settings_entry(
+    parent: &mut ChildBuilder,
+    materials: &SettingsMaterials,
+    label: String,
+    is_current: bool,
+    button: impl Component,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(2.)),
+                padding: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: if is_current {
+                materials.current.clone()
+            } else {
+                materials.button.clone()
+            },
+            ..Default::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    label,
+                    TextStyle {
+                        font: materials.font.clone(),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
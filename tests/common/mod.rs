@@ -0,0 +1,92 @@
+// Each test file uses its own share of these
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::Events, prelude::*};
+
+use rust_chess::{
+    headless::{headless_app, play_move},
+    history::MoveHistory,
+    network::Network,
+};
+
+/// Longest a test waits for the other side of a connection
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A folder of its own for a test's files, so tests running together don't
+/// overwrite each other's. It's removed when the test is done.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(test: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("rust_chess_{}_{}", std::process::id(), test)))
+    }
+
+    pub fn join(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A headless app with what `setup` adds to it, once the first frame has run
+pub fn start(setup: impl FnOnce(&mut AppBuilder)) -> App {
+    let mut app = headless_app();
+    setup(&mut app);
+    let mut app = app.app;
+    app.update();
+    app
+}
+
+pub fn play_moves(app: &mut App, moves: &[&str]) {
+    for input in moves {
+        assert!(play_move(app, input).is_ok(), "{} can't be played", input);
+    }
+}
+
+/// The moves played so far
+pub fn sans(app: &App) -> Vec<String> {
+    app.world
+        .get_resource::<MoveHistory>()
+        .unwrap()
+        .moves
+        .iter()
+        .map(|record| record.san.clone())
+        .collect()
+}
+
+pub fn send<T: Send + Sync + 'static>(app: &mut App, event: T) {
+    app.world
+        .get_resource_mut::<Events<T>>()
+        .unwrap()
+        .send(event);
+}
+
+pub fn network(app: &App) -> &Network {
+    app.world.get_resource::<Network>().unwrap()
+}
+
+/// Run the games until `done` holds for them
+pub fn run_until(apps: &mut [&mut App], done: impl Fn(&[&mut App]) -> bool) {
+    let start = Instant::now();
+    while !done(apps) {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "Timed out waiting on the other side"
+        );
+        for app in apps.iter_mut() {
+            app.update();
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
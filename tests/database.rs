@@ -0,0 +1,289 @@
+mod common;
+
+use bevy::prelude::*;
+
+use rust_chess::{
+    database::{DatabaseFile, DatabasePlugin, Exploration, GameDatabase, GameFilter, GameRecord},
+    game::{GameAction, GameOver, GameResult, Termination},
+    history::HistoryView,
+    pieces::*,
+    save::{SaveAction, SavePlugin},
+};
+
+use common::{play_moves, sans, send, start, TempDir};
+
+/// A headless app storing finished games in a database in `folder`
+fn new_game(folder: &TempDir) -> App {
+    let file = DatabaseFile {
+        path: folder.join("games.jsonl"),
+    };
+    start(|app| {
+        app.insert_resource(file)
+            .add_plugin(SavePlugin)
+            .add_plugin(DatabasePlugin);
+    })
+}
+
+fn resign(app: &mut App, color: PieceColor) {
+    send(app, GameAction::Resign(color));
+    app.update();
+    app.update();
+}
+
+fn record(white: &str, black: &str, date: &str, result: GameResult, eco: &str) -> GameRecord {
+    GameRecord {
+        id: 0,
+        white: white.to_string(),
+        black: black.to_string(),
+        date: date.to_string(),
+        result,
+        termination: Termination::Resignation,
+        eco: Some(eco.to_string()),
+        opening: Some(format!("Opening {}", eco)),
+        time_control: None,
+        start: None,
+        moves: vec!["e4".to_string()],
+        final_fen: STARTING_FEN.to_string(),
+        hashes: Vec::new(),
+        material: Vec::new(),
+    }
+}
+
+#[test]
+fn stores_finished_games() {
+    let folder = TempDir::new("store");
+    let mut app = new_game(&folder);
+    play_moves(&mut app, &["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"]);
+    resign(&mut app, PieceColor::Black);
+
+    let database = app.world.get_resource::<GameDatabase>().unwrap();
+    assert_eq!(database.games.len(), 1);
+    let game = &database.games[0];
+    assert_eq!(game.id, 1);
+    assert!(game.result == GameResult::WhiteWins);
+    assert_eq!(game.eco.as_deref(), Some("C50"));
+    assert_eq!(game.opening.as_deref(), Some("Italian Game: Giuoco Piano"));
+    assert_eq!(
+        game.final_fen,
+        "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+    );
+
+    // Still there when the database is opened again
+    let reopened = GameDatabase::open(&database.path).unwrap();
+    assert_eq!(reopened.games.len(), 1);
+    assert_eq!(reopened.games[0].moves, game.moves);
+}
+
+#[test]
+fn skips_lines_that_are_not_games() {
+    let folder = TempDir::new("skip");
+    let path = folder.join("games.jsonl");
+    let mut game = record("Alice", "Bob", "2024.01.15", GameResult::Draw, "A00");
+    game.id = 3;
+    let lines = [
+        serde_json::to_string(&game).unwrap(),
+        "{\"id\": 7, \"white\": \"Alice\"}".to_string(),
+        "not a game".to_string(),
+    ];
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, lines.join("\n")).unwrap();
+
+    let mut database = GameDatabase::open(&path).unwrap();
+    assert_eq!(database.games.len(), 1);
+    assert_eq!(database.games[0].white, "Alice");
+    // The id of the line that was skipped isn't given out again
+    assert_eq!(
+        database.add(record(
+            "Bob",
+            "Alice",
+            "2024.01.16",
+            GameResult::Draw,
+            "A00"
+        )),
+        Ok(8)
+    );
+}
+
+#[test]
+fn finds_games_by_player_result_opening_and_date() {
+    let folder = TempDir::new("search");
+    let mut database = GameDatabase::open(&folder.join("games.jsonl")).unwrap();
+    database
+        .add(record(
+            "Alice",
+            "Computer",
+            "2024.01.15",
+            GameResult::WhiteWins,
+            "C50",
+        ))
+        .unwrap();
+    database
+        .add(record(
+            "Computer",
+            "Bob",
+            "2024.03.02",
+            GameResult::Draw,
+            "B90",
+        ))
+        .unwrap();
+    database
+        .add(record(
+            "Bob",
+            "Alice",
+            "2025.06.30",
+            GameResult::BlackWins,
+            "B12",
+        ))
+        .unwrap();
+
+    let ids = |filter: GameFilter| -> Vec<u64> {
+        database
+            .search(&filter)
+            .iter()
+            .map(|game| game.id)
+            .collect()
+    };
+    assert_eq!(ids(GameFilter::default()), vec![3, 2, 1]);
+    assert_eq!(
+        ids(GameFilter {
+            player: "alice".to_string(),
+            ..Default::default()
+        }),
+        vec![3, 1]
+    );
+    assert_eq!(
+        ids(GameFilter {
+            result: Some(GameResult::Draw),
+            ..Default::default()
+        }),
+        vec![2]
+    );
+    assert_eq!(
+        ids(GameFilter {
+            opening: "B".to_string(),
+            ..Default::default()
+        }),
+        vec![3, 2]
+    );
+    assert_eq!(
+        ids(GameFilter {
+            from: "2024-02-01".to_string(),
+            to: "2024".to_string(),
+            ..Default::default()
+        }),
+        vec![2]
+    );
+}
+
+#[test]
+fn replays_stored_games() {
+    let folder = TempDir::new("replay");
+    let mut app = new_game(&folder);
+    play_moves(&mut app, &["d4", "d5", "c4", "e6"]);
+    resign(&mut app, PieceColor::White);
+    let game = app.world.get_resource::<GameDatabase>().unwrap().games[0].clone();
+
+    let mut replay = new_game(&folder);
+    send(&mut replay, SaveAction::Replay(game.saved_game()));
+    replay.update();
+    replay.update();
+
+    assert_eq!(sans(&replay), vec!["d4", "d5", "c4", "e6"]);
+    assert!(replay.world.get_resource::<GameOver>().unwrap().0.is_some());
+    assert_eq!(
+        replay.world.get_resource::<HistoryView>().unwrap().ply,
+        Some(0)
+    );
+    // Replaying a game doesn't store it again
+    assert_eq!(
+        replay
+            .world
+            .get_resource::<GameDatabase>()
+            .unwrap()
+            .games
+            .len(),
+        1
+    );
+}
+
+fn position_after(moves: &[&str]) -> Position {
+    let mut position = Position::from_fen(STARTING_FEN).unwrap();
+    for input in moves {
+        let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+            .unwrap_or_else(|error| panic!("{}: {}", input, error));
+        position.play(&chess_move);
+    }
+    position
+}
+
+#[test]
+fn hashes_positions_whatever_the_move_order() {
+    let queens_gambit = position_after(&["d4", "d5", "c4", "e6"]);
+    let english = position_after(&["c4", "e6", "d4", "d5"]);
+    assert_eq!(queens_gambit.zobrist(), english.zobrist());
+    assert!(queens_gambit.zobrist() != position_after(&["d4", "d5", "c4"]).zobrist());
+
+    // 1. e4 leaves an en passant square no black pawn can use
+    let king_pawn = position_after(&["e4"]);
+    let from_fen =
+        Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+    assert_eq!(king_pawn.zobrist(), from_fen.zobrist());
+}
+
+#[test]
+fn explores_positions_reached_by_transposition() {
+    let folder = TempDir::new("explore");
+    let mut first = new_game(&folder);
+    play_moves(&mut first, &["d4", "d5", "c4", "e6", "Nc3"]);
+    resign(&mut first, PieceColor::White);
+    let mut second = new_game(&folder);
+    play_moves(&mut second, &["c4", "e6", "d4", "d5", "Nf3"]);
+    resign(&mut second, PieceColor::Black);
+    let mut unrelated = new_game(&folder);
+    play_moves(&mut unrelated, &["e4", "e5"]);
+    resign(&mut unrelated, PieceColor::Black);
+
+    let database = unrelated.world.get_resource::<GameDatabase>().unwrap();
+    let hash = position_after(&["d4", "d5", "c4", "e6"]).zobrist();
+    let games = database.search(&GameFilter {
+        position: Some(hash),
+        ..Default::default()
+    });
+    assert_eq!(games.len(), 2);
+
+    let exploration = Exploration::of_position(&games, hash);
+    assert_eq!(exploration.continuations.len(), 2);
+    for continuation in exploration.continuations.iter() {
+        let expected = match continuation.san.as_deref() {
+            Some("Nc3") => (0, 1),
+            Some("Nf3") => (1, 0),
+            _ => panic!("Unexpected continuation"),
+        };
+        assert_eq!((continuation.white_wins, continuation.black_wins), expected);
+    }
+}
+
+#[test]
+fn explores_material_signatures() {
+    let mut game = record("Alice", "Bob", "2024.01.15", GameResult::Draw, "A00");
+    game.eco = None;
+    game.opening = None;
+    // Black has the extra pawn here
+    game.start = Some("r6R/8/4k3/8/3K4/4p3/8/8 w - - 0 1".to_string());
+    game.moves = vec!["Rh6+".to_string(), "Kd7".to_string()];
+    game.index_positions().unwrap();
+    assert_eq!(game.final_fen, "r7/3k4/7R/8/3K4/4p3/8/8 w - - 2 2");
+
+    let signature = MaterialSignature::parse("R+P vs R").unwrap();
+    let material = Material::from_key(game.material[0]);
+    assert!(signature.matches(&material) == Some(PieceColor::Black));
+    assert!(MaterialSignature::parse("KQ vs K")
+        .unwrap()
+        .matches(&material)
+        .is_none());
+
+    let exploration = Exploration::of_material(&[&game], &signature);
+    assert_eq!(exploration.games, vec![game.id]);
+    assert_eq!(exploration.continuations[0].san.as_deref(), Some("Rh6+"));
+    assert!(MaterialSignature::parse("R+X vs R").is_err());
+}
@@ -0,0 +1,224 @@
+mod common;
+
+use bevy::prelude::*;
+
+use rust_chess::{
+    board::{PlayerTurn, SelectedSquare, Square},
+    check::Check,
+    engine::SearchLimits,
+    game::{DrawOffer, GameAction, GameOver, GameResult, PlayerKind, Players, Termination},
+    headless::play_move,
+    pieces::*,
+};
+
+use common::{play_moves, sans, send, start};
+
+/// A headless app with the starting position set up
+fn new_game() -> App {
+    start(|_| ())
+}
+
+/// Hand `color` to the built-in engine, searching a ply deep to keep tests quick
+fn seat_engine(app: &mut App, color: PieceColor) {
+    let limits = SearchLimits {
+        depth: 1,
+        ..Default::default()
+    };
+    let mut players = app.world.get_resource_mut::<Players>().unwrap();
+    match color {
+        PieceColor::White => {
+            players.white = PlayerKind::Computer;
+            players.white_engine = limits;
+        }
+        PieceColor::Black => {
+            players.black = PlayerKind::Computer;
+            players.black_engine = limits;
+        }
+    }
+}
+
+fn pieces(app: &mut App) -> Vec<Piece> {
+    app.world
+        .query::<&Piece>()
+        .iter(&app.world)
+        .cloned()
+        .collect()
+}
+
+fn piece_at(app: &mut App, name: &str) -> Option<Piece> {
+    let position = parse_square(name).unwrap();
+    pieces(app)
+        .into_iter()
+        .find(|piece| (piece.x, piece.y) == position)
+}
+
+fn select(app: &mut App, name: &str) {
+    let position = parse_square(name).unwrap();
+    let square = app
+        .world
+        .query::<(Entity, &Square)>()
+        .iter(&app.world)
+        .find(|(_, square)| (square.x, square.y) == position)
+        .map(|(entity, _)| entity);
+    app.world
+        .get_resource_mut::<SelectedSquare>()
+        .unwrap()
+        .entity = square;
+    app.update();
+}
+
+#[test]
+fn sets_up_the_board_without_a_window() {
+    let mut app = new_game();
+
+    let squares = app.world.query::<&Square>().iter(&app.world).count();
+    assert_eq!(squares, 64);
+    assert_eq!(pieces(&mut app).len(), 32);
+    let king = piece_at(&mut app, "e1").unwrap();
+    assert!(king.piece_type == PieceType::King && king.color == PieceColor::White);
+    assert!(app.world.get_resource::<PlayerTurn>().unwrap().0 == PieceColor::White);
+}
+
+#[test]
+fn plays_scripted_moves() {
+    let mut app = new_game();
+    play_moves(&mut app, &["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+
+    assert_eq!(sans(&app), vec!["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+    assert!(piece_at(&mut app, "e2").is_none());
+    assert!(piece_at(&mut app, "b5").unwrap().piece_type == PieceType::Bishop);
+    assert!(app.world.get_resource::<PlayerTurn>().unwrap().0 == PieceColor::Black);
+}
+
+#[test]
+fn rejects_illegal_moves() {
+    let mut app = new_game();
+
+    assert!(matches!(
+        play_move(&mut app, "e5"),
+        Err(MoveParseError::Illegal)
+    ));
+    assert!(sans(&app).is_empty());
+}
+
+#[test]
+fn moves_pieces_by_selecting_squares() {
+    let mut app = new_game();
+    select(&mut app, "g1");
+    select(&mut app, "f3");
+
+    assert!(piece_at(&mut app, "g1").is_none());
+    assert!(piece_at(&mut app, "f3").unwrap().piece_type == PieceType::Knight);
+    assert_eq!(sans(&app), vec!["Nf3"]);
+}
+
+#[test]
+fn keeps_captured_pieces_off_the_board() {
+    let mut app = new_game();
+    play_moves(&mut app, &["e4", "d5", "exd5"]);
+
+    assert_eq!(pieces(&mut app).len(), 31);
+    let captured: Vec<PieceType> = app
+        .world
+        .query::<&Captured>()
+        .iter(&app.world)
+        .map(|captured| captured.piece_type)
+        .collect();
+    assert!(captured == vec![PieceType::Pawn]);
+}
+
+#[test]
+fn castles_and_takes_en_passant() {
+    let mut app = new_game();
+    play_moves(
+        &mut app,
+        &[
+            "e4", "a6", "e5", "d5", "exd6", "a5", "Nf3", "a4", "Bc4", "a3", "O-O",
+        ],
+    );
+
+    assert!(piece_at(&mut app, "d5").is_none());
+    assert!(piece_at(&mut app, "g1").unwrap().piece_type == PieceType::King);
+    assert!(piece_at(&mut app, "f1").unwrap().piece_type == PieceType::Rook);
+}
+
+#[test]
+fn detects_checkmate() {
+    let mut app = new_game();
+    play_moves(&mut app, &["f3", "e5", "g4", "Qh4"]);
+
+    let check = app.world.get_resource::<Check>().unwrap();
+    assert!(check.is_check && check.is_checkmate);
+    assert_eq!(sans(&app).last().unwrap(), "Qh4#");
+
+    let outcome = app.world.get_resource::<GameOver>().unwrap().0.unwrap();
+    assert!(outcome.result == GameResult::BlackWins);
+    assert!(outcome.termination == Termination::Checkmate);
+}
+
+#[test]
+fn draws_by_threefold_repetition() {
+    let mut app = new_game();
+    play_moves(&mut app, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"]);
+    assert!(app.world.get_resource::<GameOver>().unwrap().0.is_none());
+
+    play_moves(&mut app, &["Ng8"]);
+    let outcome = app.world.get_resource::<GameOver>().unwrap().0.unwrap();
+    assert!(outcome.result == GameResult::Draw);
+    assert!(outcome.termination == Termination::Repetition);
+}
+
+#[test]
+fn ends_the_game_on_resignation() {
+    let mut app = new_game();
+    play_moves(&mut app, &["d4"]);
+    send(&mut app, GameAction::Resign(PieceColor::Black));
+    app.update();
+
+    let outcome = app.world.get_resource::<GameOver>().unwrap().0.unwrap();
+    assert!(outcome.result == GameResult::WhiteWins);
+    assert!(outcome.termination == Termination::Resignation);
+}
+
+#[test]
+fn the_engine_plays_its_side() {
+    let mut app = new_game();
+    seat_engine(&mut app, PieceColor::Black);
+    play_moves(&mut app, &["e4"]);
+    for _ in 0..3 {
+        app.update();
+    }
+
+    assert_eq!(sans(&app).len(), 2);
+    assert!(app.world.get_resource::<PlayerTurn>().unwrap().0 == PieceColor::White);
+}
+
+#[test]
+fn the_engine_answers_draw_offers_from_its_search() {
+    // Black has given away the queen and takes the draw
+    let mut behind = new_game();
+    play_moves(&mut behind, &["e4", "e5", "Nf3", "Qh4", "Nxh4", "Nc6"]);
+    seat_engine(&mut behind, PieceColor::Black);
+    send(&mut behind, GameAction::OfferDraw(PieceColor::White));
+    for _ in 0..3 {
+        behind.update();
+    }
+    let outcome = behind.world.get_resource::<GameOver>().unwrap().0.unwrap();
+    assert!(outcome.termination == Termination::DrawAgreement);
+
+    // White has given away the queen, so black plays on
+    let mut ahead = new_game();
+    play_moves(&mut ahead, &["e4", "e5", "Qh5", "Nc6", "Qxf7+", "Kxf7"]);
+    seat_engine(&mut ahead, PieceColor::Black);
+    send(&mut ahead, GameAction::OfferDraw(PieceColor::White));
+    for _ in 0..3 {
+        ahead.update();
+    }
+    assert!(ahead.world.get_resource::<GameOver>().unwrap().0.is_none());
+    assert!(ahead
+        .world
+        .get_resource::<DrawOffer>()
+        .unwrap()
+        .offered_by
+        .is_none());
+}
@@ -0,0 +1,425 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+use rust_chess::{
+    board::{SelectedPiece, SelectedSquare, Square},
+    clock::{GameClock, TimeControl},
+    game::{ChatMessage, DrawOffer, GameAction, GameOver, GameResult, PlayerKind, Players},
+    headless::play_move,
+    network::*,
+    pieces::*,
+};
+
+use common::{network, sans, send, TIMEOUT};
+
+fn start(role: NetworkRole, clock: Option<TimeControl>) -> App {
+    common::start(|app| {
+        app.insert_resource(role)
+            .insert_resource(GameClock::new(clock))
+            .add_plugin(NetworkPlugin);
+    })
+}
+
+fn host() -> App {
+    start(
+        NetworkRole::Host {
+            port: 0,
+            color: PieceColor::White,
+        },
+        None,
+    )
+}
+
+fn join(host: &App) -> App {
+    let port = network(host).local_address.unwrap().port();
+    start(
+        NetworkRole::Join {
+            address: format!("127.0.0.1:{}", port),
+        },
+        None,
+    )
+}
+
+/// Run both games until `done` holds for them
+fn run_until(host: &mut App, guest: &mut App, done: impl Fn(&App, &App) -> bool) {
+    common::run_until(&mut [host, guest], |apps| done(apps[0], apps[1]));
+}
+
+fn connected(host: &mut App, guest: &mut App) {
+    run_until(host, guest, |host, guest| {
+        network(host).is_connected() && network(guest).is_connected()
+    });
+}
+
+#[test]
+fn joining_player_gets_the_other_color() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    assert!(network(&guest).local_color == Some(PieceColor::Black));
+    let players = guest.world.get_resource::<Players>().unwrap();
+    assert!(players.white == PlayerKind::Remote);
+    assert!(players.black == PlayerKind::Human);
+    let players = host.world.get_resource::<Players>().unwrap();
+    assert!(players.white == PlayerKind::Human);
+    assert!(players.black == PlayerKind::Remote);
+}
+
+#[test]
+fn moves_cross_over() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    assert!(play_move(&mut host, "e4").is_ok());
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 1);
+    assert!(play_move(&mut guest, "e5").is_ok());
+    run_until(&mut host, &mut guest, |host, _| sans(host).len() == 2);
+    assert!(play_move(&mut host, "Nf3").is_ok());
+    run_until(&mut host, &mut guest, |host, guest| {
+        sans(host).len() == 3 && sans(guest).len() == 3
+    });
+
+    assert_eq!(sans(&host), vec!["e4", "e5", "Nf3"]);
+    assert_eq!(sans(&guest), sans(&host));
+}
+
+#[test]
+fn players_only_select_their_own_pieces() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    // White to move, and White is played on the other computer
+    let square = guest
+        .world
+        .query::<(Entity, &Square)>()
+        .iter(&guest.world)
+        .find(|(_, square)| (square.x, square.y) == (1, 4))
+        .map(|(entity, _)| entity)
+        .unwrap();
+    guest
+        .world
+        .get_resource_mut::<SelectedSquare>()
+        .unwrap()
+        .entity = Some(square);
+    guest.update();
+
+    assert!(guest
+        .world
+        .get_resource::<SelectedPiece>()
+        .unwrap()
+        .entity
+        .is_none());
+}
+
+#[test]
+fn draw_offers_cross_over() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    send(&mut guest, GameAction::OfferDraw(PieceColor::Black));
+    run_until(&mut host, &mut guest, |host, _| {
+        host.world.get_resource::<DrawOffer>().unwrap().offered_by == Some(PieceColor::Black)
+    });
+    send(&mut host, GameAction::AcceptDraw(PieceColor::White));
+    run_until(&mut host, &mut guest, |host, guest| {
+        let over = |app: &App| app.world.get_resource::<GameOver>().unwrap().0;
+        over(host).map(|outcome| outcome.result) == Some(GameResult::Draw)
+            && over(guest).map(|outcome| outcome.result) == Some(GameResult::Draw)
+    });
+}
+
+#[test]
+fn resignation_and_chat_cross_over() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    send(
+        &mut guest,
+        ChatMessage {
+            color: PieceColor::Black,
+            text: "Good game".to_string(),
+        },
+    );
+    run_until(&mut host, &mut guest, |host, _| {
+        let log = host.world.get_resource::<ChatLog>().unwrap();
+        log.messages.len() == 1 && log.messages[0].text == "Good game"
+    });
+
+    send(&mut host, GameAction::Resign(PieceColor::White));
+    run_until(&mut host, &mut guest, |_, guest| {
+        let outcome = guest.world.get_resource::<GameOver>().unwrap().0;
+        outcome.map(|outcome| outcome.result) == Some(GameResult::BlackWins)
+    });
+}
+
+#[test]
+fn game_carries_on_after_the_link_drops() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+    assert!(play_move(&mut host, "d4").is_ok());
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 1);
+
+    network(&guest).drop_connection();
+    assert!(play_move(&mut guest, "d5").is_ok());
+    connected(&mut host, &mut guest);
+    run_until(&mut host, &mut guest, |host, _| sans(host).len() == 2);
+    assert!(play_move(&mut host, "c4").is_ok());
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 3);
+
+    assert_eq!(sans(&guest), vec!["d4", "d5", "c4"]);
+}
+
+#[test]
+fn rejoining_player_catches_up() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+    assert!(play_move(&mut host, "e4").is_ok());
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 1);
+    assert!(play_move(&mut guest, "c5").is_ok());
+    run_until(&mut host, &mut guest, |host, _| sans(host).len() == 2);
+
+    // The link drops, and a move is made before the other player is back
+    network(&guest).drop_connection();
+    assert!(play_move(&mut host, "Nf3").is_ok());
+    connected(&mut host, &mut guest);
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 3);
+
+    assert_eq!(sans(&guest), vec!["e4", "c5", "Nf3"]);
+    assert!(network(&guest).local_color == Some(PieceColor::Black));
+}
+
+#[test]
+fn strangers_cant_take_over_a_game() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+    assert!(play_move(&mut host, "e4").is_ok());
+    run_until(&mut host, &mut guest, |_, guest| sans(guest).len() == 1);
+
+    // Someone else joins while the other player is connected
+    let mut stranger = join(&host);
+    run_until(&mut host, &mut stranger, |_, stranger| {
+        matches!(network(stranger).status, ConnectionStatus::Rejected(_))
+    });
+    assert!(sans(&stranger).is_empty());
+
+    // The game carries on with the player who joined first
+    assert!(play_move(&mut guest, "e5").is_ok());
+    run_until(&mut host, &mut guest, |host, _| sans(host).len() == 2);
+    assert!(network(&host).is_connected() && network(&guest).is_connected());
+
+    // And they're turned away after the other player's link drops, too
+    network(&guest).drop_connection();
+    let mut stranger = join(&host);
+    run_until(&mut host, &mut stranger, |_, stranger| {
+        matches!(network(stranger).status, ConnectionStatus::Rejected(_))
+    });
+    connected(&mut host, &mut guest);
+    assert_eq!(sans(&guest), vec!["e4", "e5"]);
+}
+
+#[test]
+fn returning_player_takes_over_a_stale_connection() {
+    let mut host = host();
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    // The other player comes back for the game before the old link timed out
+    let port = network(&host).local_address.unwrap().port();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let hello = Message::Hello {
+        version: PROTOCOL_VERSION,
+        game: network(&host).game,
+        your_color: None,
+        time_control: None,
+        moves: Vec::new(),
+        clock: None,
+        draw_offer: None,
+        result: None,
+        seat: None,
+        fen: None,
+    };
+    writeln!(stream, "{}", hello.encode()).unwrap();
+
+    let address = stream.local_addr().unwrap();
+    run_until(&mut host, &mut guest, |host, _| {
+        network(host).status == ConnectionStatus::Connected(address) && network(host).is_connected()
+    });
+}
+
+#[test]
+fn joining_player_takes_the_host_clock() {
+    let time_control: TimeControl = "300+2".parse().unwrap();
+    let mut host = start(
+        NetworkRole::Host {
+            port: 0,
+            color: PieceColor::Black,
+        },
+        Some(time_control),
+    );
+    let mut guest = join(&host);
+    connected(&mut host, &mut guest);
+
+    let clock = |app: &App| {
+        let clock = app.world.get_resource::<GameClock>().unwrap();
+        (clock.time_control, clock.white, clock.black)
+    };
+    assert!(network(&guest).local_color == Some(PieceColor::White));
+    assert!(clock(&guest).0 == Some(time_control));
+
+    assert!(play_move(&mut guest, "e4").is_ok());
+    run_until(&mut host, &mut guest, |host, _| sans(host).len() == 1);
+    // One more frame for the reported time to be set once the move is in
+    host.update();
+    let (_, host_white, _) = clock(&host);
+    let (_, guest_white, _) = clock(&guest);
+    assert!(host_white > Duration::from_secs(300));
+    assert!(host_white <= guest_white + Duration::from_millis(100));
+}
+
+#[test]
+fn other_protocol_versions_are_turned_away() {
+    let mut host = host();
+    let port = network(&host).local_address.unwrap().port();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(20)))
+        .unwrap();
+    writeln!(stream, "{{\"type\":\"hello\",\"version\":999}}").unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let start = Instant::now();
+    let reason = loop {
+        assert!(start.elapsed() < TIMEOUT, "Never turned away");
+        host.update();
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_ok() {
+            if let Ok(Message::Reject { reason }) = Message::decode(&line) {
+                break reason;
+            }
+        }
+    };
+    assert!(reason.contains("999"));
+}
+
+#[test]
+fn messages_from_newer_versions_are_skipped() {
+    let hello = Message::decode("{\"type\":\"hello\",\"version\":1}").unwrap();
+    assert!(matches!(hello, Message::Hello { ref moves, .. } if moves.is_empty()));
+
+    let message = Message::Move {
+        ply: 0,
+        uci: "e2e4".to_string(),
+        clock: None,
+        fen: None,
+    };
+    assert!(Message::decode(&message.encode()).unwrap() == message);
+    assert!(Message::decode("{\"type\":\"takeback\"}").is_err());
+}
+
+/// Run the host until a message `wanted` picks out arrives on `reader`
+fn expect_message<T>(
+    host: &mut App,
+    reader: &mut BufReader<TcpStream>,
+    wanted: impl Fn(Message) -> Option<T>,
+) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "The message never came");
+        host.update();
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_ok() {
+            if let Some(found) = Message::decode(&line).ok().and_then(&wanted) {
+                return found;
+            }
+        }
+    }
+}
+
+#[test]
+fn illegal_moves_from_the_other_side_ask_for_the_game_again() {
+    let mut host = host();
+    let port = network(&host).local_address.unwrap().port();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(20)))
+        .unwrap();
+    let game = network(&host).game;
+    let hello = |moves: &[&str]| Message::Hello {
+        version: PROTOCOL_VERSION,
+        game,
+        your_color: None,
+        time_control: None,
+        moves: moves.iter().map(|uci| uci.to_string()).collect(),
+        clock: None,
+        draw_offer: None,
+        result: None,
+        seat: None,
+        fen: None,
+    };
+    writeln!(stream, "{}", hello(&[]).encode()).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    expect_message(&mut host, &mut reader, |message| match message {
+        Message::Hello { .. } => Some(()),
+        _ => None,
+    });
+
+    assert!(play_move(&mut host, "e4").is_ok());
+    let illegal = Message::Move {
+        ply: 1,
+        uci: "e7e4".to_string(),
+        clock: None,
+        fen: None,
+    };
+    writeln!(stream, "{}", illegal.encode()).unwrap();
+    let reason = expect_message(&mut host, &mut reader, |message| match message {
+        Message::Error { reason } => Some(reason),
+        _ => None,
+    });
+    assert!(reason.contains("e7e4"));
+    expect_message(&mut host, &mut reader, |message| match message {
+        Message::GetState => Some(()),
+        _ => None,
+    });
+    assert_eq!(sans(&host), vec!["e4"]);
+
+    // The game sent again still has the move, so the games have gone apart
+    writeln!(stream, "{}", hello(&["e2e4", "e7e4"]).encode()).unwrap();
+    expect_message(&mut host, &mut reader, |message| match message {
+        Message::Reject { .. } => Some(()),
+        _ => None,
+    });
+    assert_eq!(sans(&host), vec!["e4"]);
+}
+
+#[test]
+fn hosting_on_a_port_in_use_says_so() {
+    let taken = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let host = start(
+        NetworkRole::Host {
+            port,
+            color: PieceColor::White,
+        },
+        None,
+    );
+
+    match &network(&host).status {
+        ConnectionStatus::Rejected(reason) => assert!(reason.contains(&port.to_string())),
+        _ => panic!("Hosting on a port in use went ahead"),
+    }
+}
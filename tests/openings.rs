@@ -0,0 +1,112 @@
+mod common;
+
+use rust_chess::{
+    history::MoveHistory,
+    openings::{CurrentOpening, Openings},
+    pieces::*,
+};
+
+use common::{play_moves, start};
+
+#[test]
+fn bundled_openings_are_legal_lines() {
+    for opening in Openings::default().openings.iter() {
+        let mut position = Position::from_fen(STARTING_FEN).unwrap();
+        for input in opening.moves.iter() {
+            let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+                .unwrap_or_else(|error| panic!("{} {}: {}", opening.eco, opening.name, error));
+            // The table is meant to read like the game's own SAN
+            assert_eq!(
+                &san(&chess_move, &position.pieces, &position.state),
+                input,
+                "{} {}",
+                opening.eco,
+                opening.name
+            );
+            position.play(&chess_move);
+        }
+    }
+}
+
+#[test]
+fn covers_every_eco_code() {
+    let openings = Openings::default();
+    for volume in ['A', 'B', 'C', 'D', 'E'].iter() {
+        for number in 0..100 {
+            let eco = format!("{}{:02}", volume, number);
+            assert!(
+                openings.openings.iter().any(|opening| opening.eco == eco),
+                "No opening under {}",
+                eco
+            );
+        }
+    }
+}
+
+fn hashes(moves: &[&str]) -> Vec<u64> {
+    let mut position = Position::from_fen(STARTING_FEN).unwrap();
+    let mut hashes = vec![position.zobrist()];
+    for input in moves {
+        let chess_move = parse_move(input, position.turn, &position.pieces, &position.state)
+            .unwrap_or_else(|error| panic!("{} can't be played: {}", input, error));
+        position.play(&chess_move);
+        hashes.push(position.zobrist());
+    }
+    hashes
+}
+
+#[test]
+fn names_the_last_named_position() {
+    let openings = Openings::default();
+    let game = hashes(&[
+        "e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "Be3", "e5",
+    ]);
+    let opening = openings.classify(&game).unwrap();
+    assert_eq!(
+        (opening.eco.as_str(), opening.name.as_str()),
+        ("B90", "Sicilian Defense: Najdorf Variation, English Attack")
+    );
+
+    // Nothing named is reached from a position off the table
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    assert!(openings.classify(&[start.zobrist()]).is_none());
+}
+
+#[test]
+fn recognizes_transpositions() {
+    let openings = Openings::default();
+    // An English that turns into a Queen's Gambit Declined
+    let game = hashes(&["c4", "e6", "d4", "d5", "Nc3", "Nf6"]);
+    let opening = openings.classify(&game).unwrap();
+    assert_eq!(
+        (opening.eco.as_str(), opening.name.as_str()),
+        ("D35", "Queen's Gambit Declined: Normal Defense")
+    );
+}
+
+#[test]
+fn follows_the_game_on_the_board() {
+    let mut app = start(|_| ());
+    let current = |app: &bevy::prelude::App| {
+        app.world
+            .get_resource::<CurrentOpening>()
+            .unwrap()
+            .opening
+            .clone()
+    };
+    assert!(current(&app).is_none());
+
+    play_moves(&mut app, &["e4", "e5", "Nf3", "Nc6", "Bc4"]);
+    app.update();
+    let opening = current(&app).unwrap();
+    assert_eq!(opening.name, "Italian Game");
+
+    // The name stays once the game leaves the table
+    play_moves(&mut app, &["h6"]);
+    app.update();
+    assert!(current(&app).unwrap() == opening);
+
+    let history = app.world.get_resource::<MoveHistory>().unwrap();
+    let pgn = history.to_pgn(("White", "Black"), None, Some(&opening));
+    assert!(pgn.contains("[ECO \"C50\"]\n[Opening \"Italian Game\"]\n"));
+}
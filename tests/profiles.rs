@@ -0,0 +1,198 @@
+mod common;
+
+use rust_chess::{
+    database::{DatabaseFile, DatabasePlugin, GameDatabase},
+    game::{GameAction, GameResult, PlayerKind, Players},
+    pieces::PieceColor,
+    profiles::*,
+    save::SavePlugin,
+};
+
+use common::{play_moves, send, start, TempDir};
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} isn't within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn rates_as_in_the_glicko2_paper() {
+    let player = Rating {
+        rating: 1500.,
+        deviation: 200.,
+        volatility: 0.06,
+    };
+    let opponent = |rating, deviation| Rating {
+        rating,
+        deviation,
+        volatility: 0.06,
+    };
+    let updated = player.update(&[
+        (opponent(1400., 30.), 1.),
+        (opponent(1550., 100.), 0.),
+        (opponent(1700., 300.), 0.),
+    ]);
+    assert_close(updated.rating, 1464.06, 0.01);
+    assert_close(updated.deviation, 151.52, 0.01);
+    assert_close(updated.volatility, 0.05999, 0.00001);
+}
+
+#[test]
+fn keeps_engine_profiles_at_every_depth() {
+    let folder = TempDir::new("profiles_defaults");
+    let book = ProfileBook::open(&folder.join("profiles.ron")).unwrap();
+    assert!(book.get(DEFAULT_PLAYER).is_some());
+    for depth in ENGINE_DEPTHS {
+        let profile = book.get(&engine_profile_name(depth)).unwrap();
+        assert_eq!(profile.engine_depth, Some(depth));
+    }
+}
+
+#[test]
+fn rates_games_and_keeps_stats_by_color() {
+    let folder = TempDir::new("profiles_rate");
+    let path = folder.join("profiles.ron");
+    let mut book = ProfileBook::open(&path).unwrap();
+    let engine = engine_profile_name(1);
+    book.record_game(
+        (DEFAULT_PLAYER, &engine),
+        GameResult::WhiteWins,
+        "2024.05.17",
+    )
+    .unwrap();
+    book.record_game((&engine, DEFAULT_PLAYER), GameResult::Draw, "2024.05.18")
+        .unwrap();
+    book.write().unwrap();
+    assert!(book
+        .record_game(
+            (DEFAULT_PLAYER, DEFAULT_PLAYER),
+            GameResult::Draw,
+            "2024.05.18"
+        )
+        .is_err());
+
+    let book = ProfileBook::open(&path).unwrap();
+    let player = book.get(DEFAULT_PLAYER).unwrap();
+    assert_eq!(player.games(), 2);
+    assert!(
+        player.as_white
+            == Tally {
+                wins: 1,
+                draws: 0,
+                losses: 0
+            }
+    );
+    assert!(
+        player.as_black
+            == Tally {
+                wins: 0,
+                draws: 1,
+                losses: 0
+            }
+    );
+    assert!(player.rating.rating > 1500.);
+    assert!(player.rating.deviation < 350.);
+
+    let engine = book.get(&engine).unwrap();
+    assert!(engine.rating.rating < 1500.);
+    assert_eq!(engine.history.len(), 2);
+    assert_eq!(engine.history[0].opponent, DEFAULT_PLAYER);
+    assert!(engine.history[0].color == PieceColor::Black);
+    assert!(engine.history[1].result == GameResult::Draw);
+}
+
+#[test]
+fn leaves_a_file_that_cannot_be_read_as_it_is() {
+    let folder = TempDir::new("profiles_unreadable");
+    let path = folder.join("profiles.ron");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "[(name: \"Alice\", rating: ").unwrap();
+
+    let mut book = ProfileBook::load(&path);
+    assert!(book.get(DEFAULT_PLAYER).is_some());
+    assert!(book
+        .record_game(
+            (DEFAULT_PLAYER, DEFAULT_GUEST),
+            GameResult::WhiteWins,
+            "2024.05.17"
+        )
+        .is_err());
+    assert_eq!(book.get(DEFAULT_PLAYER).unwrap().games(), 0);
+    assert!(book.write().is_err());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[(name: \"Alice\", rating: "
+    );
+}
+
+#[test]
+fn names_the_engine_by_the_depth_it_plays_at() {
+    let mut players = Players {
+        black: PlayerKind::Computer,
+        ..Default::default()
+    };
+    players.black_engine.depth = 1;
+    let seats = Seats::default();
+    assert_eq!(
+        seats.name(PieceColor::Black, &players),
+        engine_profile_name(1)
+    );
+    assert_eq!(seats.name(PieceColor::White, &players), DEFAULT_PLAYER);
+}
+
+#[test]
+fn rates_finished_games_under_the_seated_profiles() {
+    let folder = TempDir::new("profiles_seats");
+    let file = DatabaseFile {
+        path: folder.join("games.jsonl"),
+    };
+    let profiles = ProfileFile {
+        path: folder.join("profiles.ron"),
+    };
+    let mut app = start(|app| {
+        app.insert_resource(file)
+            .insert_resource(profiles)
+            .insert_resource(Seats {
+                white: Some("Alice".to_string()),
+                black: None,
+            })
+            .add_plugin(SavePlugin)
+            .add_plugin(DatabasePlugin)
+            .add_plugin(ProfilesPlugin);
+    });
+
+    play_moves(&mut app, &["e4", "e5", "Qh5", "Nc6"]);
+    send(&mut app, GameAction::Resign(PieceColor::Black));
+    app.update();
+    app.update();
+
+    let database = app.world.get_resource::<GameDatabase>().unwrap();
+    assert_eq!(database.games.len(), 1);
+    assert_eq!(database.games[0].white, "Alice");
+    assert_eq!(database.games[0].black, DEFAULT_GUEST);
+
+    let book = app.world.get_resource::<ProfileBook>().unwrap();
+    let alice = book.get("Alice").unwrap();
+    assert!(
+        alice.as_white
+            == Tally {
+                wins: 1,
+                draws: 0,
+                losses: 0
+            }
+    );
+    assert_eq!(book.get(DEFAULT_GUEST).unwrap().as_black.losses, 1);
+    // Written for the next start
+    let reopened = ProfileBook::open(&book.path).unwrap();
+    assert_eq!(reopened.get("Alice").unwrap().games(), 1);
+
+    // Rated once however long the game stays over
+    app.update();
+    let book = app.world.get_resource::<ProfileBook>().unwrap();
+    assert_eq!(book.get("Alice").unwrap().games(), 1);
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use bevy::{app::AppExit, prelude::*};
+
+use rust_chess::{
+    board::PlayerTurn,
+    clock::{GameClock, TimeControl},
+    engine::SearchLimits,
+    game::{GameAction, GameOver, PlayerKind, Players},
+    pieces::*,
+    save::{SaveAction, SaveFiles, SavePlugin, SaveStatus, SavedGame},
+};
+
+use common::{play_moves, sans, send, start, TempDir};
+
+/// A headless app saving to files in `folder`, on a 5+2 clock
+fn new_game(folder: &TempDir) -> App {
+    let files = SaveFiles {
+        game: folder.join("game.ron"),
+        autosave: folder.join("autosave.ron"),
+    };
+    start(|app| {
+        app.insert_resource(files)
+            .insert_resource(GameClock::new(Some(TimeControl {
+                base: Duration::from_secs(300),
+                increment: Duration::from_secs(2),
+            })))
+            .add_plugin(SavePlugin);
+    })
+}
+
+fn save_action(app: &mut App, action: SaveAction) {
+    send(app, action);
+    app.update();
+    app.update();
+}
+
+fn game_path(app: &App) -> PathBuf {
+    app.world.get_resource::<SaveFiles>().unwrap().game.clone()
+}
+
+#[test]
+fn resumes_a_saved_game() {
+    let folder = TempDir::new("resume");
+    let mut app = new_game(&folder);
+    play_moves(
+        &mut app,
+        &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6"],
+    );
+    let path = game_path(&app);
+    save_action(&mut app, SaveAction::Save(path.clone()));
+    let saved = SavedGame::read(&path).unwrap();
+
+    let mut resumed = new_game(&folder);
+    save_action(&mut resumed, SaveAction::Load(path));
+    assert_eq!(sans(&resumed), sans(&app));
+    assert!(resumed.world.get_resource::<PlayerTurn>().unwrap().0 == PieceColor::White);
+    // Only White's clock has run since the game was loaded
+    let clock = resumed.world.get_resource::<GameClock>().unwrap();
+    assert_eq!(clock.black, saved.black_clock);
+    assert!(clock.white <= saved.white_clock);
+    assert!(clock.white > saved.white_clock - Duration::from_secs(1));
+    let on_board = resumed.world.query::<&Piece>().iter(&resumed.world).count();
+    let taken = resumed
+        .world
+        .query::<&Captured>()
+        .iter(&resumed.world)
+        .count();
+    assert_eq!((on_board, taken), (30, 2));
+
+    // White still has the right to castle
+    play_moves(&mut resumed, &["O-O"]);
+    assert_eq!(sans(&resumed).last().unwrap(), "O-O");
+}
+
+#[test]
+fn keeps_the_engine_settings_of_each_side() {
+    let folder = TempDir::new("engine");
+    let mut app = new_game(&folder);
+    let limits = SearchLimits {
+        depth: 1,
+        quiescence: false,
+        ..Default::default()
+    };
+    {
+        let mut players = app.world.get_resource_mut::<Players>().unwrap();
+        players.black = PlayerKind::Computer;
+        players.black_engine = limits;
+    }
+    let path = game_path(&app);
+    save_action(&mut app, SaveAction::Save(path.clone()));
+    let saved = SavedGame::read(&path).unwrap();
+    assert!(saved.white_engine.is_none());
+    assert!(saved.black_engine == Some(limits));
+
+    let mut resumed = new_game(&folder);
+    save_action(&mut resumed, SaveAction::Load(path));
+    let players = resumed.world.get_resource::<Players>().unwrap();
+    assert!(players.black == PlayerKind::Computer);
+    assert!(players.engine(PieceColor::Black) == Some(limits));
+    assert!(players.engine(PieceColor::White).is_none());
+}
+
+#[test]
+fn autosaves_an_unfinished_game_on_exit() {
+    let folder = TempDir::new("autosave");
+    let mut app = new_game(&folder);
+    play_moves(&mut app, &["d4", "d5"]);
+    send(&mut app, AppExit);
+    app.update();
+
+    let autosave = app
+        .world
+        .get_resource::<SaveFiles>()
+        .unwrap()
+        .autosave
+        .clone();
+    let saved = SavedGame::read(&autosave).unwrap();
+    assert_eq!(saved.moves, vec!["d2d4", "d7d5"]);
+
+    // A finished game isn't offered again
+    send(&mut app, GameAction::Resign(PieceColor::White));
+    app.update();
+    assert!(app.world.get_resource::<GameOver>().unwrap().0.is_some());
+    send(&mut app, AppExit);
+    app.update();
+    assert!(!autosave.exists());
+}
+
+#[test]
+fn keeps_the_game_when_a_save_cannot_be_read() {
+    let folder = TempDir::new("corrupt");
+    let mut app = new_game(&folder);
+    play_moves(&mut app, &["c4"]);
+    let path = game_path(&app);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "(version: 1, moves: [\"e2e5\"])").unwrap();
+
+    save_action(&mut app, SaveAction::Load(path));
+    assert_eq!(sans(&app), vec!["c4"]);
+    let status = app.world.get_resource::<SaveStatus>().unwrap();
+    assert!(status
+        .message
+        .as_ref()
+        .unwrap()
+        .starts_with("Couldn't load"));
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+use rust_chess::{
+    clock::{GameClock, TimeControl},
+    game::{GameOutcome, GameResult, Termination},
+    headless::play_move,
+    network::*,
+    pieces::PieceColor,
+    server::{Seat, Server, ServerGame, ABANDONED_GAME_EXPIRY},
+};
+
+use common::{network, run_until, sans, TIMEOUT};
+
+fn start(role: NetworkRole) -> App {
+    common::start(|app| {
+        app.insert_resource(role)
+            .insert_resource(GameClock::new(None))
+            .add_plugin(NetworkPlugin);
+    })
+}
+
+fn address(server: &Server) -> String {
+    format!("127.0.0.1:{}", server.local_address.port())
+}
+
+fn seek(server: &Server) -> App {
+    start(NetworkRole::Seek {
+        address: address(server),
+    })
+}
+
+fn seated(apps: &[&mut App]) -> bool {
+    apps.iter().all(|app| network(app).local_color.is_some())
+}
+
+/// Speaks the protocol by hand, to do what a game never would
+struct RawClient {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl RawClient {
+    fn seek(server: &Server, time_control: Option<TimeControl>) -> Self {
+        let stream = TcpStream::connect(address(server)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let mut client = Self { stream, reader };
+        client.send(Message::decode("{\"type\":\"hello\",\"version\":1}").unwrap());
+        client.send(Message::Seek { time_control });
+        client
+    }
+
+    fn send(&mut self, message: Message) {
+        self.send_line(&message.encode());
+    }
+
+    fn send_line(&mut self, line: &str) {
+        writeln!(self.stream, "{}", line).unwrap();
+    }
+
+    /// The next message besides pings, if one comes within `wait`
+    fn receive(&mut self, wait: Duration) -> Option<Message> {
+        let start = Instant::now();
+        while start.elapsed() < wait {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).is_ok() {
+                match Message::decode(&line) {
+                    Ok(Message::Ping) | Err(_) => (),
+                    Ok(message) => return Some(message),
+                }
+            }
+        }
+        None
+    }
+
+    fn expect(&mut self) -> Message {
+        self.receive(TIMEOUT).expect("Nothing came from the server")
+    }
+
+    /// The color the server seated this client with
+    fn color(&mut self) -> PieceColor {
+        match self.expect() {
+            Message::Hello {
+                your_color: Some(color),
+                ..
+            } => color,
+            _ => panic!("Expected to be seated"),
+        }
+    }
+
+    fn play(&mut self, ply: usize, uci: &str) {
+        self.send(Message::Move {
+            ply,
+            uci: uci.to_string(),
+            clock: None,
+            fen: None,
+        });
+    }
+}
+
+/// Two raw clients seated against each other, White first
+fn raw_game(server: &Server, time_control: Option<TimeControl>) -> (RawClient, RawClient) {
+    let mut first = RawClient::seek(server, time_control);
+    let mut second = RawClient::seek(server, time_control);
+    let first_color = first.color();
+    second.color();
+    match first_color {
+        PieceColor::White => (first, second),
+        PieceColor::Black => (second, first),
+    }
+}
+
+#[test]
+fn seeking_players_are_paired_and_moves_relayed() {
+    let server = Server::start(0).unwrap();
+    let mut first = seek(&server);
+    let mut second = seek(&server);
+    run_until(&mut [&mut first, &mut second], seated);
+
+    assert!(network(&first).local_color != network(&second).local_color);
+    assert!(network(&first).game == network(&second).game);
+    let (mut white, mut black) = match network(&first).local_color {
+        Some(PieceColor::White) => (first, second),
+        _ => (second, first),
+    };
+
+    assert!(play_move(&mut white, "e4").is_ok());
+    run_until(&mut [&mut white, &mut black], |apps| {
+        sans(apps[1]).len() == 1
+    });
+    assert!(play_move(&mut black, "e5").is_ok());
+    run_until(&mut [&mut white, &mut black], |apps| {
+        sans(apps[0]).len() == 2
+    });
+    assert_eq!(sans(&white), vec!["e4", "e5"]);
+}
+
+#[test]
+fn spectators_follow_the_live_game() {
+    let server = Server::start(0).unwrap();
+    let (mut white, mut black) = raw_game(&server, None);
+    white.play(0, "d2d4");
+
+    let mut watcher = start(NetworkRole::Watch {
+        address: address(&server),
+        game: None,
+    });
+    run_until(&mut [&mut watcher], |apps| sans(apps[0]).len() == 1);
+    assert!(network(&watcher).local_color.is_none());
+
+    black.play(1, "d7d5");
+    run_until(&mut [&mut watcher], |apps| sans(apps[0]).len() == 2);
+    assert_eq!(sans(&watcher), vec!["d4", "d5"]);
+}
+
+#[test]
+fn illegal_moves_are_turned_away() {
+    let server = Server::start(0).unwrap();
+    let (mut white, mut black) = raw_game(&server, None);
+
+    white.play(0, "e2e5");
+    assert!(matches!(white.expect(), Message::Error { .. }));
+    assert!(black.receive(Duration::from_millis(200)).is_none());
+
+    // The player stays on to make a legal move
+    white.play(0, "e2e4");
+    assert!(matches!(black.expect(), Message::Move { ply: 0, .. }));
+}
+
+#[test]
+fn moves_ahead_of_the_game_are_answered_with_an_error() {
+    let server = Server::start(0).unwrap();
+    let (mut white, mut black) = raw_game(&server, None);
+
+    white.play(2, "e2e4");
+    assert!(matches!(white.expect(), Message::Error { .. }));
+    assert!(black.receive(Duration::from_millis(200)).is_none());
+}
+
+#[test]
+fn malformed_messages_are_answered_with_an_error() {
+    let server = Server::start(0).unwrap();
+    let (mut white, _black) = raw_game(&server, None);
+
+    white.send_line("{\"type\":\"move\",");
+    assert!(matches!(white.expect(), Message::Error { .. }));
+    white.send_line("{\"type\":\"move\",\"uci\":\"e2e4\"}");
+    assert!(matches!(white.expect(), Message::Error { .. }));
+
+    // A message from a newer version is left alone
+    white.send_line("{\"type\":\"takeback\"}");
+    assert!(white.receive(Duration::from_millis(200)).is_none());
+}
+
+#[test]
+fn abandoned_games_expire() {
+    let seat = |client| Seat { token: 0, client };
+    let now = Instant::now();
+
+    // Players that left an unfinished game get time to come back
+    let mut game = ServerGame::new(None, seat(Some(1)), seat(None));
+    assert!(!game.has_expired(now));
+    game.white.client = None;
+    assert!(!game.has_expired(now));
+    assert!(!game.has_expired(now + ABANDONED_GAME_EXPIRY / 2));
+    assert!(game.has_expired(now + ABANDONED_GAME_EXPIRY));
+
+    // A finished game goes as soon as nobody follows it
+    let mut finished = ServerGame::new(None, seat(None), seat(Some(2)));
+    finished.spectators.push(3);
+    finished.end(GameOutcome {
+        result: GameResult::Draw,
+        termination: Termination::DrawAgreement,
+    });
+    finished.black.client = None;
+    assert!(!finished.has_expired(now));
+    finished.spectators.clear();
+    assert!(finished.has_expired(now));
+}
+
+#[test]
+fn only_equal_time_controls_are_paired() {
+    let server = Server::start(0).unwrap();
+    let blitz: TimeControl = "180+2".parse().unwrap();
+    let rapid: TimeControl = "600+5".parse().unwrap();
+    let mut first = RawClient::seek(&server, Some(blitz));
+    let mut second = RawClient::seek(&server, Some(rapid));
+    assert!(first.receive(Duration::from_millis(200)).is_none());
+
+    let mut third = RawClient::seek(&server, Some(rapid));
+    assert!(matches!(
+        second.expect(),
+        Message::Hello { time_control: Some(time_control), .. } if time_control == rapid
+    ));
+    assert!(matches!(third.expect(), Message::Hello { .. }));
+    assert!(first.receive(Duration::from_millis(200)).is_none());
+}
+
+#[test]
+fn server_ends_the_game_on_checkmate() {
+    let server = Server::start(0).unwrap();
+    let (mut white, mut black) = raw_game(&server, None);
+
+    for (ply, uci) in ["f2f3", "e7e5", "g2g4", "d8h4"].iter().enumerate() {
+        let (mover, other) = match ply % 2 {
+            0 => (&mut white, &mut black),
+            _ => (&mut black, &mut white),
+        };
+        mover.play(ply, uci);
+        assert!(matches!(other.expect(), Message::Move { .. }));
+    }
+    let game_over = loop {
+        if let message @ Message::GameOver { .. } = white.expect() {
+            break message;
+        }
+    };
+    assert!(
+        game_over
+            == Message::GameOver {
+                result: GameResult::BlackWins,
+                termination: Termination::Checkmate,
+            }
+    );
+}
+
+#[test]
+fn server_calls_the_flag() {
+    let server = Server::start(0).unwrap();
+    let (mut white, mut black) = raw_game(&server, Some("0.3".parse().unwrap()));
+
+    white.play(0, "e2e4");
+    let game_over = loop {
+        if let message @ Message::GameOver { .. } = black.expect() {
+            break message;
+        }
+    };
+    assert!(
+        game_over
+            == Message::GameOver {
+                result: GameResult::WhiteWins,
+                termination: Termination::Timeout,
+            }
+    );
+    assert!(matches!(white.expect(), Message::GameOver { .. }));
+}
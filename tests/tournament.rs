@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use rust_chess::{engine::*, game::GameResult, openings::Openings, pieces::*, tournament::*};
+
+fn builtin(depth: u32) -> EngineSpec {
+    format!("builtin,depth={}", depth).parse().unwrap()
+}
+
+#[test]
+fn fen_round_trips() {
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+    assert_eq!(Position::default().to_fen(), STARTING_FEN);
+}
+
+#[test]
+fn pgn_replays_to_the_final_position() {
+    let games = parse_pgn(
+        "[Event \"Test\"]\n\n1. e4 {best by test} e5 2. Nf3 (2. f4) Nc6 $1 3. O-O-O?? 1-0\n\n\
+         [Event \"Second\"]\n\n1. d4 d5 *\n",
+    );
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tag("Event"), Some("Test"));
+    // Long castling isn't legal there, so replaying stops with an error
+    assert!(games[0].replay().is_err());
+
+    let (position, moves) = games[1].replay().unwrap();
+    assert_eq!(moves.len(), 2);
+    // No white pawn can take on d6, so the key leaves the square out
+    assert_eq!(
+        position.key(),
+        "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq -"
+    );
+    assert_eq!(
+        position.to_fen(),
+        "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 2"
+    );
+}
+
+#[test]
+fn engine_finds_mate_in_one() {
+    let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let result = search(&position, &SearchLimits::default());
+    assert_eq!(uci(&result.best_move.unwrap()), "a1a8");
+    assert!(result.score > MATE_SCORE - 10);
+}
+
+#[test]
+fn engine_takes_a_free_queen() {
+    let position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+    let result = search(
+        &position,
+        &SearchLimits {
+            depth: 2,
+            ..SearchLimits::default()
+        },
+    );
+    assert_eq!(uci(&result.best_move.unwrap()), "d1d5");
+}
+
+#[test]
+fn even_score_means_equal_strength() {
+    let score = Score {
+        wins: 30,
+        draws: 40,
+        losses: 30,
+    };
+    assert_eq!(score.ratio(), 0.5);
+    assert!(score.elo().abs() < 1e-9);
+    assert!((score.los() - 0.5).abs() < 1e-9);
+    assert!((elo_from_ratio(ratio_from_elo(100.)) - 100.).abs() < 1e-6);
+}
+
+#[test]
+fn sprt_accepts_a_clearly_stronger_engine() {
+    let sprt: Sprt = "elo0=0,elo1=10,alpha=0.05,beta=0.05".parse().unwrap();
+    let (lower, upper) = sprt.bounds();
+    assert!(lower < 0. && upper > 0.);
+    assert!(sprt.status(&Score::default()) == SprtStatus::Continue);
+
+    let winning = Score {
+        wins: 400,
+        draws: 200,
+        losses: 100,
+    };
+    assert!(sprt.status(&winning) == SprtStatus::AcceptH1);
+    let losing = Score {
+        wins: 100,
+        draws: 200,
+        losses: 400,
+    };
+    assert!(sprt.status(&losing) == SprtStatus::AcceptH0);
+}
+
+#[test]
+fn engine_specs_parse() {
+    let spec: EngineSpec = "builtin,depth=2,quiescence=off,name=Shallow"
+        .parse()
+        .unwrap();
+    assert_eq!(spec.name, "Shallow");
+    assert!(matches!(
+        spec.kind,
+        EngineKind::BuiltIn(SearchLimits {
+            depth: 2,
+            quiescence: false,
+            ..
+        })
+    ));
+
+    let spec: EngineSpec = "uci=stockfish,option.Hash=16".parse().unwrap();
+    assert_eq!(spec.name, "stockfish");
+    assert!(matches!(spec.kind, EngineKind::Uci { ref options, .. } if options.len() == 1));
+
+    assert!("uci=".parse::<EngineSpec>().is_err());
+    assert!("builtin,depth=two".parse::<EngineSpec>().is_err());
+}
+
+#[test]
+fn games_alternate_colors_and_stop_at_the_move_limit() {
+    let config = TournamentConfig {
+        engines: [builtin(1), builtin(1)],
+        games: 4,
+        concurrency: 2,
+        openings: Vec::new(),
+        time_control: TimeControl {
+            base: Duration::from_secs(10),
+            increment: Duration::from_millis(10),
+        },
+        adjudication: Adjudication {
+            max_moves: Some(10),
+            ..Adjudication::default()
+        },
+        sprt: None,
+    };
+
+    let mut finished = Vec::new();
+    let standings = run_tournament(&config, |game, _standings| {
+        assert!(game.record.moves.len() <= 20);
+        finished.push((game.index, game.first_engine_white));
+    })
+    .unwrap();
+
+    finished.sort_unstable();
+    assert_eq!(finished, vec![(0, true), (1, false), (2, true), (3, false)]);
+    assert_eq!(standings.score.games(), 4);
+}
+
+#[test]
+fn missing_engine_fails_the_tournament() {
+    let config = TournamentConfig {
+        engines: [builtin(1), "uci=/nonexistent/engine".parse().unwrap()],
+        games: 2,
+        concurrency: 1,
+        openings: Vec::new(),
+        time_control: "1+0".parse().unwrap(),
+        adjudication: Adjudication::default(),
+        sprt: None,
+    };
+    assert!(run_tournament(&config, |_, _| ()).is_err());
+}
+
+#[test]
+fn game_records_write_pgn() {
+    let start = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let mut white = builtin(2).start().unwrap();
+    let mut black = builtin(2).start().unwrap();
+    let record = play_game(
+        white.as_mut(),
+        black.as_mut(),
+        ("White", "Black"),
+        &start,
+        &"10+0".parse().unwrap(),
+        &Adjudication::default(),
+    );
+    assert!(record.result == GameResult::WhiteWins);
+    assert_eq!(record.moves, vec!["Ra8#"]);
+
+    let pgn = record.to_pgn("Test", 1, &Openings::default());
+    assert!(pgn.contains("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]"));
+    assert!(pgn.contains("1. Ra8# "));
+    assert!(pgn.trim_end().ends_with("1-0"));
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use std::{
+    io,
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use tungstenite::{Message as Frame, WebSocket};
+
+use rust_chess::{
+    game::{GameResult, Termination},
+    network::Message,
+    pieces::PieceColor,
+    server::Server,
+};
+
+use common::TIMEOUT;
+
+/// A bot talking to the server the way a browser would
+struct Bot {
+    socket: WebSocket<TcpStream>,
+}
+
+impl Bot {
+    fn connect(server: &Server) -> Self {
+        let address = format!("127.0.0.1:{}", server.local_address.port());
+        let stream = TcpStream::connect(&address).unwrap();
+        let (socket, _) = tungstenite::client(format!("ws://{}/", address), stream).unwrap();
+        socket
+            .get_ref()
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let mut bot = Self { socket };
+        bot.send_json("{\"type\": \"hello\", \"version\": 1}");
+        bot
+    }
+
+    fn send_json(&mut self, json: &str) {
+        self.socket
+            .write_message(Frame::Text(json.to_string()))
+            .unwrap();
+    }
+
+    fn expect(&mut self) -> Message {
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < TIMEOUT, "Nothing came from the server");
+            match self.socket.read_message() {
+                Ok(Frame::Text(text)) => return Message::decode(&text).unwrap(),
+                Ok(_) => (),
+                Err(tungstenite::Error::Io(error))
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(error) => panic!("Connection failed: {}", error),
+            }
+        }
+    }
+
+    /// The game id, color and position from the server's hello
+    fn hello(&mut self) -> (u64, Option<PieceColor>, String) {
+        match self.expect() {
+            Message::Hello {
+                game: Some(game),
+                your_color,
+                fen: Some(fen),
+                ..
+            } => (game, your_color, fen),
+            _ => panic!("Expected a hello"),
+        }
+    }
+}
+
+#[test]
+fn bots_create_and_join_games() {
+    let server = Server::start(0).unwrap();
+    let mut creator = Bot::connect(&server);
+    creator.send_json("{\"type\": \"create\", \"color\": \"Black\"}");
+    let (game, color, fen) = creator.hello();
+    assert!(color == Some(PieceColor::Black));
+    assert_eq!(
+        fen,
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+
+    let mut joiner = Bot::connect(&server);
+    joiner.send_json(&format!("{{\"type\": \"join\", \"game\": {}}}", game));
+    let (joined, color, _) = joiner.hello();
+    assert_eq!(joined, game);
+    assert!(color == Some(PieceColor::White));
+    // The creator hears that someone sat down
+    assert!(creator.hello().0 == game);
+
+    joiner.send_json("{\"type\": \"move\", \"ply\": 0, \"uci\": \"e2e4\"}");
+    match creator.expect() {
+        Message::Move { ply, uci, fen, .. } => {
+            assert_eq!((ply, uci.as_str()), (0, "e2e4"));
+            assert!(fen
+                .unwrap()
+                .starts_with("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"));
+        }
+        _ => panic!("Expected the move"),
+    }
+
+    creator.send_json("{\"type\": \"get_state\"}");
+    match creator.expect() {
+        Message::Hello { moves, .. } => assert_eq!(moves, vec!["e2e4"]),
+        _ => panic!("Expected a hello"),
+    }
+}
+
+#[test]
+fn bots_hear_how_the_game_ends() {
+    let server = Server::start(0).unwrap();
+    let mut white = Bot::connect(&server);
+    white.send_json("{\"type\": \"create\", \"color\": \"White\"}");
+    let (game, _, _) = white.hello();
+    let mut black = Bot::connect(&server);
+    black.send_json(&format!("{{\"type\": \"join\", \"game\": {}}}", game));
+    black.hello();
+    white.hello();
+
+    let mut watcher = Bot::connect(&server);
+    watcher.send_json(&format!("{{\"type\": \"watch\", \"game\": {}}}", game));
+    assert!(watcher.hello().1.is_none());
+
+    white.send_json("{\"type\": \"resign\"}");
+    for bot in [&mut black, &mut watcher].iter_mut() {
+        assert!(
+            bot.expect()
+                == Message::GameOver {
+                    result: GameResult::BlackWins,
+                    termination: Termination::Resignation,
+                }
+        );
+    }
+}
+
+#[test]
+fn moves_before_anyone_joins_are_turned_away() {
+    let server = Server::start(0).unwrap();
+    let mut bot = Bot::connect(&server);
+    bot.send_json("{\"type\": \"create\", \"color\": \"White\"}");
+    bot.hello();
+
+    bot.send_json("{\"type\": \"move\", \"ply\": 0, \"uci\": \"e2e4\"}");
+    assert!(matches!(bot.expect(), Message::Error { .. }));
+}